@@ -75,6 +75,32 @@ impl GeoTransform {
         Self::new(&geo)
     }
 
+    pub fn rotation(theta_degrees: f64) -> Self {
+        let theta = theta_degrees.to_radians();
+        let geo = [theta.cos(), -theta.sin(), 0.0, theta.sin(), theta.cos(), 0.0];
+        Self::new(&geo)
+    }
+
+    /// Recovers the translation, scale, rotation (in degrees) and shear that compose this
+    /// transform, so callers can detect north-up vs. rotated datasets without inspecting the raw
+    /// matrix entries.
+    #[allow(clippy::many_single_char_names)]
+    pub fn decompose(&self) -> ((f64, f64), (f64, f64), f64, f64) {
+        let (a, b, _, d, e, _) = self.to_tuple();
+
+        let sx = a.hypot(d);
+        let (u1x, u1y) = (a / sx, d / sx);
+
+        let shear_raw = b * u1x + e * u1y;
+        let (perp_x, perp_y) = (b - shear_raw * u1x, e - shear_raw * u1y);
+        let sy = perp_y * u1x - perp_x * u1y;
+
+        let rotation = d.atan2(a).to_degrees();
+        let shear = shear_raw / sx;
+
+        ((self.xoff(), self.yoff()), (sx, sy), rotation, shear)
+    }
+
     #[allow(clippy::many_single_char_names)]
     pub fn to_tuple(&self) -> (f64, f64, f64, f64, f64, f64) {
         let a = self.geo[0];
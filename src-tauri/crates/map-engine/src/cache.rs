@@ -0,0 +1,337 @@
+//! A small, stable binary format for caching computed artifacts (band [`Histogram`]s/[`BandStats`],
+//! or pre-rendered [`Tile`] value buffers) to disk, so repeated passes over the same raster don't
+//! recompute them.
+//!
+//! Every encoded file starts with a fixed header — magic bytes, a format-version byte, an
+//! artifact-kind byte and a flags byte — followed by a body of little-endian `f64`s and counts
+//! packed as LEB128 varints, optionally wrapped in zlib compression (via `flate2`). The uncompressed layout is byte-for-byte
+//! reproducible across runs and platforms, so cached files are directly comparable; compression
+//! is an independent, opt-in wrapper around it.
+use crate::{
+    errors::MapEngineError,
+    raster::{BandStats, Histogram},
+    tiles::Tile,
+};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 4] = b"MECF";
+const FORMAT_VERSION: u8 = 1;
+
+/// Distinguishes which artifact a cache file's body decodes as, so a reader can't accidentally
+/// decode a [`Histogram`] file as a [`Tile`] buffer and vice versa.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArtifactKind {
+    Histogram = 0,
+    BandStats = 1,
+    TileBuffer = 2,
+}
+
+/// Encode a [`Histogram`] in this module's on-disk format.
+pub fn encode_histogram(histogram: &Histogram, compress: bool) -> Result<Vec<u8>, MapEngineError> {
+    encode(ArtifactKind::Histogram, encode_histogram_body(histogram), compress)
+}
+
+/// Decode a [`Histogram`] previously produced by [`encode_histogram`].
+pub fn decode_histogram(bytes: &[u8]) -> Result<Histogram, MapEngineError> {
+    decode_histogram_body(&decode(ArtifactKind::Histogram, bytes)?)
+}
+
+/// Encode [`BandStats`] (including its embedded [`Histogram`]) in this module's on-disk format.
+pub fn encode_band_stats(stats: &BandStats, compress: bool) -> Result<Vec<u8>, MapEngineError> {
+    encode(ArtifactKind::BandStats, encode_band_stats_body(stats), compress)
+}
+
+/// Decode [`BandStats`] previously produced by [`encode_band_stats`].
+pub fn decode_band_stats(bytes: &[u8]) -> Result<BandStats, MapEngineError> {
+    decode_band_stats_body(&decode(ArtifactKind::BandStats, bytes)?)
+}
+
+/// Encode an arbitrary pre-rendered tile buffer (e.g. a styled PNG or raw pixel blob), keyed by
+/// `tile`'s `(x, y, z)`, in this module's on-disk format.
+pub fn encode_tile_buffer(tile: &Tile, data: &[u8], compress: bool) -> Result<Vec<u8>, MapEngineError> {
+    encode(ArtifactKind::TileBuffer, encode_tile_buffer_body(tile, data), compress)
+}
+
+/// Decode a `(tile, buffer)` pair previously produced by [`encode_tile_buffer`].
+pub fn decode_tile_buffer(bytes: &[u8]) -> Result<(Tile, Vec<u8>), MapEngineError> {
+    decode_tile_buffer_body(&decode(ArtifactKind::TileBuffer, bytes)?)
+}
+
+/// Wrap `body` with the header (magic/version/kind/flags), compressing it first when `compress`.
+fn encode(kind: ArtifactKind, body: Vec<u8>, compress: bool) -> Result<Vec<u8>, MapEngineError> {
+    let (flags, payload): (u8, Vec<u8>) = if compress {
+        (1, compress_body(&body)?)
+    } else {
+        (0, body)
+    };
+
+    let mut out = Vec::with_capacity(payload.len() + 7);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    out.push(kind as u8);
+    out.push(flags);
+    out.extend_from_slice(&payload);
+    Ok(out)
+}
+
+/// Validate the header against `expected` and return the (decompressed, if needed) body.
+fn decode(expected: ArtifactKind, bytes: &[u8]) -> Result<Vec<u8>, MapEngineError> {
+    if bytes.len() < 7 || &bytes[0..4] != MAGIC {
+        return Err(MapEngineError::Msg("cache: not a map-engine cache file".into()));
+    }
+
+    let version = bytes[4];
+    if version != FORMAT_VERSION {
+        return Err(MapEngineError::Msg(format!(
+            "cache: unsupported format version {version}"
+        )));
+    }
+
+    let kind = bytes[5];
+    if kind != expected as u8 {
+        return Err(MapEngineError::Msg(format!(
+            "cache: expected artifact kind {}, got {kind}",
+            expected as u8
+        )));
+    }
+
+    let payload = &bytes[7..];
+    if bytes[6] & 1 != 0 {
+        decompress_body(payload)
+    } else {
+        Ok(payload.to_vec())
+    }
+}
+
+fn compress_body(body: &[u8]) -> Result<Vec<u8>, MapEngineError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    Ok(encoder.finish()?)
+}
+
+fn decompress_body(body: &[u8]) -> Result<Vec<u8>, MapEngineError> {
+    let mut decoder = ZlibDecoder::new(body);
+    let mut out = Vec::new();
+    decoder.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+fn encode_histogram_body(histogram: &Histogram) -> Vec<u8> {
+    let (lo, hi) = histogram.bounds();
+    let mut buf = Vec::with_capacity(16 + histogram.buckets().len());
+    write_f64(&mut buf, lo);
+    write_f64(&mut buf, hi);
+    write_varint(&mut buf, histogram.buckets().len() as u64);
+    for &count in histogram.buckets() {
+        write_varint(&mut buf, count);
+    }
+    buf
+}
+
+fn decode_histogram_body(bytes: &[u8]) -> Result<Histogram, MapEngineError> {
+    let mut cursor = 0;
+    let lo = read_f64(bytes, &mut cursor)?;
+    let hi = read_f64(bytes, &mut cursor)?;
+    let n_buckets = read_varint(bytes, &mut cursor)? as usize;
+    let mut buckets = Vec::with_capacity(n_buckets);
+    for _ in 0..n_buckets {
+        buckets.push(read_varint(bytes, &mut cursor)?);
+    }
+    Ok(Histogram::from_parts(lo, hi, buckets))
+}
+
+fn encode_band_stats_body(stats: &BandStats) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_option_f64(&mut buf, stats.min());
+    write_option_f64(&mut buf, stats.max());
+    write_option_f64(&mut buf, stats.mean());
+    write_option_f64(&mut buf, stats.std_dev());
+    write_varint(&mut buf, stats.count());
+    buf.extend_from_slice(&encode_histogram_body(stats.histogram()));
+    buf
+}
+
+fn decode_band_stats_body(bytes: &[u8]) -> Result<BandStats, MapEngineError> {
+    let mut cursor = 0;
+    let min = read_option_f64(bytes, &mut cursor)?;
+    let max = read_option_f64(bytes, &mut cursor)?;
+    let mean = read_option_f64(bytes, &mut cursor)?;
+    let std_dev = read_option_f64(bytes, &mut cursor)?;
+    let count = read_varint(bytes, &mut cursor)?;
+    let histogram = decode_histogram_body(&bytes[cursor..])?;
+    Ok(BandStats::from_parts(min, max, mean, std_dev, count, histogram))
+}
+
+fn encode_tile_buffer_body(tile: &Tile, data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(data.len() + 16);
+    write_varint(&mut buf, tile.x as u64);
+    write_varint(&mut buf, tile.y as u64);
+    write_varint(&mut buf, tile.z as u64);
+    write_varint(&mut buf, data.len() as u64);
+    buf.extend_from_slice(data);
+    buf
+}
+
+fn decode_tile_buffer_body(bytes: &[u8]) -> Result<(Tile, Vec<u8>), MapEngineError> {
+    let mut cursor = 0;
+    let x = read_varint(bytes, &mut cursor)? as u32;
+    let y = read_varint(bytes, &mut cursor)? as u32;
+    let z = read_varint(bytes, &mut cursor)? as u32;
+    let len = read_varint(bytes, &mut cursor)? as usize;
+    let data = bytes
+        .get(cursor..cursor + len)
+        .ok_or_else(|| MapEngineError::Msg("cache: truncated tile buffer".into()))?
+        .to_vec();
+    Ok((Tile::new(x, y, z), data))
+}
+
+fn write_f64(buf: &mut Vec<u8>, value: f64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn read_f64(bytes: &[u8], cursor: &mut usize) -> Result<f64, MapEngineError> {
+    let end = *cursor + 8;
+    let slice = bytes
+        .get(*cursor..end)
+        .ok_or_else(|| MapEngineError::Msg("cache: truncated f64".into()))?;
+    *cursor = end;
+    Ok(f64::from_le_bytes(slice.try_into().unwrap()))
+}
+
+fn write_option_f64(buf: &mut Vec<u8>, value: Option<f64>) {
+    match value {
+        Some(v) => {
+            buf.push(1);
+            write_f64(buf, v);
+        }
+        None => buf.push(0),
+    }
+}
+
+fn read_option_f64(bytes: &[u8], cursor: &mut usize) -> Result<Option<f64>, MapEngineError> {
+    let tag = *bytes
+        .get(*cursor)
+        .ok_or_else(|| MapEngineError::Msg("cache: truncated option tag".into()))?;
+    *cursor += 1;
+    if tag == 0 {
+        Ok(None)
+    } else {
+        Ok(Some(read_f64(bytes, cursor)?))
+    }
+}
+
+/// Unsigned LEB128: 7 payload bits per byte, continuation flagged by the high bit.
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        buf.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Decode an unsigned LEB128 varint, saturating the accumulated total instead of panicking if a
+/// corrupt/adversarial input encodes more than 64 bits worth of payload.
+fn read_varint(bytes: &[u8], cursor: &mut usize) -> Result<u64, MapEngineError> {
+    let mut result: u64 = 0;
+    let mut shift: u32 = 0;
+    loop {
+        let byte = *bytes
+            .get(*cursor)
+            .ok_or_else(|| MapEngineError::Msg("cache: truncated varint".into()))?;
+        *cursor += 1;
+
+        let payload = (byte & 0x7f) as u64;
+        let shifted = if payload == 0 {
+            0
+        } else {
+            payload
+                .checked_shl(shift)
+                .filter(|v| v >> shift == payload)
+                .unwrap_or(u64::MAX)
+        };
+        result = result.saturating_add(shifted);
+
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift = shift.saturating_add(7);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_histogram() -> Histogram {
+        Histogram::from_parts(0.0, 10.0, vec![1, 2, 3, 4, 5])
+    }
+
+    #[test]
+    fn test_histogram_round_trips_uncompressed() {
+        let histogram = sample_histogram();
+        let encoded = encode_histogram(&histogram, false).unwrap();
+        assert_eq!(&encoded[0..4], MAGIC);
+        assert_eq!(decode_histogram(&encoded).unwrap(), histogram);
+    }
+
+    #[test]
+    fn test_histogram_round_trips_compressed() {
+        let histogram = sample_histogram();
+        let encoded = encode_histogram(&histogram, true).unwrap();
+        assert_eq!(decode_histogram(&encoded).unwrap(), histogram);
+    }
+
+    #[test]
+    fn test_uncompressed_encoding_is_reproducible() {
+        let histogram = sample_histogram();
+        assert_eq!(
+            encode_histogram(&histogram, false).unwrap(),
+            encode_histogram(&histogram, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_band_stats_round_trips() {
+        let stats = BandStats::from_parts(Some(1.0), Some(9.0), Some(5.0), Some(2.5), 42, sample_histogram());
+        let encoded = encode_band_stats(&stats, true).unwrap();
+        assert_eq!(decode_band_stats(&encoded).unwrap(), stats);
+    }
+
+    #[test]
+    fn test_tile_buffer_round_trips() {
+        let tile = Tile::new(304, 624, 10);
+        let data = vec![1u8, 2, 3, 4, 5];
+        let encoded = encode_tile_buffer(&tile, &data, false).unwrap();
+        let (decoded_tile, decoded_data) = decode_tile_buffer(&encoded).unwrap();
+        assert_eq!(decoded_tile, tile);
+        assert_eq!(decoded_data, data);
+    }
+
+    #[test]
+    fn test_decode_rejects_wrong_artifact_kind() {
+        let histogram = sample_histogram();
+        let encoded = encode_histogram(&histogram, false).unwrap();
+        assert!(decode_band_stats(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_magic() {
+        assert!(decode_histogram(&[0, 1, 2, 3, 4, 5, 6, 7]).is_err());
+    }
+
+    #[test]
+    fn test_read_varint_saturates_instead_of_panicking() {
+        // Ten continuation bytes plus a terminating byte encode far more than 64 bits of payload.
+        let mut bytes = vec![0xff; 10];
+        bytes.push(0x01);
+        let mut cursor = 0;
+        assert_eq!(read_varint(&bytes, &mut cursor).unwrap(), u64::MAX);
+    }
+}
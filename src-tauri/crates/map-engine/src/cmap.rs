@@ -3,11 +3,17 @@
 // Eg: `matplotlib.cm.get_cmap('viridis', 7).colors`
 // Potentialy use this data: https://github.com/matplotlib/matplotlib/blob/c06e8709dde6504d396349c0c80ef019c88c3927/lib/matplotlib/_cm_listed.py
 use crate::colour::{Colour, RgbaComponents};
-use ndarray::Array;
+use crate::errors::MapEngineError;
+use crate::filters::ColorMatrix;
+use crate::hillshade::Hillshade;
+use crate::raster::pixels::driver::Driver;
+use crate::raster::StyledPixels;
+use ndarray::{Array, Array3};
 use palette::{
     encoding::{Linear, Srgb},
     rgb::Rgb,
-    Alpha, Gradient, LinSrgba,
+    white_point::D65,
+    Alpha, FromColor, Gradient, Lab, Lch, LinSrgba,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -15,6 +21,47 @@ use std::convert::TryInto;
 
 /// A linear RGBA gradient
 pub type GradientLinearRGBA = Gradient<Alpha<Rgb<Linear<Srgb>, f64>, f64>>;
+/// A CIE Lab gradient (see [`InterpolationSpace::Lab`])
+pub type GradientLab = Gradient<Alpha<Lab<D65, f64>, f64>>;
+/// A CIE Lch gradient (see [`InterpolationSpace::Lch`])
+pub type GradientLch = Gradient<Alpha<Lch<D65, f64>, f64>>;
+
+/// Colour space used to interpolate between the control points of a gradient-based
+/// [`Composite`].
+///
+/// `LinearRgb` (the default, and the only space `make_gradient`/`make_gradient_with_breaks` used
+/// to support) blends a [`Colour`]'s stored components directly, with no gamma conversion at all
+/// — the naive lerp most image editors do, which is fast but biases the midpoints of a gradient
+/// towards the dark end (e.g. black → white looks too dark at 50%). `Rgb` instead decodes the
+/// stops from gamma-encoded sRGB to linear light before interpolating, then re-encodes the
+/// result, which is the "gamma-correct" fix for that bias without reaching for a perceptual
+/// space. `Lab`/`Lch` go further and reproject the control points into CIE Lab/Lch before
+/// interpolating, keeping perceived lightness steps even — the exact problem matplotlib's
+/// viridis/inferno were designed to avoid; `Lch` additionally walks the shortest hue path
+/// (350° → 10° crosses 0°, rather than sweeping through 180°). Alpha is always interpolated
+/// linearly, independently of the colour channels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InterpolationSpace {
+    Rgb,
+    LinearRgb,
+    Lab,
+    Lch,
+}
+
+impl Default for InterpolationSpace {
+    fn default() -> Self {
+        Self::LinearRgb
+    }
+}
+
+/// A gradient, keyed by the colour space it was built to interpolate in.
+#[derive(Debug, Clone)]
+enum GradientKind {
+    Rgb(GradientLinearRGBA),
+    LinearRgb(GradientLinearRGBA),
+    Lab(GradientLab),
+    Lch(GradientLch),
+}
 
 const VIRIDIS7: [Colour; 7] = [
     Colour::Seq((0.267004, 0.004874, 0.329415, 1.)),
@@ -93,6 +140,334 @@ fn make_gradient_with_breaks(nums: &[(f64, Colour)]) -> GradientLinearRGBA {
     Gradient::with_domain(cols)
 }
 
+/// Convert a gamma-encoded sRGB channel (`[0, 1]`) to linear light.
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear-light channel (`[0, 1]`) back to gamma-encoded sRGB.
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Build a [`GradientKind::Rgb`] gradient: its control points are gamma-decoded up front so the
+/// underlying [`Gradient`] (the same linear-light machinery [`make_gradient`] uses) interpolates
+/// in linear light; [`gradient_handle`] gamma-encodes the sampled result back on the way out.
+fn make_gradient_rgb(vmin: f64, vmax: f64, rgba: &[Colour]) -> GradientLinearRGBA {
+    let nums = Array::linspace(vmin, vmax, rgba.len());
+    let cols = nums
+        .iter()
+        .zip(rgba)
+        .map(|(v, comps)| {
+            let (r, g, b, a): RgbaComponents = comps.clone().into();
+            (
+                *v,
+                LinSrgba::from_components((
+                    srgb_to_linear(r),
+                    srgb_to_linear(g),
+                    srgb_to_linear(b),
+                    a,
+                )),
+            )
+        })
+        .collect();
+    Gradient::with_domain(cols)
+}
+
+fn make_gradient_with_breaks_rgb(nums: &[(f64, Colour)]) -> GradientLinearRGBA {
+    let cols = nums
+        .iter()
+        .map(|(v, comps)| {
+            let (r, g, b, a): RgbaComponents = comps.clone().into();
+            (
+                *v,
+                LinSrgba::from_components((
+                    srgb_to_linear(r),
+                    srgb_to_linear(g),
+                    srgb_to_linear(b),
+                    a,
+                )),
+            )
+        })
+        .collect();
+    Gradient::with_domain(cols)
+}
+
+fn make_gradient_lab(vmin: f64, vmax: f64, rgba: &[Colour]) -> GradientLab {
+    let nums = Array::linspace(vmin, vmax, rgba.len());
+    let cols = nums
+        .iter()
+        .zip(rgba)
+        .map(|(v, comps)| (*v, colour_to_lab(comps)))
+        .collect();
+    Gradient::with_domain(cols)
+}
+
+fn make_gradient_with_breaks_lab(nums: &[(f64, Colour)]) -> GradientLab {
+    let cols = nums.iter().map(|(v, comps)| (*v, colour_to_lab(comps))).collect();
+    Gradient::with_domain(cols)
+}
+
+fn make_gradient_lch(vmin: f64, vmax: f64, rgba: &[Colour]) -> GradientLch {
+    let nums = Array::linspace(vmin, vmax, rgba.len());
+    let cols = nums
+        .iter()
+        .zip(rgba)
+        .map(|(v, comps)| (*v, colour_to_lch(comps)))
+        .collect();
+    Gradient::with_domain(cols)
+}
+
+fn make_gradient_with_breaks_lch(nums: &[(f64, Colour)]) -> GradientLch {
+    let cols = nums.iter().map(|(v, comps)| (*v, colour_to_lch(comps))).collect();
+    Gradient::with_domain(cols)
+}
+
+fn colour_to_lab(colour: &Colour) -> Alpha<Lab<D65, f64>, f64> {
+    let (r, g, b, a): RgbaComponents = colour.clone().into();
+    Alpha {
+        color: Lab::from_color(Rgb::<Linear<Srgb>, f64>::new(
+            srgb_to_linear(r),
+            srgb_to_linear(g),
+            srgb_to_linear(b),
+        )),
+        alpha: a,
+    }
+}
+
+fn colour_to_lch(colour: &Colour) -> Alpha<Lch<D65, f64>, f64> {
+    let (r, g, b, a): RgbaComponents = colour.clone().into();
+    Alpha {
+        color: Lch::from_color(Rgb::<Linear<Srgb>, f64>::new(
+            srgb_to_linear(r),
+            srgb_to_linear(g),
+            srgb_to_linear(b),
+        )),
+        alpha: a,
+    }
+}
+
+/// Convert gamma-encoded sRGB components (`[0, 1]`) to CIE Lab, returned as `(L, a, b)`.
+fn rgb_to_lab(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+    let lab: Lab<D65, f64> = Lab::from_color(Rgb::<Linear<Srgb>, f64>::new(
+        srgb_to_linear(r),
+        srgb_to_linear(g),
+        srgb_to_linear(b),
+    ));
+    (lab.l, lab.a, lab.b)
+}
+
+/// CIEDE2000 perceptual colour difference (ΔE00) between two CIE Lab colours, given as
+/// `(L, a, b)`.
+fn ciede2000(lab1: (f64, f64, f64), lab2: (f64, f64, f64)) -> f64 {
+    let (l1, a1, b1) = lab1;
+    let (l2, a2, b2) = lab2;
+
+    let c1 = (a1 * a1 + b1 * b1).sqrt();
+    let c2 = (a2 * a2 + b2 * b2).sqrt();
+    let c_bar7 = ((c1 + c2) / 2.0).powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + 25f64.powi(7))).sqrt());
+
+    let a1p = a1 * (1.0 + g);
+    let a2p = a2 * (1.0 + g);
+    let c1p = (a1p * a1p + b1 * b1).sqrt();
+    let c2p = (a2p * a2p + b2 * b2).sqrt();
+
+    let h1p = if a1p == 0.0 && b1 == 0.0 {
+        0.0
+    } else {
+        b1.atan2(a1p).to_degrees().rem_euclid(360.0)
+    };
+    let h2p = if a2p == 0.0 && b2 == 0.0 {
+        0.0
+    } else {
+        b2.atan2(a2p).to_degrees().rem_euclid(360.0)
+    };
+
+    let delta_l = l2 - l1;
+    let delta_c = c2p - c1p;
+    let delta_h = if c1p * c2p == 0.0 {
+        0.0
+    } else {
+        let diff = h2p - h1p;
+        if diff.abs() <= 180.0 {
+            diff
+        } else if diff > 180.0 {
+            diff - 360.0
+        } else {
+            diff + 360.0
+        }
+    };
+    let delta_big_h = 2.0 * (c1p * c2p).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let l_bar_p = (l1 + l2) / 2.0;
+    let c_bar_p = (c1p + c2p) / 2.0;
+    let h_bar_p = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180.0 {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 360.0 {
+        (h1p + h2p + 360.0) / 2.0
+    } else {
+        (h1p + h2p - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_bar_p - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar_p).to_radians().cos()
+        + 0.32 * (3.0 * h_bar_p + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar_p - 63.0).to_radians().cos();
+
+    let delta_theta = 30.0 * (-(((h_bar_p - 275.0) / 25.0).powi(2))).exp();
+    let c_bar_p7 = c_bar_p.powi(7);
+    let r_c = 2.0 * (c_bar_p7 / (c_bar_p7 + 25f64.powi(7))).sqrt();
+    let s_l = 1.0 + (0.015 * (l_bar_p - 50.0).powi(2)) / (20.0 + (l_bar_p - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_p;
+    let s_h = 1.0 + 0.015 * c_bar_p * t;
+    let r_t = -(2.0 * delta_theta.to_radians()).sin() * r_c;
+
+    ((delta_l / s_l).powi(2)
+        + (delta_c / s_c).powi(2)
+        + (delta_big_h / s_h).powi(2)
+        + r_t * (delta_c / s_c) * (delta_big_h / s_h))
+        .sqrt()
+}
+
+/// Method used to pick class-break values from a sample of raster pixel values, for
+/// [`classify_breaks`]/[`Composite::classify_from_samples`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ClassifyMethod {
+    /// Split `[min, max]` into `k` equal-width ranges.
+    EqualInterval,
+    /// Break at the `k`-quantiles of the sorted samples, so each class holds roughly the same
+    /// number of samples.
+    Quantile,
+    /// Fisher-Jenks "natural breaks": choose breaks that minimise the within-class variance
+    /// (equivalently, maximise the between-class variance).
+    NaturalBreaks,
+}
+
+/// Compute `k + 1` class breaks from `samples` using `method` (see [`ClassifyMethod`]). Breaks
+/// are returned ascending with `breaks[0]`/`breaks[k]` at the sample min/max, so they slot
+/// straight into [`Composite::new_classified`] or, paired with colours, into
+/// [`Composite::new_gradient_with_breaks`] (see [`Composite::classify_from_samples`]).
+///
+/// # Panics
+/// Panics if `samples` is empty, `k == 0`, or (for [`ClassifyMethod::NaturalBreaks`])
+/// `samples.len() < k`.
+pub fn classify_breaks(samples: &[f64], k: usize, method: ClassifyMethod) -> Vec<f64> {
+    assert!(k > 0, "`classify_breaks` needs at least 1 class");
+    assert!(!samples.is_empty(), "`classify_breaks` needs at least 1 sample");
+    match method {
+        ClassifyMethod::EqualInterval => equal_interval_breaks(samples, k),
+        ClassifyMethod::Quantile => quantile_breaks(samples, k),
+        ClassifyMethod::NaturalBreaks => natural_breaks(samples, k),
+    }
+}
+
+/// Split `[min(samples), max(samples)]` into `k` equal-width ranges.
+fn equal_interval_breaks(samples: &[f64], k: usize) -> Vec<f64> {
+    let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = samples.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    (0..=k)
+        .map(|i| min + (max - min) * i as f64 / k as f64)
+        .collect()
+}
+
+/// Break at the `k`-quantiles of `samples`, linearly interpolating between the two nearest ranks
+/// when a quantile doesn't land exactly on a sample.
+fn quantile_breaks(samples: &[f64], k: usize) -> Vec<f64> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = sorted.len();
+
+    (0..=k)
+        .map(|i| {
+            let pos = (n - 1) as f64 * i as f64 / k as f64;
+            let lo = pos.floor() as usize;
+            let hi = pos.ceil() as usize;
+            sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+        })
+        .collect()
+}
+
+/// Fisher-Jenks natural breaks, partitioning sorted `samples` into `k` classes that minimise
+/// within-class variance. The classic dynamic-programming formulation: `lower_class_limits` and
+/// `variance_combinations` are `(n + 1) x (k + 1)` matrices, where
+/// `variance_combinations[l][j]` holds the best achievable total variance for the first `l`
+/// (sorted) samples split into `j` classes, and `lower_class_limits[l][j]` records where the
+/// last of those classes starts so the optimal partition can be recovered by backtracking from
+/// `lower_class_limits[n][k]`.
+fn natural_breaks(samples: &[f64], k: usize) -> Vec<f64> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = sorted.len();
+    assert!(n >= k, "`NaturalBreaks` needs at least as many samples as classes");
+
+    let mut lower_class_limits = vec![vec![0usize; k + 1]; n + 1];
+    let mut variance_combinations = vec![vec![0.0f64; k + 1]; n + 1];
+
+    for j in 1..=k {
+        lower_class_limits[1][j] = 1;
+        variance_combinations[1][j] = 0.0;
+        for row in variance_combinations.iter_mut().take(n + 1).skip(2) {
+            row[j] = f64::INFINITY;
+        }
+    }
+
+    let mut variance = 0.0;
+    for l in 2..=n {
+        let mut sum = 0.0;
+        let mut sum_sq = 0.0;
+        let mut count = 0.0;
+
+        for m in 1..=l {
+            let lower_class_limit = l - m + 1;
+            let val = sorted[lower_class_limit - 1];
+
+            count += 1.0;
+            sum += val;
+            sum_sq += val * val;
+            variance = sum_sq - (sum * sum) / count;
+
+            let i4 = lower_class_limit - 1;
+            if i4 != 0 {
+                for j in 2..=k {
+                    if variance_combinations[l][j] >= variance + variance_combinations[i4][j - 1] {
+                        lower_class_limits[l][j] = lower_class_limit;
+                        variance_combinations[l][j] = variance + variance_combinations[i4][j - 1];
+                    }
+                }
+            }
+        }
+
+        lower_class_limits[l][1] = 1;
+        variance_combinations[l][1] = variance;
+    }
+
+    let mut breaks = vec![0.0; k + 1];
+    breaks[0] = sorted[0];
+    breaks[k] = sorted[n - 1];
+
+    let mut row = n;
+    let mut class = k;
+    while class > 1 {
+        let limit = lower_class_limits[row][class];
+        breaks[class - 1] = sorted[limit - 2];
+        row = limit - 1;
+        class -= 1;
+    }
+
+    breaks
+}
+
 /// Types of palettes supported.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -105,6 +480,11 @@ pub enum ColourDefinition {
     ColoursAndBreaks(Vec<(f64, Colour)>),
     /// A RGB composite. See [`Composite::new_rgb`].
     RGB([f64; 3], [f64; 3]),
+    /// A classed/stepped palette. See [`Composite::classify`].
+    Classified(Vec<f64>, Vec<Colour>),
+    /// A relief-shaded elevation band, optionally multiplied with a gradient. See
+    /// [`Composite::new_hillshade`].
+    Hillshade(Hillshade),
 }
 
 /// Object to style `RawPixels`.
@@ -112,8 +492,20 @@ pub enum ColourDefinition {
 pub struct Composite {
     vmin: Option<Vec<f64>>,
     vmax: Option<Vec<f64>>,
-    gradient: Option<GradientLinearRGBA>,
+    gradient: Option<GradientKind>,
+    interpolation_space: InterpolationSpace,
     hashmap: Option<HashMap<isize, RgbaComponents>>,
+    /// `(breaks, colours)` for [`ColourDefinition::Classified`] composites: `breaks` has
+    /// `colours.len() + 1` entries, and class `i` covers the half-open range
+    /// `[breaks[i], breaks[i + 1])`.
+    classified: Option<(Vec<f64>, Vec<RgbaComponents>)>,
+    /// `(key, Lab, rgba)` for every entry of a `Discrete` composite, precomputed by
+    /// [`Composite::with_nearest_color`] so [`HandleGet::get`] only has to Lab-convert the
+    /// incoming pixel.
+    nearest_lab_palette: Option<Vec<(isize, (f64, f64, f64), RgbaComponents)>>,
+    /// Optional `feColorMatrix`-style recolouring applied to the final RGBA output of
+    /// [`HandleGet::get`], before quantization. See [`Composite::with_color_matrix`].
+    color_matrix: Option<ColorMatrix>,
     display: Option<String>,
     colour_definition: ColourDefinition,
     len: usize,
@@ -125,8 +517,12 @@ impl Default for Composite {
         Self {
             vmin: Some(vec![0.0]),
             vmax: Some(vec![1.0]),
-            gradient: Some(grad),
+            gradient: Some(GradientKind::LinearRgb(grad)),
+            interpolation_space: InterpolationSpace::LinearRgb,
             hashmap: None,
+            classified: None,
+            nearest_lab_palette: None,
+            color_matrix: None,
             display: Some("Gradient".to_string()),
             colour_definition: ColourDefinition::Colours(vec![
                 (0.0, 0.0, 0.0, 0.0).into(),
@@ -191,7 +587,7 @@ impl Composite {
         Self {
             vmin: Some(vec![vmin]),
             vmax: Some(vec![vmax]),
-            gradient: Some(grad),
+            gradient: Some(GradientKind::LinearRgb(grad)),
             display: Some("Gradient".to_string()),
             len: 1,
             ..Default::default()
@@ -217,7 +613,7 @@ impl Composite {
         Self {
             vmin: Some(vec![vmin]),
             vmax: Some(vec![vmax]),
-            gradient: Some(grad),
+            gradient: Some(GradientKind::LinearRgb(grad)),
             display: Some("Gradient".to_string()),
             colour_definition: ColourDefinition::Colours(colours),
             len: 1,
@@ -243,7 +639,7 @@ impl Composite {
     pub fn new_gradient_with_breaks(cols_and_breaks: Vec<(f64, Colour)>) -> Self {
         let grad = make_gradient_with_breaks(&cols_and_breaks);
         Self {
-            gradient: Some(grad),
+            gradient: Some(GradientKind::LinearRgb(grad)),
             display: Some("GradientWithBreaks".to_string()),
             colour_definition: ColourDefinition::ColoursAndBreaks(cols_and_breaks),
             len: 1,
@@ -251,6 +647,59 @@ impl Composite {
         }
     }
 
+    /// Rebuild this composite's gradient to interpolate in a different colour space.
+    ///
+    /// Only affects composites built from [`ColourDefinition::Colours`] or
+    /// [`ColourDefinition::ColoursAndBreaks`] (i.e. [`Composite::new_gradient`],
+    /// [`Composite::new_custom_gradient`] and [`Composite::new_gradient_with_breaks`]); `RGB` and
+    /// `Discrete` composites don't interpolate a [`Gradient`] and are returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use map_engine::{
+    ///     colour::Colour,
+    ///     cmap::{Composite, HandleGet, InterpolationSpace},
+    /// };
+    /// let comp = Composite::new_custom_gradient(0.0, 100.0, vec![
+    ///     Colour::from((255, 0, 0, 255)), // red
+    ///     Colour::from((0, 0, 255, 255)), // blue
+    /// ]).with_interpolation_space(InterpolationSpace::Lch);
+    /// ```
+    pub fn with_interpolation_space(mut self, space: InterpolationSpace) -> Self {
+        let vmin = self.vmin.as_ref().and_then(|v| v.first()).copied();
+        let vmax = self.vmax.as_ref().and_then(|v| v.first()).copied();
+
+        self.gradient = match (&self.colour_definition, space) {
+            (ColourDefinition::Colours(colours), InterpolationSpace::Rgb) => vmin
+                .zip(vmax)
+                .map(|(lo, hi)| GradientKind::Rgb(make_gradient_rgb(lo, hi, colours))),
+            (ColourDefinition::Colours(colours), InterpolationSpace::LinearRgb) => vmin
+                .zip(vmax)
+                .map(|(lo, hi)| GradientKind::LinearRgb(make_gradient(lo, hi, colours))),
+            (ColourDefinition::Colours(colours), InterpolationSpace::Lab) => vmin
+                .zip(vmax)
+                .map(|(lo, hi)| GradientKind::Lab(make_gradient_lab(lo, hi, colours))),
+            (ColourDefinition::Colours(colours), InterpolationSpace::Lch) => vmin
+                .zip(vmax)
+                .map(|(lo, hi)| GradientKind::Lch(make_gradient_lch(lo, hi, colours))),
+            (ColourDefinition::ColoursAndBreaks(breaks), InterpolationSpace::Rgb) => {
+                Some(GradientKind::Rgb(make_gradient_with_breaks_rgb(breaks)))
+            }
+            (ColourDefinition::ColoursAndBreaks(breaks), InterpolationSpace::LinearRgb) => {
+                Some(GradientKind::LinearRgb(make_gradient_with_breaks(breaks)))
+            }
+            (ColourDefinition::ColoursAndBreaks(breaks), InterpolationSpace::Lab) => {
+                Some(GradientKind::Lab(make_gradient_with_breaks_lab(breaks)))
+            }
+            (ColourDefinition::ColoursAndBreaks(breaks), InterpolationSpace::Lch) => {
+                Some(GradientKind::Lch(make_gradient_with_breaks_lch(breaks)))
+            }
+            _ => self.gradient,
+        };
+        self.interpolation_space = space;
+        self
+    }
+
     /// Create an discrete `Composite` that maps 1 pixel value into RGBA.
     ///
     /// # Example
@@ -283,10 +732,88 @@ impl Composite {
         }
     }
 
+    /// Enable "nearest colour" snapping for out-of-table values of a `Discrete` composite.
+    ///
+    /// Normally a pixel value with no exact entry in the palette is rendered transparent. With
+    /// this enabled, the value is instead treated as a packed `0xRRGGBB` colour and assigned the
+    /// palette entry whose colour is perceptually closest to it (by CIEDE2000 ΔE00) — useful for
+    /// re-colouring pre-quantized imagery, or matching arbitrary colours to a fixed legend. Only
+    /// affects `Discrete` composites ([`Composite::new_discrete_palette`]); returned unchanged
+    /// otherwise.
+    ///
+    /// Every palette colour is converted to CIE Lab once here, so [`HandleGet::get`] only needs
+    /// to convert the incoming pixel on every call.
+    ///
+    /// # Example
+    /// ```
+    /// use map_engine::{colour::Colour, cmap::{Composite, HandleGet}};
+    /// let comp = Composite::new_discrete_palette(vec![
+    ///     (0, Colour::from((255, 0, 0, 255))), // red
+    ///     (1, Colour::from((0, 0, 255, 255))), // blue
+    /// ])
+    /// .with_nearest_color();
+    /// // 0xDD0000 isn't a key in the table, but it's much closer to red than blue.
+    /// assert_eq!(comp.get(&[0xDD0000 as f64], None), [255, 0, 0, 255]);
+    /// ```
+    pub fn with_nearest_color(mut self) -> Self {
+        if let ColourDefinition::Discrete(pairs) = &self.colour_definition {
+            self.nearest_lab_palette = Some(
+                pairs
+                    .iter()
+                    .map(|(key, colour)| {
+                        let (r, g, b, a): RgbaComponents = colour.clone().into();
+                        (*key, rgb_to_lab(r, g, b), (r, g, b, a))
+                    })
+                    .collect(),
+            );
+        }
+        self
+    }
+
+    /// Recolour every pixel this composite produces with a [`ColorMatrix`], applied to its
+    /// `[R, G, B, A, 1]` output before quantization to `u8`.
+    ///
+    /// Particularly useful for [`Composite::new_rgb`], where there's otherwise no way to balance
+    /// or rotate the bands mapped onto `R`/`G`/`B`, but it applies to any `ColourDefinition`.
+    ///
+    /// # Example
+    /// ```
+    /// use map_engine::{cmap::{Composite, HandleGet}, filters::ColorMatrix};
+    /// let comp = Composite::new_rgb(vec![0.0, 0.0, 0.0], vec![100.0, 100.0, 100.0])
+    ///     .with_color_matrix(ColorMatrix::saturate(0.0));
+    /// // Desaturated, so R, G and B all land on the same (greyscale) value.
+    /// let [r, g, b, _] = comp.get(&[100.0, 0.0, 0.0], None);
+    /// assert_eq!(r, g);
+    /// assert_eq!(g, b);
+    /// ```
+    pub fn with_color_matrix(mut self, matrix: ColorMatrix) -> Self {
+        self.color_matrix = Some(matrix);
+        self
+    }
+
     pub(crate) fn is_contiguous(&self) -> bool {
         !matches!(self.colour_definition, ColourDefinition::RGB(_, _))
     }
 
+    /// Precompute a lookup table for gradient-based composites by sampling [`HandleGet::get`]
+    /// at `size` evenly spaced values between `vmin` and `vmax`.
+    ///
+    /// Returns `None` when the composite isn't a single contiguous gradient (e.g. RGB or
+    /// discrete palettes), which callers should treat as "cannot be approximated by a LUT".
+    #[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+    pub(crate) fn gradient_lut(&self, size: usize) -> Option<(f64, f64, Vec<[u8; 4]>)> {
+        if self.gradient.is_none() || self.len != 1 {
+            return None;
+        }
+        let vmin = *self.vmin.as_ref()?.first()?;
+        let vmax = *self.vmax.as_ref()?.first()?;
+        let lut = Array::linspace(vmin, vmax, size)
+            .iter()
+            .map(|v| gradient_handle(self, &[*v], None))
+            .collect();
+        Some((vmin, vmax, lut))
+    }
+
     /// Number of bands supported by the `Composite`.
     ///
     /// ⚠ This will probably be deprecated once we enforce the number of bands using the type
@@ -294,12 +821,375 @@ impl Composite {
     pub fn n_bands(&self) -> usize {
         self.len
     }
+
+    /// Quantize a gradient-based `Composite` into a stepped/classed palette of exactly `n`
+    /// colours, for classed legends and choropleth-style rasters.
+    ///
+    /// The underlying gradient is sampled at `n` evenly spaced values that include both
+    /// endpoints (`vmin + (vmax - vmin) * i / (n - 1)`, for `i` in `0..n`); `n == 1` is handled
+    /// separately (sampling the midpoint) to avoid a divide-by-zero. Each sampled colour then
+    /// fills the half-open value range `[break_i, break_{i + 1})`, so [`HandleGet::get`] returns
+    /// the class colour for a pixel rather than a smoothly interpolated one.
+    ///
+    /// # Example
+    /// ```
+    /// use map_engine::cmap::{Composite, HandleGet, viridis};
+    /// let comp = Composite::new_gradient(0.0, 100.0, &viridis).classify(4);
+    /// assert_eq!(comp.get(&[0.0], None), comp.get(&[10.0], None));
+    /// assert_ne!(comp.get(&[0.0], None), comp.get(&[99.0], None));
+    /// ```
+    pub fn classify(self, n: usize) -> Composite {
+        assert!(n > 0, "`classify` needs at least 1 class");
+        assert!(
+            self.gradient.is_some(),
+            "`classify` requires a gradient-based Composite"
+        );
+        let vmin = self.vmin.as_ref().and_then(|v| v.first()).copied().unwrap_or(0.0);
+        let vmax = self.vmax.as_ref().and_then(|v| v.first()).copied().unwrap_or(1.0);
+
+        let samples: Vec<f64> = if n > 1 {
+            (0..n)
+                .map(|i| vmin + (vmax - vmin) * i as f64 / (n - 1) as f64)
+                .collect()
+        } else {
+            vec![(vmin + vmax) / 2.0]
+        };
+        let colours: Vec<RgbaComponents> = samples
+            .iter()
+            .map(|v| {
+                let [r, g, b, a] = gradient_handle(&self, &[*v], None);
+                (
+                    r as f64 / 255.0,
+                    g as f64 / 255.0,
+                    b as f64 / 255.0,
+                    a as f64 / 255.0,
+                )
+            })
+            .collect();
+        let breaks: Vec<f64> = (0..=n)
+            .map(|i| vmin + (vmax - vmin) * i as f64 / n as f64)
+            .collect();
+
+        Self {
+            display: Some("Classified".to_string()),
+            colour_definition: ColourDefinition::Classified(
+                breaks.clone(),
+                colours.iter().map(|c| Colour::Seq(*c)).collect(),
+            ),
+            classified: Some((breaks, colours)),
+            gradient: None,
+            vmin: self.vmin,
+            vmax: self.vmax,
+            ..Default::default()
+        }
+    }
+
+    /// Compute `k` class breaks from a sample of raster pixel values (see [`classify_breaks`])
+    /// and pair them with colours sampled from `cmap_f` (e.g. [`viridis`]/[`inferno`]), producing
+    /// a ready-to-use [`Composite::new_gradient_with_breaks`] composite.
+    ///
+    /// Unlike [`Composite::classify`] (which quantizes an existing gradient into equal-width
+    /// classes), the breaks here are computed from the data itself, so a skewed raster still gets
+    /// meaningful class boundaries instead of most classes being empty.
+    ///
+    /// # Example
+    /// ```
+    /// use map_engine::cmap::{Composite, ClassifyMethod, viridis, HandleGet};
+    /// let samples = vec![0.0, 1.0, 2.0, 3.0, 100.0];
+    /// let comp = Composite::classify_from_samples(&samples, 4, ClassifyMethod::Quantile, &viridis);
+    /// assert_eq!(comp.get(&[0.0], None), [68, 1, 84, 255]); // viridis(0.0)
+    /// ```
+    pub fn classify_from_samples(
+        samples: &[f64],
+        k: usize,
+        method: ClassifyMethod,
+        cmap_f: &'static dyn Fn(f64, f64) -> GradientLinearRGBA,
+    ) -> Self {
+        let breaks = classify_breaks(samples, k, method);
+        let grad = cmap_f(0.0, (breaks.len() - 1) as f64);
+        let cols_and_breaks: Vec<(f64, Colour)> = breaks
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (v, grad.get(i as f64).into()))
+            .collect();
+        Composite::new_gradient_with_breaks(cols_and_breaks)
+    }
+
+    /// Build a `Classified` composite directly from its breaks and class colours, e.g. to
+    /// reconstruct one from a deserialized [`ColourDefinition::Classified`] rather than sampling
+    /// it out of a gradient (see [`Composite::classify`]).
+    ///
+    /// # Panics
+    /// Panics unless `breaks.len() == colours.len() + 1`.
+    ///
+    /// # Example
+    /// ```
+    /// use map_engine::cmap::{Composite, HandleGet};
+    /// let comp = Composite::new_classified(
+    ///     vec![0.0, 50.0, 100.0],
+    ///     vec![(0, 0, 0, 255).into(), (255, 255, 255, 255).into()],
+    /// );
+    /// assert_eq!(comp.get(&[10.0], None), [0, 0, 0, 255]);
+    /// assert_eq!(comp.get(&[75.0], None), [255, 255, 255, 255]);
+    /// ```
+    pub fn new_classified(breaks: Vec<f64>, colours: Vec<Colour>) -> Self {
+        assert_eq!(
+            breaks.len(),
+            colours.len() + 1,
+            "`breaks` must have exactly one more entry than `colours`"
+        );
+        let vmin = breaks.first().copied();
+        let vmax = breaks.last().copied();
+        let rgba: Vec<RgbaComponents> = colours.iter().map(|c| c.clone().into()).collect();
+        Self {
+            display: Some("Classified".to_string()),
+            colour_definition: ColourDefinition::Classified(breaks.clone(), colours),
+            classified: Some((breaks, rgba)),
+            gradient: None,
+            vmin: vmin.map(|v| vec![v]),
+            vmax: vmax.map(|v| vec![v]),
+            ..Default::default()
+        }
+    }
+
+    /// Create a `Composite` that renders a single elevation band as relief-shaded grayscale.
+    ///
+    /// Unlike the other constructors, the colouring here needs the neighbourhood around each
+    /// pixel (to estimate a surface normal), which [`HandleGet::get`] has no access to. The
+    /// actual shading happens ahead of time in
+    /// [`crate::raster::Raster::read_hillshade_tile`], which hands this composite a single band
+    /// of precomputed intensity in `[0.0, 1.0]` instead of raw elevation.
+    ///
+    /// # Example
+    /// ```
+    /// use map_engine::{cmap::{Composite, HandleGet}, hillshade::Hillshade};
+    /// let comp = Composite::new_hillshade(Hillshade::new(315.0, 45.0));
+    /// assert_eq!(comp.get(&[1.0], None), [255, 255, 255, 255]);
+    /// assert_eq!(comp.get(&[0.0], None), [0, 0, 0, 255]);
+    /// ```
+    pub fn new_hillshade(hillshade: Hillshade) -> Self {
+        Self {
+            display: Some("Hillshade".to_string()),
+            colour_definition: ColourDefinition::Hillshade(hillshade),
+            len: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Multiply this hillshade's intensity with an existing gradient-based `Composite`'s
+    /// colour, instead of flat grayscale — e.g. a relief-shaded hypsometric tint.
+    ///
+    /// Once this is applied, [`HandleGet::get`] expects `values` to be `[intensity, elevation]`
+    /// (see [`crate::raster::Raster::read_hillshade_tile`]) rather than `[intensity]`.
+    ///
+    /// Only affects [`Composite::new_hillshade`] composites built from a gradient-based `base`
+    /// (i.e. [`Composite::new_gradient`], [`Composite::new_custom_gradient`] or
+    /// [`Composite::new_gradient_with_breaks`]); returned unchanged otherwise.
+    pub fn with_hillshade_base(mut self, base: Composite) -> Self {
+        if matches!(self.colour_definition, ColourDefinition::Hillshade(_)) && base.gradient.is_some() {
+            self.gradient = base.gradient;
+            self.vmin = base.vmin;
+            self.vmax = base.vmax;
+            self.len = 2;
+        }
+        self
+    }
+
+    /// Draw this composite's colour map as a `(width, height)` swatch strip, for a front-end
+    /// legend that always matches the styling actually in use.
+    ///
+    /// * `Discrete`/`Classified` palettes are drawn as `n` equal-width, full-height boxes (one
+    ///   per class).
+    /// * `Colours`/`ColoursAndBreaks` gradients are sampled once per column across
+    ///   `[vmin, vmax]`.
+    /// * `RGB` composites are drawn as three stacked per-band ramps, each column holding that
+    ///   band's value at `vmin + (vmax - vmin) * x / (width - 1)` with the other bands at `vmin`.
+    ///
+    /// No tick labels are drawn onto the bitmap itself (this crate has no font-rendering
+    /// dependency); pair this with [`Composite::legend_stops`] to get the `value`s a front-end
+    /// can label the strip with.
+    pub fn render_legend(
+        &self,
+        width: usize,
+        height: usize,
+    ) -> Result<StyledPixels, MapEngineError> {
+        if width == 0 || height == 0 {
+            return Err(MapEngineError::Msg(
+                "render_legend needs width > 0 and height > 0".to_string(),
+            ));
+        }
+        let mut data = Array3::<u8>::zeros((height, width, 4));
+
+        let mut fill_column = |data: &mut Array3<u8>, x: usize, y_range: std::ops::Range<usize>, rgba: [u8; 4]| {
+            for y in y_range {
+                for (c, v) in rgba.iter().enumerate() {
+                    data[[y, x, c]] = *v;
+                }
+            }
+        };
+
+        match &self.colour_definition {
+            ColourDefinition::Discrete(pairs) => {
+                let n = pairs.len().max(1);
+                for x in 0..width {
+                    let (value, _) = pairs[(x * n / width).min(n - 1)];
+                    let rgba = self.get(&[value as f64], None);
+                    fill_column(&mut data, x, 0..height, rgba);
+                }
+            }
+            ColourDefinition::Classified(breaks, _) => {
+                let n = (breaks.len().saturating_sub(1)).max(1);
+                for x in 0..width {
+                    let i = (x * n / width).min(n - 1);
+                    let mid = (breaks[i] + breaks[i + 1]) / 2.0;
+                    let rgba = self.get(&[mid], None);
+                    fill_column(&mut data, x, 0..height, rgba);
+                }
+            }
+            ColourDefinition::RGB(vmin, vmax) => {
+                let band_height = height / 3;
+                for band in 0..3 {
+                    let y_start = band * band_height;
+                    let y_end = if band == 2 { height } else { y_start + band_height };
+                    for x in 0..width {
+                        let t = if width > 1 {
+                            x as f64 / (width - 1) as f64
+                        } else {
+                            0.0
+                        };
+                        let mut values = *vmin;
+                        values[band] = vmin[band] + (vmax[band] - vmin[band]) * t;
+                        let rgba = self.get(&values, None);
+                        fill_column(&mut data, x, y_start..y_end, rgba);
+                    }
+                }
+            }
+            ColourDefinition::Colours(_) | ColourDefinition::ColoursAndBreaks(_) => {
+                let vmin = self.vmin.as_ref().and_then(|v| v.first()).copied().unwrap_or(0.0);
+                let vmax = self.vmax.as_ref().and_then(|v| v.first()).copied().unwrap_or(1.0);
+                for x in 0..width {
+                    let t = if width > 1 {
+                        x as f64 / (width - 1) as f64
+                    } else {
+                        0.0
+                    };
+                    let rgba = self.get(&[vmin + (vmax - vmin) * t], None);
+                    fill_column(&mut data, x, 0..height, rgba);
+                }
+            }
+            ColourDefinition::Hillshade(_) => {
+                // No value range to speak of (the input is already a [0.0, 1.0] intensity), so
+                // draw the intensity ramp itself, black to white.
+                for x in 0..width {
+                    let t = if width > 1 {
+                        x as f64 / (width - 1) as f64
+                    } else {
+                        0.0
+                    };
+                    let rgba = self.get(&[t], None);
+                    fill_column(&mut data, x, 0..height, rgba);
+                }
+            }
+        }
+
+        Ok(StyledPixels::new(data, Driver::Generic))
+    }
+
+    /// Structured `{value, rgba}` stops describing this composite's legend, for front-ends that
+    /// want to draw their own legend (with labels) instead of using
+    /// [`Composite::render_legend`]'s bitmap.
+    ///
+    /// Gradient composites (`Colours`/`ColoursAndBreaks`) are sampled at `n` evenly spaced values
+    /// across `[vmin, vmax]`, including both endpoints. `Discrete`/`Classified` composites return
+    /// one stop per class (`n` is ignored). `RGB` composites aren't representable as single-value
+    /// stops and return an empty `Vec` — use [`Composite::render_legend`] for those instead.
+    pub fn legend_stops(&self, n: usize) -> Vec<LegendStop> {
+        match &self.colour_definition {
+            ColourDefinition::Discrete(pairs) => pairs
+                .iter()
+                .map(|(value, _)| LegendStop {
+                    value: *value as f64,
+                    rgba: self.get(&[*value as f64], None),
+                })
+                .collect(),
+            ColourDefinition::Classified(breaks, _) => breaks
+                .windows(2)
+                .map(|w| {
+                    let mid = (w[0] + w[1]) / 2.0;
+                    LegendStop {
+                        value: mid,
+                        rgba: self.get(&[mid], None),
+                    }
+                })
+                .collect(),
+            ColourDefinition::Colours(_) | ColourDefinition::ColoursAndBreaks(_) => {
+                let vmin = self.vmin.as_ref().and_then(|v| v.first()).copied().unwrap_or(0.0);
+                let vmax = self.vmax.as_ref().and_then(|v| v.first()).copied().unwrap_or(1.0);
+                let n = n.max(1);
+                (0..n)
+                    .map(|i| {
+                        let t = if n > 1 { i as f64 / (n - 1) as f64 } else { 0.0 };
+                        let value = vmin + (vmax - vmin) * t;
+                        LegendStop {
+                            value,
+                            rgba: self.get(&[value], None),
+                        }
+                    })
+                    .collect()
+            }
+            ColourDefinition::RGB(_, _) => Vec::new(),
+            ColourDefinition::Hillshade(_) => {
+                let n = n.max(1);
+                (0..n)
+                    .map(|i| {
+                        let t = if n > 1 { i as f64 / (n - 1) as f64 } else { 0.0 };
+                        LegendStop {
+                            value: t,
+                            rgba: self.get(&[t], None),
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A single stop in a legend rendered via [`Composite::legend_stops`]: a representative value and
+/// its RGBA colour.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LegendStop {
+    pub value: f64,
+    pub rgba: [u8; 4],
 }
 
 fn gradient_handle(comp: &Composite, values: &[f64], no_data_values: Option<&[f64]>) -> [u8; 4] {
-    let grad = comp.gradient.as_ref().unwrap();
-    let col = grad.get(values[0]);
-    let (r, g, b, a) = col.into_components();
+    let (r, g, b, a) = match comp.gradient.as_ref().unwrap() {
+        GradientKind::Rgb(grad) => {
+            let (r, g, b, a) = grad.get(values[0]).into_components();
+            (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a)
+        }
+        GradientKind::LinearRgb(grad) => grad.get(values[0]).into_components(),
+        GradientKind::Lab(grad) => {
+            let col = grad.get(values[0]);
+            let rgb = Rgb::<Linear<Srgb>, f64>::from_color(col.color);
+            (
+                linear_to_srgb(rgb.red),
+                linear_to_srgb(rgb.green),
+                linear_to_srgb(rgb.blue),
+                col.alpha,
+            )
+        }
+        GradientKind::Lch(grad) => {
+            let col = grad.get(values[0]);
+            let rgb = Rgb::<Linear<Srgb>, f64>::from_color(col.color);
+            (
+                linear_to_srgb(rgb.red),
+                linear_to_srgb(rgb.green),
+                linear_to_srgb(rgb.blue),
+                col.alpha,
+            )
+        }
+    };
     let a = if let Some(ndv) = no_data_values {
         assert!(
             ndv.len() == 1,
@@ -357,15 +1247,57 @@ fn rgb_handle(comp: &Composite, values: &[f64], no_data_values: Option<&[f64]>)
 fn hashmap_handle(comp: &Composite, values: &[f64]) -> [u8; 4] {
     let val = values[0];
     let hash = comp.hashmap.as_ref().unwrap();
-    let (r, g, b, a) = hash
-        .get(&(val.trunc() as isize))
-        .unwrap_or(&(0.0, 0.0, 0.0, 0.0));
-    [
-        (r * 255.0) as u8,
-        (g * 255.0) as u8,
-        (b * 255.0) as u8,
-        (a * 255.0) as u8,
-    ]
+    if let Some((r, g, b, a)) = hash.get(&(val.trunc() as isize)) {
+        return [
+            (r * 255.0) as u8,
+            (g * 255.0) as u8,
+            (b * 255.0) as u8,
+            (a * 255.0) as u8,
+        ];
+    }
+
+    if let Some(palette) = comp.nearest_lab_palette.as_ref() {
+        let packed = val.trunc() as i64 as u32;
+        let r = ((packed >> 16) & 0xff) as f64 / 255.0;
+        let g = ((packed >> 8) & 0xff) as f64 / 255.0;
+        let b = (packed & 0xff) as f64 / 255.0;
+        let lab = rgb_to_lab(r, g, b);
+
+        if let Some((_, _, (r, g, b, a))) = palette.iter().min_by(|(_, a, _), (_, b, _)| {
+            ciede2000(lab, *a)
+                .partial_cmp(&ciede2000(lab, *b))
+                .unwrap()
+        }) {
+            return [
+                (r * 255.0) as u8,
+                (g * 255.0) as u8,
+                (b * 255.0) as u8,
+                (a * 255.0) as u8,
+            ];
+        }
+    }
+
+    [0, 0, 0, 0]
+}
+
+/// Shade a precomputed hillshade intensity (`values[0]`, in `[0.0, 1.0]`), optionally multiplied
+/// with `comp.gradient` sampled at a second, raw elevation value (`values[1]`) set up by
+/// [`Composite::with_hillshade_base`].
+fn hillshade_handle(comp: &Composite, values: &[f64], no_data_values: Option<&[f64]>) -> [u8; 4] {
+    let intensity = values[0].clamp(0.0, 1.0);
+
+    if values.len() > 1 && comp.gradient.is_some() {
+        let [r, g, b, a] = gradient_handle(comp, &values[1..], no_data_values);
+        return [
+            (r as f64 * intensity) as u8,
+            (g as f64 * intensity) as u8,
+            (b as f64 * intensity) as u8,
+            a,
+        ];
+    }
+
+    let grey = (intensity * 255.0).round() as u8;
+    [grey, grey, grey, 255]
 }
 
 /// Get a RGBA colour given a raw pixel value
@@ -379,14 +1311,43 @@ pub trait HandleGet {
     fn get(&self, values: &[f64], no_data_values: Option<&[f64]>) -> [u8; 4];
 }
 
+fn classified_handle(comp: &Composite, values: &[f64]) -> [u8; 4] {
+    let (breaks, colours) = comp.classified.as_ref().unwrap();
+    let v = values[0];
+    // Find the class whose half-open range [breaks[i], breaks[i + 1]) contains `v`, clamping
+    // out-of-range values to the first/last class instead of going transparent.
+    let idx = breaks
+        .windows(2)
+        .position(|w| v >= w[0] && v < w[1])
+        .unwrap_or(if v < breaks[0] { 0 } else { colours.len() - 1 });
+    let (r, g, b, a) = colours[idx];
+    [
+        (r * 255.0) as u8,
+        (g * 255.0) as u8,
+        (b * 255.0) as u8,
+        (a * 255.0) as u8,
+    ]
+}
+
 impl HandleGet for Composite {
     fn get(&self, values: &[f64], no_data_values: std::option::Option<&[f64]>) -> [u8; 4] {
-        match &self.colour_definition {
+        let rgba = match &self.colour_definition {
             ColourDefinition::Discrete(_) => hashmap_handle(self, values),
             ColourDefinition::Colours(_) | ColourDefinition::ColoursAndBreaks(_) => {
                 gradient_handle(self, values, no_data_values)
             }
             ColourDefinition::RGB(_, _) => rgb_handle(self, values, no_data_values),
+            ColourDefinition::Classified(_, _) => classified_handle(self, values),
+            ColourDefinition::Hillshade(_) => hillshade_handle(self, values, no_data_values),
+        };
+        match &self.color_matrix {
+            Some(matrix) => {
+                let pixel = rgba.map(|v| v as f32 / 255.0);
+                matrix
+                    .apply(pixel)
+                    .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+            }
+            None => rgba,
         }
     }
 }
@@ -437,4 +1398,279 @@ mod tests {
         let col_def: ColourDefinition = serde_json::from_str(s).unwrap();
         assert_eq!(col_def, expected_col_def);
     }
+
+    #[test]
+    fn test_interpolation_space_endpoints_match_linear_rgb() {
+        // Whatever the interpolation space, the endpoints are exact: only the colours in
+        // between differ.
+        let colours = vec![
+            Colour::from((255, 0, 0, 255)),
+            Colour::from((0, 0, 255, 255)),
+        ];
+        for space in [
+            InterpolationSpace::Rgb,
+            InterpolationSpace::LinearRgb,
+            InterpolationSpace::Lab,
+            InterpolationSpace::Lch,
+        ] {
+            let comp = Composite::new_custom_gradient(0.0, 100.0, colours.clone())
+                .with_interpolation_space(space);
+            assert_eq!(comp.get(&[0.0], None), [255, 0, 0, 255]);
+            assert_eq!(comp.get(&[100.0], None), [0, 0, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn test_lab_lch_interpolation_differs_from_linear_rgb() {
+        let colours = vec![
+            Colour::from((255, 0, 0, 255)),
+            Colour::from((0, 0, 255, 255)),
+        ];
+        let linear = Composite::new_custom_gradient(0.0, 100.0, colours.clone());
+        let lab = Composite::new_custom_gradient(0.0, 100.0, colours.clone())
+            .with_interpolation_space(InterpolationSpace::Lab);
+        let lch = Composite::new_custom_gradient(0.0, 100.0, colours)
+            .with_interpolation_space(InterpolationSpace::Lch);
+
+        assert_ne!(linear.get(&[50.0], None), lab.get(&[50.0], None));
+        assert_ne!(linear.get(&[50.0], None), lch.get(&[50.0], None));
+    }
+
+    #[test]
+    fn test_rgb_interpolation_differs_from_linear_rgb() {
+        // Black to white is the classic case where the two disagree: a naive lerp of the raw
+        // (gamma-encoded) components undershoots, while decoding to linear light first and
+        // re-encoding (`Rgb`) lands on a visibly brighter midpoint.
+        let colours = vec![
+            Colour::from((0, 0, 0, 255)),
+            Colour::from((255, 255, 255, 255)),
+        ];
+        let linear = Composite::new_custom_gradient(0.0, 100.0, colours.clone());
+        let rgb = Composite::new_custom_gradient(0.0, 100.0, colours)
+            .with_interpolation_space(InterpolationSpace::Rgb);
+
+        let linear_mid = linear.get(&[50.0], None);
+        let rgb_mid = rgb.get(&[50.0], None);
+        assert_ne!(linear_mid, rgb_mid);
+        assert!(rgb_mid[0] > linear_mid[0]);
+    }
+
+    #[test]
+    fn test_classify_includes_both_endpoints() {
+        let comp = Composite::new_gradient(0.0, 100.0, &viridis);
+        let classed = comp.classify(4);
+        assert_eq!(classed.get(&[0.0], None), [68, 1, 84, 255]); // viridis(0.0)
+        assert_eq!(classed.get(&[100.0], None), [253, 231, 36, 255]); // viridis(100.0)
+    }
+
+    #[test]
+    fn test_classify_steps_are_constant_within_a_class() {
+        let comp = Composite::new_gradient(0.0, 100.0, &viridis).classify(4);
+        // [0, 25) is a single class, so every value in it must match.
+        assert_eq!(comp.get(&[0.0], None), comp.get(&[10.0], None));
+        assert_eq!(comp.get(&[0.0], None), comp.get(&[24.9], None));
+        assert_ne!(comp.get(&[0.0], None), comp.get(&[25.0], None));
+    }
+
+    #[test]
+    fn test_classify_single_class_samples_midpoint() {
+        let comp = Composite::new_gradient(0.0, 100.0, &viridis).classify(1);
+        assert_eq!(comp.get(&[0.0], None), comp.get(&[100.0], None));
+        assert_eq!(comp.get(&[0.0], None), comp.get(&[50.0], None));
+    }
+
+    #[test]
+    fn test_with_interpolation_space_leaves_rgb_composite_unchanged() {
+        let comp = Composite::new_rgb(vec![0.0, 0.0, 0.0], vec![100.0, 100.0, 100.0])
+            .with_interpolation_space(InterpolationSpace::Lab);
+        assert_eq!(comp.get(&[0.0, 50.0, 100.0], None), [0, 127, 255, 255]);
+    }
+
+    #[test]
+    fn test_legend_stops_gradient_samples_endpoints() {
+        let comp = Composite::new_gradient(0.0, 100.0, &viridis);
+        let stops = comp.legend_stops(5);
+        assert_eq!(stops.len(), 5);
+        assert_eq!(stops[0].value, 0.0);
+        assert_eq!(stops[0].rgba, [68, 1, 84, 255]);
+        assert_eq!(stops[4].value, 100.0);
+        assert_eq!(stops[4].rgba, [253, 231, 36, 255]);
+    }
+
+    #[test]
+    fn test_legend_stops_discrete_one_per_class() {
+        let comp = Composite::new_discrete_palette(vec![
+            (0, Colour::from((255, 0, 0, 255))),
+            (1, Colour::from((0, 255, 0, 255))),
+        ]);
+        let stops = comp.legend_stops(10); // n is ignored
+        assert_eq!(stops.len(), 2);
+        assert_eq!(stops[0].rgba, [255, 0, 0, 255]);
+        assert_eq!(stops[1].rgba, [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_legend_stops_rgb_is_empty() {
+        let comp = Composite::new_rgb(vec![0.0, 0.0, 0.0], vec![100.0, 100.0, 100.0]);
+        assert!(comp.legend_stops(5).is_empty());
+    }
+
+    #[test]
+    fn test_render_legend_gradient_matches_endpoints() {
+        let comp = Composite::new_gradient(0.0, 100.0, &viridis);
+        let legend = comp.render_legend(10, 4).unwrap().into_array();
+        assert_eq!(legend.shape(), &[4, 10, 4]);
+        assert_eq!(
+            legend.slice(ndarray::s![0, 0, ..]).to_vec(),
+            vec![68, 1, 84, 255]
+        ); // viridis(0.0)
+        assert_eq!(
+            legend.slice(ndarray::s![0, 9, ..]).to_vec(),
+            vec![253, 231, 36, 255]
+        ); // viridis(100.0)
+    }
+
+    #[test]
+    fn test_render_legend_rejects_zero_size() {
+        let comp = Composite::new_gradient(0.0, 100.0, &viridis);
+        assert!(comp.render_legend(0, 10).is_err());
+        assert!(comp.render_legend(10, 0).is_err());
+    }
+
+    #[test]
+    fn test_nearest_color_exact_match_unaffected() {
+        let comp = Composite::new_discrete_palette(vec![
+            (0, Colour::from((255, 0, 0, 255))),
+            (1, Colour::from((0, 0, 255, 255))),
+        ])
+        .with_nearest_color();
+        assert_eq!(comp.get(&[1.0], None), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_nearest_color_snaps_to_closest_palette_entry() {
+        let comp = Composite::new_discrete_palette(vec![
+            (0, Colour::from((255, 0, 0, 255))),
+            (1, Colour::from((0, 0, 255, 255))),
+        ])
+        .with_nearest_color();
+        // 0xDD0000 is much closer to red than blue.
+        assert_eq!(comp.get(&[0xDD0000 as f64], None), [255, 0, 0, 255]);
+        // 0x0000DD is much closer to blue than red.
+        assert_eq!(comp.get(&[0x0000DD as f64], None), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_nearest_color_matches_in_gamma_decoded_lab_space() {
+        let comp = Composite::new_discrete_palette(vec![
+            (0, Colour::from((0, 0, 0, 255))),
+            (1, Colour::from((255, 255, 255, 255))),
+        ])
+        .with_nearest_color();
+        // Gamma-encoded 77/255 decodes to linear-light ~0.073, whose Lab L (~33) sits on the
+        // black side of the black/white midpoint; treating it as already-linear instead gives L
+        // ~62, which would wrongly snap to white.
+        assert_eq!(comp.get(&[0x4D4D4D as f64], None), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_hillshade_maps_intensity_to_grayscale() {
+        let comp = Composite::new_hillshade(Hillshade::new(315.0, 45.0));
+        assert_eq!(comp.get(&[0.0], None), [0, 0, 0, 255]);
+        assert_eq!(comp.get(&[1.0], None), [255, 255, 255, 255]);
+        assert_eq!(comp.get(&[0.5], None), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn test_hillshade_base_multiplies_gradient_colour() {
+        let comp = Composite::new_hillshade(Hillshade::new(315.0, 45.0))
+            .with_hillshade_base(Composite::new_gradient(0.0, 100.0, &viridis));
+        let lit = comp.get(&[1.0, 0.0], None);
+        let dimmed = comp.get(&[0.5, 0.0], None);
+        assert_eq!(lit, [68, 1, 84, 255]); // viridis(0.0), unmodified
+        assert_eq!(dimmed, [34, 0, 42, 255]); // halved
+    }
+
+    #[test]
+    fn test_hillshade_base_ignored_without_gradient_composite() {
+        let comp = Composite::new_hillshade(Hillshade::new(315.0, 45.0))
+            .with_hillshade_base(Composite::new_rgb(vec![0.0; 3], vec![1.0; 3]));
+        // `with_hillshade_base` only takes effect for gradient-based composites, so this behaves
+        // like a plain hillshade.
+        assert_eq!(comp.get(&[1.0], None), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_nearest_color_disabled_stays_transparent() {
+        let comp = Composite::new_discrete_palette(vec![
+            (0, Colour::from((255, 0, 0, 255))),
+            (1, Colour::from((0, 0, 255, 255))),
+        ]);
+        assert_eq!(comp.get(&[3.0], None), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_color_matrix_disabled_by_default() {
+        let comp = Composite::new_rgb(vec![0.0, 0.0, 0.0], vec![100.0, 100.0, 100.0]);
+        assert_eq!(comp.get(&[0.0, 50.0, 100.0], None), [0, 127, 255, 255]);
+    }
+
+    #[test]
+    fn test_color_matrix_desaturates_rgb_composite() {
+        let comp = Composite::new_rgb(vec![0.0, 0.0, 0.0], vec![100.0, 100.0, 100.0])
+            .with_color_matrix(ColorMatrix::saturate(0.0));
+        let [r, g, b, a] = comp.get(&[100.0, 0.0, 0.0], None);
+        assert_eq!(r, g);
+        assert_eq!(g, b);
+        assert_eq!(a, 255);
+    }
+
+    #[test]
+    fn test_equal_interval_breaks_are_evenly_spaced() {
+        let breaks = classify_breaks(&[0.0, 10.0, 20.0, 100.0], 4, ClassifyMethod::EqualInterval);
+        assert_eq!(breaks, vec![0.0, 25.0, 50.0, 75.0, 100.0]);
+    }
+
+    #[test]
+    fn test_quantile_breaks_split_sample_counts_evenly() {
+        let samples: Vec<f64> = (0..=100).map(|v| v as f64).collect();
+        let breaks = classify_breaks(&samples, 4, ClassifyMethod::Quantile);
+        assert_eq!(breaks[0], 0.0);
+        assert_eq!(breaks[4], 100.0);
+        assert!((breaks[2] - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_natural_breaks_separates_clusters() {
+        // Two tight clusters far apart: the natural break is the top of the lower cluster, not
+        // the midpoint (50.0) an equal-interval split would pick.
+        let samples = vec![0.0, 1.0, 2.0, 98.0, 99.0, 100.0];
+        let breaks = classify_breaks(&samples, 2, ClassifyMethod::NaturalBreaks);
+        assert_eq!(breaks[0], 0.0);
+        assert_eq!(breaks[2], 100.0);
+        assert_eq!(breaks[1], 2.0);
+    }
+
+    #[test]
+    fn test_classify_from_samples_feeds_gradient_with_breaks() {
+        let samples = vec![0.0, 1.0, 2.0, 3.0, 100.0];
+        let comp = Composite::classify_from_samples(&samples, 4, ClassifyMethod::Quantile, &viridis);
+        assert_eq!(comp.get(&[0.0], None), [68, 1, 84, 255]); // viridis(0.0)
+        assert_eq!(comp.get(&[100.0], None), [253, 231, 36, 255]); // viridis(100.0)
+    }
+
+    #[test]
+    fn test_color_matrix_swaps_channels() {
+        // Swap R and B, leave G and A untouched.
+        #[rustfmt::skip]
+        let swap_rb = ColorMatrix::new([
+            0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 0.0,
+            1.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ]);
+        let comp = Composite::new_rgb(vec![0.0, 0.0, 0.0], vec![100.0, 100.0, 100.0])
+            .with_color_matrix(swap_rb);
+        assert_eq!(comp.get(&[0.0, 50.0, 100.0], None), [255, 127, 0, 255]);
+    }
 }
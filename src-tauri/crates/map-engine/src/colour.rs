@@ -2,7 +2,7 @@
 use palette::{
     encoding::{Linear, Srgb},
     rgb::Rgb,
-    Alpha,
+    Alpha, FromColor, Hsl, Hsv,
 };
 use serde::{
     de::{self, SeqAccess, Unexpected, Visitor},
@@ -34,6 +34,10 @@ type HexString = String;
 /// # Ok(())
 /// # }
 /// ```
+///
+/// When deserializing from JSON, a string may also be a CSS named colour (`"steelblue"`) or
+/// `hsl(...)`/`hsv(...)` function notation (`"hsl(210, 50%, 40%)"`), so style files authored by
+/// non-programmers don't need hand-computed hex. See [`Deserialize`] below.
 #[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(untagged)]
 pub enum Colour {
@@ -54,7 +58,8 @@ impl<'de> Deserialize<'de> for Colour {
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
                 write!(
                     formatter,
-                    "4 values (r, g, b, a) in the range 0.0-1.0 or 0-255, or a hex colour"
+                    "4 values (r, g, b, a) in the range 0.0-1.0 or 0-255, a hex colour, a CSS \
+                     named colour, or hsl(...)/hsv(...) notation"
                 )
             }
 
@@ -62,10 +67,16 @@ impl<'de> Deserialize<'de> for Colour {
             where
                 E: de::Error,
             {
-                match decode_hex(s) {
-                    Ok(c) => Ok(Colour::Seq(c)),
-                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(s), &self)),
+                if let Ok(c) = decode_hex(s) {
+                    return Ok(Colour::Seq(c));
+                }
+                if let Some(c) = decode_functional(s) {
+                    return Ok(Colour::Seq(c));
+                }
+                if let Some(c) = decode_named(s) {
+                    return Ok(Colour::Seq(c));
                 }
+                Err(de::Error::invalid_value(Unexpected::Str(s), &self))
             }
 
             fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
@@ -111,11 +122,26 @@ impl<'de> Deserialize<'de> for Colour {
     }
 }
 
-fn decode_hex(s: &str) -> Result<RgbaComponents, ParseIntError> {
+/// Why a string failed to parse as a hex colour.
+#[derive(Debug, thiserror::Error)]
+pub enum HexColourError {
+    #[error("hex colour must have 3, 4, 6 or 8 hex digits (after an optional '#'), got {0}")]
+    InvalidLength(usize),
+    #[error(transparent)]
+    InvalidDigit(#[from] ParseIntError),
+}
+
+fn decode_hex(s: &str) -> Result<RgbaComponents, HexColourError> {
     let s = s.trim_start_matches('#');
-    let mut v: Vec<u8> = (0..s.len())
+    // Expand shorthand notation (`f00` -> `ff0000`, `f00a` -> `ff0000aa`) by doubling each nibble.
+    let expanded: String = match s.len() {
+        3 | 4 => s.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => s.to_string(),
+        n => return Err(HexColourError::InvalidLength(n)),
+    };
+    let mut v: Vec<u8> = (0..expanded.len())
         .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+        .map(|i| u8::from_str_radix(&expanded[i..i + 2], 16))
         .collect::<Result<_, _>>()?;
     if v.len() == 3 {
         v.extend_from_slice(&[255])
@@ -128,6 +154,46 @@ fn decode_hex(s: &str) -> Result<RgbaComponents, ParseIntError> {
     ))
 }
 
+/// Parse `hsl(h, s%, l%)`/`hsla(h, s%, l%, a)` or `hsv(h, s%, v%)`/`hsva(h, s%, v%, a)` function
+/// notation, converting through `palette`'s `Hsl`/`Hsv` -> sRGB paths. `None` for anything that
+/// isn't recognisably one of these four functions.
+fn decode_functional(s: &str) -> Option<RgbaComponents> {
+    let s = s.trim();
+    let (name, rest) = s.split_once('(')?;
+    let rest = rest.strip_suffix(')')?;
+    let parts: Vec<f64> = rest
+        .split(',')
+        .map(|p| p.trim().trim_end_matches('%').trim().parse().ok())
+        .collect::<Option<_>>()?;
+
+    let (h, s_pct, l_or_v, a) = match parts.as_slice() {
+        [h, s, l] => (*h, *s, *l, 1.0),
+        [h, s, l, a] => (*h, *s, *l, *a),
+        _ => return None,
+    };
+
+    let (r, g, b) = match name.trim() {
+        "hsl" | "hsla" => {
+            Rgb::<Srgb, f64>::from_color(Hsl::<Srgb, f64>::new(h, s_pct / 100.0, l_or_v / 100.0))
+                .into_components()
+        }
+        "hsv" | "hsva" => {
+            Rgb::<Srgb, f64>::from_color(Hsv::<Srgb, f64>::new(h, s_pct / 100.0, l_or_v / 100.0))
+                .into_components()
+        }
+        _ => return None,
+    };
+
+    Some((r, g, b, a))
+}
+
+/// Look up a CSS named colour (e.g. `"steelblue"`), case-insensitively. `None` if `s` isn't one
+/// of the named colours `palette` knows about.
+fn decode_named(s: &str) -> Option<RgbaComponents> {
+    let (r, g, b) = palette::named::from_str(&s.to_lowercase())?.into_components();
+    Some((r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0, 1.0))
+}
+
 impl From<RgbaComponents> for Colour {
     fn from(comp: RgbaComponents) -> Self {
         Self::Seq(comp)
@@ -146,7 +212,7 @@ impl From<(u8, u8, u8, u8)> for Colour {
 }
 
 impl TryFrom<&str> for Colour {
-    type Error = ParseIntError;
+    type Error = HexColourError;
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         Ok(Self::Seq(decode_hex(s)?))
     }
@@ -169,6 +235,28 @@ impl From<Alpha<Rgb<Linear<Srgb>, f64>, f64>> for Colour {
     }
 }
 
+impl Colour {
+    /// Render as a canonical `#rrggbbaa` hex string, the inverse of [`Colour::try_from`]/
+    /// deserializing a hex string. Lets a style loaded from JSON, mutated in code, and
+    /// re-serialized round-trip losslessly.
+    pub fn to_hex(&self) -> String {
+        let (r, g, b, a): RgbaComponents = self.clone().into();
+        format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            (r * 255.0).round() as u8,
+            (g * 255.0).round() as u8,
+            (b * 255.0).round() as u8,
+            (a * 255.0).round() as u8,
+        )
+    }
+}
+
+impl fmt::Display for Colour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,6 +272,26 @@ mod tests {
         assert_eq!(decode_hex("#ff0000").unwrap(), expected_comp);
     }
 
+    #[test]
+    fn test_decode_hex_shorthand() {
+        let expected_comp: RgbaComponents = (1., 0., 0., 1.);
+        assert_eq!(decode_hex("#f00").unwrap(), expected_comp);
+        assert_eq!(decode_hex("#f00f").unwrap(), expected_comp);
+        assert_eq!(decode_hex("#f008").unwrap(), (1., 0., 0., 0x88 as f64 / 255.0));
+    }
+
+    #[test]
+    fn test_decode_hex_rejects_bad_length() {
+        assert!(matches!(
+            decode_hex("#ff000"),
+            Err(HexColourError::InvalidLength(5))
+        ));
+        assert!(matches!(
+            decode_hex("#ff0000ff0"),
+            Err(HexColourError::InvalidLength(9))
+        ));
+    }
+
     #[test]
     fn test_colour_from() {
         assert_eq!(
@@ -236,9 +344,59 @@ mod tests {
         let s = "\"ff0000gg\"";
         let col: Result<Colour, _> = serde_json::from_str(s);
         let expected_msg =
-            "invalid value: string \"ff0000gg\", expected 4 values (r, g, b, a) in the range 0.0-1.0 or 0-255, or a hex colour at line 1 column 10";
+            "invalid value: string \"ff0000gg\", expected 4 values (r, g, b, a) in the range 0.0-1.0 or 0-255, a hex colour, a CSS named colour, or hsl(...)/hsv(...) notation at line 1 column 10";
         if let Err(err) = col {
             assert_eq!(format!("{}", err), expected_msg.to_string())
         };
     }
+
+    #[test]
+    fn test_decode_named_colour() {
+        assert_eq!(decode_named("red").unwrap(), (1.0, 0.0, 0.0, 1.0));
+        assert_eq!(decode_named("STEELBLUE"), decode_named("steelblue"));
+        assert_eq!(decode_named("notacolour"), None);
+    }
+
+    #[test]
+    fn test_decode_functional_hsl_and_hsv() {
+        let (r, g, b, a) = decode_functional("hsl(0, 100%, 50%)").unwrap();
+        assert!((r - 1.0).abs() < 1e-6);
+        assert!(g.abs() < 1e-6);
+        assert!(b.abs() < 1e-6);
+        assert_eq!(a, 1.0);
+
+        let (r, g, b, a) = decode_functional("hsla(0, 100%, 50%, 0.5)").unwrap();
+        assert!((r - 1.0).abs() < 1e-6);
+        assert!(g.abs() < 1e-6);
+        assert!(b.abs() < 1e-6);
+        assert_eq!(a, 0.5);
+
+        assert!(decode_functional("hsv(0, 100%, 100%)").is_some());
+        assert_eq!(decode_functional("not a function"), None);
+    }
+
+    #[test]
+    fn test_colour_is_deserialized_from_named_and_functional() {
+        let col: Colour = serde_json::from_str("\"steelblue\"").unwrap();
+        assert_eq!(col, Colour::Seq(decode_named("steelblue").unwrap()));
+
+        let col: Colour = serde_json::from_str("\"hsl(210, 50%, 40%)\"").unwrap();
+        assert_eq!(col, Colour::Seq(decode_functional("hsl(210, 50%, 40%)").unwrap()));
+    }
+
+    #[test]
+    fn test_to_hex_round_trips() {
+        let col = Colour::Seq((1.0, 0.0, 0.0, 1.0));
+        assert_eq!(col.to_hex(), "#ff0000ff");
+        assert_eq!(format!("{}", col), "#ff0000ff");
+
+        let round_tripped = Colour::try_from(col.to_hex().as_str()).unwrap();
+        assert_eq!(round_tripped, col);
+    }
+
+    #[test]
+    fn test_to_hex_from_json_mutated_and_reserialized() {
+        let col: Colour = serde_json::from_str("\"#f00a\"").unwrap();
+        assert_eq!(col.to_hex(), "#ff0000aa");
+    }
 }
@@ -0,0 +1,481 @@
+//! Multi-layer blend compositing of [`StyledPixels`](crate::raster::StyledPixels).
+//!
+//! A [`LayerStack`] stacks several already-styled rasters, e.g. a viridis data layer over a
+//! hillshade or an RGB basemap, into a single tile. Each [`Layer`] carries its own [`Window`]
+//! (so layers covering different extents can be combined), a [`BlendMode`] for how its colour
+//! mixes with what's beneath it and a [`CompositeOp`] for how its coverage (alpha) combines with
+//! the backdrop's, following the Porter-Duff compositing model.
+use crate::raster::pixels::driver::Driver;
+use crate::raster::StyledPixels;
+use crate::windows::{intersection, Window};
+use ndarray::Array3;
+
+/// A separable blend function, applied channel-wise to unpremultiplied colour before compositing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The source colour replaces the backdrop outright: `blend(s, d) = s`.
+    Normal,
+    /// `s * d`.
+    Multiply,
+    /// `s + d - s*d`.
+    Screen,
+    /// `d < 0.5 ? 2*s*d : 1 - 2*(1-s)*(1-d)`.
+    Overlay,
+    /// `min(s, d)`.
+    Darken,
+    /// `max(s, d)`.
+    Lighten,
+    /// `Overlay` with its arguments swapped: `s < 0.5 ? 2*s*d : 1 - 2*(1-s)*(1-d)`.
+    HardLight,
+    /// The W3C `soft-light` formula.
+    SoftLight,
+    /// `|s - d|`.
+    Difference,
+    /// `s + d - 2*s*d`.
+    Exclusion,
+}
+
+impl BlendMode {
+    fn blend(&self, s: f32, d: f32) -> f32 {
+        match self {
+            Self::Normal => s,
+            Self::Multiply => s * d,
+            Self::Screen => s + d - s * d,
+            Self::Overlay => {
+                if d < 0.5 {
+                    2.0 * s * d
+                } else {
+                    1.0 - 2.0 * (1.0 - s) * (1.0 - d)
+                }
+            }
+            Self::Darken => s.min(d),
+            Self::Lighten => s.max(d),
+            Self::HardLight => {
+                if s < 0.5 {
+                    2.0 * s * d
+                } else {
+                    1.0 - 2.0 * (1.0 - s) * (1.0 - d)
+                }
+            }
+            Self::SoftLight => {
+                if s <= 0.5 {
+                    d - (1.0 - 2.0 * s) * d * (1.0 - d)
+                } else {
+                    let g = if d <= 0.25 {
+                        ((16.0 * d - 12.0) * d + 4.0) * d
+                    } else {
+                        d.sqrt()
+                    };
+                    d + (2.0 * s - 1.0) * (g - d)
+                }
+            }
+            Self::Difference => (s - d).abs(),
+            Self::Exclusion => s + d - 2.0 * s * d,
+        }
+    }
+}
+
+/// A Porter-Duff compositing operator, controlling how a layer's coverage combines with the
+/// backdrop's rather than its colour (see [`BlendMode`] for that).
+///
+/// Expressed as the `(Fa, Fb)` factors applied to the (blend-mode-adjusted, premultiplied)
+/// source and destination respectively: `result = source * Fa + backdrop * Fb`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositeOp {
+    /// The layer is placed over the backdrop, which shows through where the layer is
+    /// transparent. The usual choice for stacking map layers.
+    Over,
+    /// Only the part of the layer inside the backdrop's coverage survives.
+    In,
+    /// Only the part of the layer outside the backdrop's coverage survives.
+    Out,
+    /// The part of the layer inside the backdrop's coverage is placed over it.
+    Atop,
+    /// The non-overlapping parts of the layer and backdrop, exclusive-or'd together.
+    Xor,
+}
+
+impl CompositeOp {
+    fn factors(&self, sa: f32, da: f32) -> (f32, f32) {
+        match self {
+            Self::Over => (1.0, 1.0 - sa),
+            Self::In => (da, 0.0),
+            Self::Out => (1.0 - da, 0.0),
+            Self::Atop => (da, 1.0 - sa),
+            Self::Xor => (1.0 - da, 1.0 - sa),
+        }
+    }
+}
+
+/// One layer of a [`LayerStack`]: a styled raster positioned at a [`Window`], with an opacity
+/// and how it blends and composites into the layers below it.
+pub struct Layer {
+    pub pixels: StyledPixels,
+    /// Where this layer sits in the stack's pixel grid. Only the part of the layer that
+    /// intersects the stack's canvas (and, transitively, the layers below it) contributes.
+    pub window: Window,
+    /// `0.0` (fully transparent) to `1.0` (fully opaque), multiplied into the layer's own alpha.
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+    pub composite_op: CompositeOp,
+}
+
+impl Layer {
+    pub fn new(pixels: StyledPixels, window: Window, opacity: f32, blend_mode: BlendMode) -> Self {
+        Self {
+            pixels,
+            window,
+            opacity,
+            blend_mode,
+            composite_op: CompositeOp::Over,
+        }
+    }
+
+    pub fn with_composite_op(mut self, composite_op: CompositeOp) -> Self {
+        self.composite_op = composite_op;
+        self
+    }
+}
+
+/// Stacks [`Layer`]s, bottom-to-top, into a single [`StyledPixels`] the size of `canvas`.
+pub struct LayerStack {
+    canvas: Window,
+    layers: Vec<Layer>,
+}
+
+impl LayerStack {
+    /// Create an empty stack producing a tile the size of `canvas`.
+    pub fn new(canvas: Window) -> Self {
+        Self {
+            canvas,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Append a layer to the top of the stack.
+    pub fn push(&mut self, layer: Layer) -> &mut Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Composite the stack into a single [`StyledPixels`] the size of the stack's canvas.
+    ///
+    /// Each layer is clipped to its intersection with the canvas via [`intersection`], blended
+    /// with [`BlendMode::blend`] (operating on unpremultiplied colour) and composited over the
+    /// accumulated result so far with its [`CompositeOp`], in premultiplied-alpha space. Returns
+    /// fully transparent pixels the size of the canvas for an empty stack.
+    pub fn composite(self) -> StyledPixels {
+        let (height, width) = (self.canvas.height, self.canvas.width);
+        let mut acc = Array3::<f32>::zeros((height, width, 4));
+
+        for layer in self.layers {
+            let inter = match intersection(&[self.canvas, layer.window]) {
+                Some(inter) => inter,
+                None => continue,
+            };
+            let src = layer.pixels.into_array().mapv(|v| v as f32 / 255.0);
+            let acc_row_off = (inter.row_off - self.canvas.row_off) as usize;
+            let acc_col_off = (inter.col_off - self.canvas.col_off) as usize;
+            let src_row_off = (inter.row_off - layer.window.row_off) as usize;
+            let src_col_off = (inter.col_off - layer.window.col_off) as usize;
+
+            for row in 0..inter.height {
+                for col in 0..inter.width {
+                    let acc_idx = (acc_row_off + row, acc_col_off + col);
+                    let src_idx = (src_row_off + row, src_col_off + col);
+                    over(
+                        &mut acc,
+                        acc_idx,
+                        &src,
+                        src_idx,
+                        layer.opacity,
+                        layer.blend_mode,
+                        layer.composite_op,
+                    );
+                }
+            }
+        }
+
+        let straight = unpremultiply(&acc);
+        let data = straight.mapv(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+        StyledPixels::new(data, Driver::Generic)
+    }
+}
+
+/// Blend and composite a single pixel of `src` (straight RGBA, `[0, 1]`) into `acc` (premultiplied
+/// RGBA accumulator), in place.
+#[allow(clippy::too_many_arguments)]
+fn over(
+    acc: &mut Array3<f32>,
+    (ay, ax): (usize, usize),
+    src: &Array3<f32>,
+    (sy, sx): (usize, usize),
+    opacity: f32,
+    blend_mode: BlendMode,
+    composite_op: CompositeOp,
+) {
+    let [sr, sg, sb, sa] = [
+        src[[sy, sx, 0]],
+        src[[sy, sx, 1]],
+        src[[sy, sx, 2]],
+        src[[sy, sx, 3]],
+    ];
+    let sa = sa * opacity;
+
+    let da = acc[[ay, ax, 3]];
+    let [dr, dg, db] = if da > 0.0 {
+        [
+            acc[[ay, ax, 0]] / da,
+            acc[[ay, ax, 1]] / da,
+            acc[[ay, ax, 2]] / da,
+        ]
+    } else {
+        [0.0, 0.0, 0.0]
+    };
+
+    let blended = [
+        blend_mode.blend(sr, dr),
+        blend_mode.blend(sg, dg),
+        blend_mode.blend(sb, db),
+    ];
+
+    let (fa, fb) = composite_op.factors(sa, da);
+    for c in 0..3 {
+        let src_premul = blended[c] * sa;
+        let dst_premul = acc[[ay, ax, c]];
+        acc[[ay, ax, c]] = src_premul * fa + dst_premul * fb;
+    }
+    acc[[ay, ax, 3]] = sa * fa + da * fb;
+}
+
+fn unpremultiply(buf: &Array3<f32>) -> Array3<f32> {
+    let mut out = buf.clone();
+    let (height, width, _) = out.dim();
+    for y in 0..height {
+        for x in 0..width {
+            let a = out[[y, x, 3]];
+            if a > 0.0 {
+                out[[y, x, 0]] /= a;
+                out[[y, x, 1]] /= a;
+                out[[y, x, 2]] /= a;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(rgba: [u8; 4]) -> StyledPixels {
+        let data = Array3::from_shape_fn((2, 2, 4), |(_, _, c)| rgba[c]);
+        StyledPixels::new(data, Driver::Generic)
+    }
+
+    fn full_window() -> Window {
+        Window::new(0, 0, 2, 2)
+    }
+
+    #[test]
+    fn test_composite_empty_stack_is_transparent() {
+        let stack = LayerStack::new(full_window());
+        let result = stack.composite().into_array();
+        assert_eq!(result, Array3::<u8>::zeros((2, 2, 4)));
+    }
+
+    #[test]
+    fn test_composite_single_layer_passes_through() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([10, 20, 30, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 10);
+        assert_eq!(out[[0, 0, 1]], 20);
+        assert_eq!(out[[0, 0, 2]], 30);
+        assert_eq!(out[[0, 0, 3]], 255);
+    }
+
+    #[test]
+    fn test_composite_opacity_fades_top_layer() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([0, 0, 0, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([255, 255, 255, 255]),
+            full_window(),
+            0.5,
+            BlendMode::Normal,
+        ));
+        let out = stack.composite().into_array();
+        // Half-opacity white over black should land roughly in the middle.
+        assert!((out[[0, 0, 0]] as i32 - 127).abs() <= 1);
+    }
+
+    #[test]
+    fn test_composite_transparent_top_layer_reveals_bottom() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([200, 100, 50, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([255, 0, 0, 255]),
+            full_window(),
+            0.0,
+            BlendMode::Normal,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 200);
+        assert_eq!(out[[0, 0, 1]], 100);
+        assert_eq!(out[[0, 0, 2]], 50);
+    }
+
+    #[test]
+    fn test_blend_multiply_of_black_is_black() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([255, 255, 255, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([0, 0, 0, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Multiply,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 0);
+    }
+
+    #[test]
+    fn test_blend_screen_of_white_is_white() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([0, 0, 0, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([255, 255, 255, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Screen,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 255);
+    }
+
+    #[test]
+    fn test_blend_darken_and_lighten() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([200, 50, 50, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([100, 255, 255, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Darken,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 100); // min(200, 100)
+        assert_eq!(out[[0, 0, 1]], 50); // min(50, 255)
+
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([200, 50, 50, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([100, 255, 255, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Lighten,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 200); // max(200, 100)
+        assert_eq!(out[[0, 0, 1]], 255); // max(50, 255)
+    }
+
+    #[test]
+    fn test_blend_difference_and_exclusion() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([200, 50, 50, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([100, 100, 100, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Difference,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 100); // |200 - 100|
+        assert_eq!(out[[0, 0, 1]], 50); // |50 - 100|
+    }
+
+    #[test]
+    fn test_composite_op_in_clips_to_backdrop_coverage() {
+        let mut stack = LayerStack::new(full_window());
+        // Bottom layer only covers the left column.
+        stack.push(Layer::new(
+            solid([0, 255, 0, 255]),
+            Window::new(0, 0, 1, 2),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(
+            Layer::new(solid([255, 0, 0, 255]), full_window(), 1.0, BlendMode::Normal)
+                .with_composite_op(CompositeOp::In),
+        );
+        let out = stack.composite().into_array();
+        // Left column: backdrop present, "in" layer shows through.
+        assert_eq!(out[[0, 0, 0]], 255);
+        assert_eq!(out[[0, 0, 3]], 255);
+        // Right column: no backdrop coverage, so the "in" layer is clipped away entirely.
+        assert_eq!(out[[0, 1, 3]], 0);
+    }
+
+    #[test]
+    fn test_layer_outside_canvas_is_skipped() {
+        let mut stack = LayerStack::new(full_window());
+        stack.push(Layer::new(
+            solid([10, 20, 30, 255]),
+            full_window(),
+            1.0,
+            BlendMode::Normal,
+        ));
+        stack.push(Layer::new(
+            solid([255, 0, 0, 255]),
+            Window::new(10, 10, 2, 2),
+            1.0,
+            BlendMode::Normal,
+        ));
+        let out = stack.composite().into_array();
+        assert_eq!(out[[0, 0, 0]], 10);
+        assert_eq!(out[[0, 0, 1]], 20);
+        assert_eq!(out[[0, 0, 2]], 30);
+    }
+}
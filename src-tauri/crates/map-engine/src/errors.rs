@@ -27,6 +27,12 @@ pub enum MapEngineError {
     ShapeError(#[from] ShapeError),
     #[error(transparent)]
     ParseIntError(#[from] ParseIntError),
+    #[error(transparent)]
+    ExrError(#[from] exr::error::Error),
+    #[error(transparent)]
+    HexColourError(#[from] crate::colour::HexColourError),
+    #[error(transparent)]
+    ImageError(#[from] image::error::ImageError),
     // #[error(transparent)]
     // MapnikError(#[from] MapnikError),
     // #[error(transparent)]
@@ -0,0 +1,564 @@
+//! A post-styling filter pipeline for [`StyledPixels`](crate::raster::StyledPixels), modeled on
+//! the SVG filter primitives (`feColorMatrix`, `feComponentTransfer`, `feConvolveMatrix`,
+//! `feGaussianBlur`).
+//!
+//! A [`FilterChain`] is an ordered list of [`Filter`]s applied, in order, to the RGBA buffer
+//! produced by [`RawPixels::style`](crate::raster::RawPixels::style) before it's handed to
+//! [`into_png`](crate::raster::StyledPixels::into_png). All math is done in `f32` over pixel
+//! values normalised to `[0, 1]`, clamping back to `u8` at the end of the chain.
+//!
+//! [`ColorMatrix`] and [`ComponentTransfer`] run on straight (non-premultiplied) alpha, matching
+//! the SVG spec. [`ConvolveMatrix`] and [`GaussianBlur`] premultiply alpha first (and
+//! unpremultiply afterwards) so that convolving near transparent pixels doesn't bleed their
+//! (meaningless) colour into opaque neighbours.
+use crate::errors::MapEngineError;
+use ndarray::Array3;
+
+/// A 4x5 matrix applied to each pixel's `[r, g, b, a, 1]` vector to produce a new `[r, g, b, a]`.
+///
+/// Mirrors SVG's `feColorMatrix`. The constructors provide the common presets; [`ColorMatrix::new`]
+/// accepts an arbitrary matrix, row-major (`row * 5 + col`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorMatrix([f32; 20]);
+
+impl ColorMatrix {
+    /// An arbitrary 4x5 matrix, row-major (4 output channels, 5 input terms `[r, g, b, a, 1]`).
+    pub fn new(matrix: [f32; 20]) -> Self {
+        Self(matrix)
+    }
+
+    /// The identity matrix: output equals input.
+    pub fn identity() -> Self {
+        Self([
+            1.0, 0.0, 0.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 1.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ])
+    }
+
+    /// Saturation adjustment, as per the SVG `feColorMatrix type="saturate"` coefficients.
+    /// `amount == 1.0` is the identity; `0.0` is fully desaturated (greyscale).
+    pub fn saturate(amount: f32) -> Self {
+        Self([
+            0.213 + 0.787 * amount,
+            0.715 - 0.715 * amount,
+            0.072 - 0.072 * amount,
+            0.0,
+            0.0,
+            0.213 - 0.213 * amount,
+            0.715 + 0.285 * amount,
+            0.072 - 0.072 * amount,
+            0.0,
+            0.0,
+            0.213 - 0.213 * amount,
+            0.715 - 0.715 * amount,
+            0.072 + 0.928 * amount,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+            0.0,
+        ])
+    }
+
+    /// Hue rotation by `degrees`, as per the SVG `feColorMatrix type="hueRotate"` coefficients.
+    pub fn hue_rotate(degrees: f32) -> Self {
+        let rad = degrees.to_radians();
+        let (sin, cos) = (rad.sin(), rad.cos());
+        Self([
+            0.213 + cos * 0.787 - sin * 0.213,
+            0.715 - cos * 0.715 - sin * 0.715,
+            0.072 - cos * 0.072 + sin * 0.928,
+            0.0,
+            0.0,
+            0.213 - cos * 0.213 + sin * 0.143,
+            0.715 + cos * 0.285 + sin * 0.140,
+            0.072 - cos * 0.072 - sin * 0.283,
+            0.0,
+            0.0,
+            0.213 - cos * 0.213 - sin * 0.787,
+            0.715 - cos * 0.715 + sin * 0.715,
+            0.072 + cos * 0.928 + sin * 0.072,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+            0.0,
+        ])
+    }
+
+    /// Collapses RGB into the alpha channel using the Rec. 709 luminance weights, leaving colour
+    /// channels at 0. As per the SVG `feColorMatrix type="luminanceToAlpha"` coefficients.
+    pub fn luminance_to_alpha() -> Self {
+        Self([
+            0.0, 0.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 0.0, 0.0, //
+            0.2125, 0.7154, 0.0721, 0.0, 0.0,
+        ])
+    }
+
+    pub(crate) fn apply(&self, pixel: [f32; 4]) -> [f32; 4] {
+        let [r, g, b, a] = pixel;
+        let m = &self.0;
+        [
+            m[0] * r + m[1] * g + m[2] * b + m[3] * a + m[4],
+            m[5] * r + m[6] * g + m[7] * b + m[8] * a + m[9],
+            m[10] * r + m[11] * g + m[12] * b + m[13] * a + m[14],
+            m[15] * r + m[16] * g + m[17] * b + m[18] * a + m[19],
+        ]
+    }
+}
+
+/// A per-channel transfer function, as per SVG's `feComponentTransfer` `feFuncR/G/B/A`.
+///
+/// All functions operate on, and produce, values in `[0, 1]` (clamped after application).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferFunction {
+    /// `out = in` unchanged.
+    Identity,
+    /// `out = amplitude * in^exponent + offset`.
+    Gamma {
+        amplitude: f32,
+        exponent: f32,
+        offset: f32,
+    },
+    /// `out = slope * in + intercept`.
+    Linear { slope: f32, intercept: f32 },
+    /// A lookup table, linearly interpolated between evenly-spaced entries over `[0, 1]`.
+    Table(Vec<f32>),
+}
+
+impl TransferFunction {
+    fn apply(&self, x: f32) -> f32 {
+        match self {
+            Self::Identity => x,
+            Self::Gamma {
+                amplitude,
+                exponent,
+                offset,
+            } => amplitude * x.powf(*exponent) + offset,
+            Self::Linear { slope, intercept } => slope * x + intercept,
+            Self::Table(table) => match table.len() {
+                0 => x,
+                1 => table[0],
+                n => {
+                    let x = x.clamp(0.0, 1.0);
+                    let segments = (n - 1) as f32;
+                    let pos = (x * segments).min(segments);
+                    let k = (pos as usize).min(n - 2);
+                    let frac = pos - k as f32;
+                    table[k] + frac * (table[k + 1] - table[k])
+                }
+            },
+        }
+    }
+}
+
+impl Default for TransferFunction {
+    fn default() -> Self {
+        Self::Identity
+    }
+}
+
+/// Independent [`TransferFunction`]s for the red, green, blue and alpha channels.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ComponentTransfer {
+    pub r: TransferFunction,
+    pub g: TransferFunction,
+    pub b: TransferFunction,
+    pub a: TransferFunction,
+}
+
+impl ComponentTransfer {
+    fn apply(&self, pixel: [f32; 4]) -> [f32; 4] {
+        [
+            self.r.apply(pixel[0]).clamp(0.0, 1.0),
+            self.g.apply(pixel[1]).clamp(0.0, 1.0),
+            self.b.apply(pixel[2]).clamp(0.0, 1.0),
+            self.a.apply(pixel[3]).clamp(0.0, 1.0),
+        ]
+    }
+}
+
+/// How [`ConvolveMatrix`] samples pixels that fall outside the image bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// Clamp to the nearest edge pixel.
+    Extend,
+    /// Wrap around to the opposite edge.
+    Wrap,
+    /// Treat out-of-bounds samples as transparent black.
+    None,
+}
+
+impl Default for EdgeMode {
+    fn default() -> Self {
+        Self::Extend
+    }
+}
+
+/// An arbitrary convolution kernel, as per SVG's `feConvolveMatrix`.
+///
+/// Runs in premultiplied-alpha space (see the [module docs](self)). The kernel's target pixel is
+/// its centre (`rows / 2`, `cols / 2`, integer division), matching the SVG default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvolveMatrix {
+    kernel: Vec<f32>,
+    rows: usize,
+    cols: usize,
+    divisor: f32,
+    bias: f32,
+    edge_mode: EdgeMode,
+}
+
+impl ConvolveMatrix {
+    /// Build a convolution kernel. `kernel` must have exactly `rows * cols` entries.
+    ///
+    /// The divisor defaults to the sum of the kernel's entries (or `1.0` if that sum is zero, as
+    /// per the SVG spec); override it with [`ConvolveMatrix::with_divisor`].
+    pub fn new(
+        kernel: Vec<f32>,
+        rows: usize,
+        cols: usize,
+        bias: f32,
+        edge_mode: EdgeMode,
+    ) -> Result<Self, MapEngineError> {
+        if kernel.len() != rows * cols {
+            return Err(MapEngineError::Msg(format!(
+                "ConvolveMatrix kernel has {} entries, expected {}x{} = {}",
+                kernel.len(),
+                rows,
+                cols,
+                rows * cols
+            )));
+        }
+        let sum: f32 = kernel.iter().sum();
+        let divisor = if sum == 0.0 { 1.0 } else { sum };
+        Ok(Self {
+            kernel,
+            rows,
+            cols,
+            divisor,
+            bias,
+            edge_mode,
+        })
+    }
+
+    /// Override the default (sum-of-kernel) divisor.
+    pub fn with_divisor(mut self, divisor: f32) -> Self {
+        self.divisor = divisor;
+        self
+    }
+
+    fn sample(&self, buf: &Array3<f32>, y: isize, x: isize, c: usize) -> f32 {
+        let (height, width) = (buf.shape()[0] as isize, buf.shape()[1] as isize);
+        let (y, x) = match self.edge_mode {
+            EdgeMode::Extend => (y.clamp(0, height - 1), x.clamp(0, width - 1)),
+            EdgeMode::Wrap => (y.rem_euclid(height), x.rem_euclid(width)),
+            EdgeMode::None => {
+                if y < 0 || y >= height || x < 0 || x >= width {
+                    return 0.0;
+                }
+                (y, x)
+            }
+        };
+        buf[[y as usize, x as usize, c]]
+    }
+
+    fn apply(&self, buf: &Array3<f32>) -> Array3<f32> {
+        let (height, width, channels) = buf.dim();
+        let target_y = (self.rows / 2) as isize;
+        let target_x = (self.cols / 2) as isize;
+        let mut out = Array3::<f32>::zeros((height, width, channels));
+        for y in 0..height {
+            for x in 0..width {
+                for c in 0..channels {
+                    let mut acc = 0.0;
+                    for ky in 0..self.rows {
+                        for kx in 0..self.cols {
+                            let weight = self.kernel[ky * self.cols + kx];
+                            let sy = y as isize + ky as isize - target_y;
+                            let sx = x as isize + kx as isize - target_x;
+                            acc += weight * self.sample(buf, sy, sx, c);
+                        }
+                    }
+                    out[[y, x, c]] = acc / self.divisor + self.bias;
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A separable Gaussian blur, run horizontally then vertically (two 1-D passes).
+///
+/// Runs in premultiplied-alpha space (see the [module docs](self)). Edge pixels are handled by
+/// clamping to the nearest edge, matching [`EdgeMode::Extend`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GaussianBlur {
+    sigma: f32,
+}
+
+impl GaussianBlur {
+    pub fn new(sigma: f32) -> Self {
+        Self { sigma }
+    }
+
+    /// `[ceil(3*sigma) * -1, ..., ceil(3*sigma)]`, weights `exp(-x^2/(2*sigma^2))` normalized to
+    /// sum 1.
+    fn kernel(&self) -> Vec<f32> {
+        if self.sigma <= 0.0 {
+            return vec![1.0];
+        }
+        let radius = (3.0 * self.sigma).ceil() as isize;
+        let raw: Vec<f32> = (-radius..=radius)
+            .map(|x| (-((x * x) as f32) / (2.0 * self.sigma * self.sigma)).exp())
+            .collect();
+        let sum: f32 = raw.iter().sum();
+        raw.into_iter().map(|w| w / sum).collect()
+    }
+
+    fn apply(&self, buf: &Array3<f32>) -> Array3<f32> {
+        let kernel = self.kernel();
+        let radius = (kernel.len() / 2) as isize;
+        let (height, width, channels) = buf.dim();
+
+        let mut horizontal = Array3::<f32>::zeros((height, width, channels));
+        for y in 0..height {
+            for x in 0..width {
+                for c in 0..channels {
+                    let mut acc = 0.0;
+                    for (i, weight) in kernel.iter().enumerate() {
+                        let sx = (x as isize + i as isize - radius).clamp(0, width as isize - 1);
+                        acc += weight * buf[[y, sx as usize, c]];
+                    }
+                    horizontal[[y, x, c]] = acc;
+                }
+            }
+        }
+
+        let mut vertical = Array3::<f32>::zeros((height, width, channels));
+        for y in 0..height {
+            for x in 0..width {
+                for c in 0..channels {
+                    let mut acc = 0.0;
+                    for (i, weight) in kernel.iter().enumerate() {
+                        let sy = (y as isize + i as isize - radius).clamp(0, height as isize - 1);
+                        acc += weight * horizontal[[sy as usize, x, c]];
+                    }
+                    vertical[[y, x, c]] = acc;
+                }
+            }
+        }
+        vertical
+    }
+}
+
+/// One stage of a [`FilterChain`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    ColorMatrix(ColorMatrix),
+    ComponentTransfer(ComponentTransfer),
+    ConvolveMatrix(ConvolveMatrix),
+    GaussianBlur(GaussianBlur),
+}
+
+fn premultiply(buf: &Array3<f32>) -> Array3<f32> {
+    let mut out = buf.clone();
+    for mut pixel in out.outer_iter_mut() {
+        for mut row in pixel.outer_iter_mut() {
+            let a = row[3];
+            row[0] *= a;
+            row[1] *= a;
+            row[2] *= a;
+        }
+    }
+    out
+}
+
+fn unpremultiply(buf: &Array3<f32>) -> Array3<f32> {
+    let mut out = buf.clone();
+    for mut pixel in out.outer_iter_mut() {
+        for mut row in pixel.outer_iter_mut() {
+            let a = row[3];
+            if a > 0.0 {
+                row[0] /= a;
+                row[1] /= a;
+                row[2] /= a;
+            }
+        }
+    }
+    out
+}
+
+/// An ordered pipeline of [`Filter`]s applied to a styled raster's RGBA buffer.
+///
+/// # Example
+/// ```
+/// use map_engine::filters::{ColorMatrix, Filter, FilterChain};
+///
+/// let mut chain = FilterChain::new();
+/// chain.push(Filter::ColorMatrix(ColorMatrix::saturate(0.0))); // greyscale
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterChain(Vec<Filter>);
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Append a filter stage to the end of the chain.
+    pub fn push(&mut self, filter: Filter) -> &mut Self {
+        self.0.push(filter);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Run every stage, in order, over `data` (a `(height, width, 4)` RGBA buffer).
+    pub(crate) fn apply(&self, data: &Array3<u8>) -> Array3<u8> {
+        let mut buf = data.mapv(|v| v as f32 / 255.0);
+        for filter in &self.0 {
+            buf = match filter {
+                Filter::ColorMatrix(m) => {
+                    let mut out = buf.clone();
+                    for mut pixel in out.outer_iter_mut() {
+                        for mut row in pixel.outer_iter_mut() {
+                            let transformed = m.apply([row[0], row[1], row[2], row[3]]);
+                            row.assign(&ndarray::arr1(&transformed));
+                        }
+                    }
+                    out
+                }
+                Filter::ComponentTransfer(ct) => {
+                    let mut out = buf.clone();
+                    for mut pixel in out.outer_iter_mut() {
+                        for mut row in pixel.outer_iter_mut() {
+                            let transformed = ct.apply([row[0], row[1], row[2], row[3]]);
+                            row.assign(&ndarray::arr1(&transformed));
+                        }
+                    }
+                    out
+                }
+                Filter::ConvolveMatrix(cm) => unpremultiply(&cm.apply(&premultiply(&buf))),
+                Filter::GaussianBlur(gb) => unpremultiply(&gb.apply(&premultiply(&buf))),
+            };
+        }
+        buf.mapv(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr3;
+
+    #[test]
+    fn test_color_matrix_saturate_zero_is_greyscale() {
+        let data = arr3(&[[[255u8, 0, 0, 255]]]);
+        let mut chain = FilterChain::new();
+        chain.push(Filter::ColorMatrix(ColorMatrix::saturate(0.0)));
+        let out = chain.apply(&data);
+        assert_eq!(out[[0, 0, 0]], out[[0, 0, 1]]);
+        assert_eq!(out[[0, 0, 1]], out[[0, 0, 2]]);
+        assert_eq!(out[[0, 0, 3]], 255); // alpha untouched
+    }
+
+    #[test]
+    fn test_color_matrix_identity_is_noop() {
+        let data = arr3(&[[[12u8, 200, 40, 128]]]);
+        let mut chain = FilterChain::new();
+        chain.push(Filter::ColorMatrix(ColorMatrix::identity()));
+        assert_eq!(chain.apply(&data), data);
+    }
+
+    #[test]
+    fn test_component_transfer_linear() {
+        let data = arr3(&[[[100u8, 100, 100, 255]]]);
+        let mut chain = FilterChain::new();
+        chain.push(Filter::ComponentTransfer(ComponentTransfer {
+            r: TransferFunction::Linear {
+                slope: 0.0,
+                intercept: 1.0,
+            },
+            ..Default::default()
+        }));
+        let out = chain.apply(&data);
+        assert_eq!(out[[0, 0, 0]], 255);
+        assert_eq!(out[[0, 0, 1]], 100); // untouched channel
+    }
+
+    #[test]
+    fn test_component_transfer_table_interpolates() {
+        let transfer = TransferFunction::Table(vec![0.0, 1.0]);
+        assert_eq!(transfer.apply(0.0), 0.0);
+        assert_eq!(transfer.apply(1.0), 1.0);
+        assert!((transfer.apply(0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_convolve_matrix_identity_kernel_is_noop() {
+        let data = arr3(&[
+            [[10u8, 20, 30, 255], [40, 50, 60, 255]],
+            [[70, 80, 90, 255], [100, 110, 120, 255]],
+        ]);
+        let kernel = ConvolveMatrix::new(
+            vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0],
+            3,
+            3,
+            0.0,
+            EdgeMode::Extend,
+        )
+        .unwrap();
+        let mut chain = FilterChain::new();
+        chain.push(Filter::ConvolveMatrix(kernel));
+        let out = chain.apply(&data);
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn test_convolve_matrix_rejects_mismatched_kernel_length() {
+        assert!(ConvolveMatrix::new(vec![1.0, 1.0], 3, 3, 0.0, EdgeMode::Extend).is_err());
+    }
+
+    #[test]
+    fn test_gaussian_blur_flat_image_is_unchanged() {
+        let data = Array3::<u8>::from_elem((8, 8, 4), 100);
+        let mut chain = FilterChain::new();
+        chain.push(Filter::GaussianBlur(GaussianBlur::new(2.0)));
+        let out = chain.apply(&data);
+        for v in out.iter() {
+            assert!((*v as i32 - 100).abs() <= 1); // rounding only
+        }
+    }
+
+    #[test]
+    fn test_gaussian_blur_smooths_a_spike() {
+        let mut arr = Array3::<u8>::zeros((9, 9, 4));
+        arr[[4, 4, 0]] = 255;
+        arr[[4, 4, 3]] = 255;
+        let mut chain = FilterChain::new();
+        chain.push(Filter::GaussianBlur(GaussianBlur::new(1.0)));
+        let out = chain.apply(&arr);
+        // The spike should spread to its neighbours and no longer be fully opaque (its colour is
+        // unpremultiplied back to pure red wherever any coverage remains).
+        assert!(out[[4, 4, 3]] < 255);
+        assert!(out[[4, 3, 0]] > 0);
+    }
+
+    #[test]
+    fn test_empty_chain_is_noop() {
+        let data = arr3(&[[[5u8, 6, 7, 8]]]);
+        let chain = FilterChain::new();
+        assert_eq!(chain.apply(&data), data);
+    }
+}
@@ -0,0 +1,332 @@
+//! Neighbourhood (focal) operations over a single raster band, applied before colour-mapping.
+//!
+//! Unlike [`crate::filters::FilterChain`], which post-processes the already-styled RGBA buffer,
+//! these operate on the raw numeric band values [`crate::raster::Raster::read_focal_tile`] reads
+//! from the source file, so they need to read a neighbourhood of pixels around the requested
+//! [`crate::windows::Window`] rather than the window alone.
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+/// How to resolve a focal filter's neighbourhood lookups that fall outside the available data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeMode {
+    /// Clamp to the nearest in-bounds pixel.
+    Duplicate,
+    /// Wrap around to the opposite edge.
+    Wrap,
+    /// Treat out-of-bounds pixels as `0.0`.
+    None,
+}
+
+impl EdgeMode {
+    pub(crate) fn sample(&self, data: &Array2<f64>, row: isize, col: isize) -> f64 {
+        let (rows, cols) = data.dim();
+        let (rows, cols) = (rows as isize, cols as isize);
+        let in_bounds = row >= 0 && row < rows && col >= 0 && col < cols;
+        match self {
+            _ if in_bounds => data[[row as usize, col as usize]],
+            EdgeMode::None => 0.0,
+            EdgeMode::Duplicate => {
+                let r = row.clamp(0, rows - 1) as usize;
+                let c = col.clamp(0, cols - 1) as usize;
+                data[[r, c]]
+            }
+            EdgeMode::Wrap => {
+                let r = row.rem_euclid(rows) as usize;
+                let c = col.rem_euclid(cols) as usize;
+                data[[r, c]]
+            }
+        }
+    }
+}
+
+/// An `order_x × order_y` convolution kernel applied to a raster band, following the SVG
+/// `feConvolveMatrix` convention: the kernel is addressed in reverse order and the weighted sum
+/// is scaled by `divisor` and offset by `bias`.
+#[derive(Debug, Clone)]
+pub struct Kernel {
+    kernel: Vec<f64>,
+    order_x: usize,
+    order_y: usize,
+    target_x: usize,
+    target_y: usize,
+    divisor: f64,
+    bias: f64,
+    edge_mode: EdgeMode,
+    preserve_alpha: bool,
+}
+
+impl Kernel {
+    /// Build a kernel, targeting its centre (`order_x / 2, order_y / 2`) and defaulting the
+    /// divisor to the sum of the kernel's entries, or `1.0` if that sum is `0.0`.
+    ///
+    /// # Panics
+    /// Panics if `kernel.len() != order_x * order_y`.
+    pub fn new(kernel: Vec<f64>, order_x: usize, order_y: usize) -> Self {
+        assert_eq!(
+            kernel.len(),
+            order_x * order_y,
+            "kernel must have order_x * order_y entries"
+        );
+        let sum: f64 = kernel.iter().sum();
+        Self {
+            kernel,
+            order_x,
+            order_y,
+            target_x: order_x / 2,
+            target_y: order_y / 2,
+            divisor: if sum == 0.0 { 1.0 } else { sum },
+            bias: 0.0,
+            edge_mode: EdgeMode::Duplicate,
+            preserve_alpha: false,
+        }
+    }
+
+    pub fn with_target(mut self, target_x: usize, target_y: usize) -> Self {
+        self.target_x = target_x;
+        self.target_y = target_y;
+        self
+    }
+
+    pub fn with_divisor(mut self, divisor: f64) -> Self {
+        self.divisor = divisor;
+        self
+    }
+
+    pub fn with_bias(mut self, bias: f64) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn with_edge_mode(mut self, edge_mode: EdgeMode) -> Self {
+        self.edge_mode = edge_mode;
+        self
+    }
+
+    pub fn with_preserve_alpha(mut self, preserve_alpha: bool) -> Self {
+        self.preserve_alpha = preserve_alpha;
+        self
+    }
+
+    pub fn preserve_alpha(&self) -> bool {
+        self.preserve_alpha
+    }
+}
+
+/// A focal operation over a single raster band: given the neighbourhood around each output
+/// pixel, produce a new value for it.
+///
+/// Shared by [`Kernel`] (weighted convolution) and [`Morphology`] (neighbourhood min/max) so
+/// [`crate::raster::Raster::read_focal_tile`] can grow the source window and crop the result the
+/// same way for either.
+pub trait FocalFilter {
+    /// How many extra pixels `(left, right, top, bottom)` a source window must be grown by (via
+    /// [`crate::windows::Window::grow`]) to evaluate this filter without going out of bounds.
+    fn padding(&self) -> (usize, usize, usize, usize);
+
+    /// Evaluate a `(out_width, out_height)` window whose top-left corner sits at
+    /// `(row_offset, col_offset)` within `src`. Lookups that fall outside `src` (because the
+    /// window was grown past the edge of the available data) are resolved via the filter's own
+    /// [`EdgeMode`].
+    fn apply(
+        &self,
+        src: &Array2<f64>,
+        row_offset: isize,
+        col_offset: isize,
+        out_width: usize,
+        out_height: usize,
+    ) -> Array2<f64>;
+}
+
+impl FocalFilter for Kernel {
+    fn padding(&self) -> (usize, usize, usize, usize) {
+        (
+            self.target_x,
+            self.order_x - 1 - self.target_x,
+            self.target_y,
+            self.order_y - 1 - self.target_y,
+        )
+    }
+
+    fn apply(
+        &self,
+        src: &Array2<f64>,
+        row_offset: isize,
+        col_offset: isize,
+        out_width: usize,
+        out_height: usize,
+    ) -> Array2<f64> {
+        Array2::from_shape_fn((out_height, out_width), |(row, col)| {
+            let y = row_offset + row as isize;
+            let x = col_offset + col as isize;
+            let mut acc = 0.0;
+            for i in 0..self.order_x {
+                for j in 0..self.order_y {
+                    let sy = y - self.target_y as isize + j as isize;
+                    let sx = x - self.target_x as isize + i as isize;
+                    let k = self.kernel[(self.order_x - i - 1) * self.order_y + (self.order_y - j - 1)];
+                    acc += self.edge_mode.sample(src, sy, sx) * k;
+                }
+            }
+            acc / self.divisor + self.bias
+        })
+    }
+}
+
+/// Which morphological operator [`Morphology`] applies over its structuring element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MorphologyOperator {
+    /// Per-pixel minimum over the structuring element: shrinks bright regions, grows dark ones.
+    Erode,
+    /// Per-pixel maximum over the structuring element: grows bright regions, shrinks dark ones.
+    Dilate,
+}
+
+/// Erode or dilate a raster band with a rectangular structuring element, e.g. to clean up a
+/// classification mask or buffer out from a nodata edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Morphology {
+    operator: MorphologyOperator,
+    radius_x: usize,
+    radius_y: usize,
+    edge_mode: EdgeMode,
+}
+
+impl Morphology {
+    /// Build a morphology filter whose structuring element is the `(2 * radius_x + 1) x
+    /// (2 * radius_y + 1)` rectangle centred on each output pixel.
+    pub fn new(operator: MorphologyOperator, radius_x: usize, radius_y: usize) -> Self {
+        Self {
+            operator,
+            radius_x,
+            radius_y,
+            edge_mode: EdgeMode::Duplicate,
+        }
+    }
+
+    pub fn with_edge_mode(mut self, edge_mode: EdgeMode) -> Self {
+        self.edge_mode = edge_mode;
+        self
+    }
+}
+
+impl FocalFilter for Morphology {
+    fn padding(&self) -> (usize, usize, usize, usize) {
+        (self.radius_x, self.radius_x, self.radius_y, self.radius_y)
+    }
+
+    fn apply(
+        &self,
+        src: &Array2<f64>,
+        row_offset: isize,
+        col_offset: isize,
+        out_width: usize,
+        out_height: usize,
+    ) -> Array2<f64> {
+        let (radius_x, radius_y) = (self.radius_x as isize, self.radius_y as isize);
+        Array2::from_shape_fn((out_height, out_width), |(row, col)| {
+            let y = row_offset + row as isize;
+            let x = col_offset + col as isize;
+            let mut acc = self.edge_mode.sample(src, y, x);
+            for j in -radius_y..=radius_y {
+                for i in -radius_x..=radius_x {
+                    let v = self.edge_mode.sample(src, y + j, x + i);
+                    acc = match self.operator {
+                        MorphologyOperator::Erode => acc.min(v),
+                        MorphologyOperator::Dilate => acc.max(v),
+                    };
+                }
+            }
+            acc
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(rows: usize, cols: usize) -> Array2<f64> {
+        Array2::from_shape_fn((rows, cols), |(r, c)| (r * cols + c) as f64)
+    }
+
+    #[test]
+    fn test_identity_kernel_is_no_op() {
+        let kernel = Kernel::new(vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0], 3, 3);
+        let src = ramp(5, 5);
+        let out = kernel.apply(&src, 1, 1, 3, 3);
+        assert_eq!(out, src.slice(ndarray::s![1..4, 1..4]));
+    }
+
+    #[test]
+    fn test_box_blur_averages_neighbours() {
+        let kernel = Kernel::new(vec![1.0; 9], 3, 3);
+        let src = Array2::<f64>::ones((5, 5));
+        let out = kernel.apply(&src, 1, 1, 3, 3);
+        assert_eq!(out, Array2::<f64>::ones((3, 3)));
+    }
+
+    #[test]
+    fn test_padding_matches_target_offsets() {
+        let kernel = Kernel::new(vec![0.0; 15], 5, 3).with_target(1, 2);
+        assert_eq!(kernel.padding(), (1, 3, 2, 0));
+    }
+
+    #[test]
+    fn test_edge_mode_none_zero_fills_out_of_bounds() {
+        let kernel = Kernel::new(vec![1.0; 9], 3, 3).with_edge_mode(EdgeMode::None);
+        let src = Array2::<f64>::ones((3, 3));
+        // Requesting the single top-left pixel's neighbourhood, most of which is out of bounds.
+        let out = kernel.apply(&src, 0, 0, 1, 1);
+        assert_eq!(out[[0, 0]], 4.0 / 9.0); // only the 2x2 in-bounds corner contributes
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_rejects_mismatched_kernel_length() {
+        Kernel::new(vec![1.0, 2.0, 3.0], 2, 2);
+    }
+
+    #[test]
+    fn test_morphology_padding_matches_radius() {
+        let morph = Morphology::new(MorphologyOperator::Erode, 2, 1);
+        assert_eq!(morph.padding(), (2, 2, 1, 1));
+    }
+
+    #[test]
+    fn test_erode_takes_neighbourhood_minimum() {
+        let morph = Morphology::new(MorphologyOperator::Erode, 1, 1);
+        let src = ramp(5, 5);
+        let out = morph.apply(&src, 1, 1, 3, 3);
+        // Output (0, 0) is centred on src (1, 1): its 3x3 neighbourhood's minimum is src (0, 0).
+        assert_eq!(out[[0, 0]], src[[0, 0]]);
+    }
+
+    #[test]
+    fn test_dilate_takes_neighbourhood_maximum() {
+        let morph = Morphology::new(MorphologyOperator::Dilate, 1, 1);
+        let src = ramp(5, 5);
+        let out = morph.apply(&src, 1, 1, 3, 3);
+        // Output (2, 2) is centred on src (3, 3): its 3x3 neighbourhood's maximum is src (4, 4).
+        assert_eq!(out[[2, 2]], src[[4, 4]]);
+    }
+
+    #[test]
+    fn test_erode_and_dilate_are_no_ops_on_flat_input() {
+        let src = Array2::<f64>::ones((5, 5)) * 7.0;
+        let erode = Morphology::new(MorphologyOperator::Erode, 1, 1).apply(&src, 1, 1, 3, 3);
+        let dilate = Morphology::new(MorphologyOperator::Dilate, 1, 1).apply(&src, 1, 1, 3, 3);
+        assert_eq!(erode, Array2::<f64>::ones((3, 3)) * 7.0);
+        assert_eq!(dilate, Array2::<f64>::ones((3, 3)) * 7.0);
+    }
+
+    #[test]
+    fn test_morphology_edge_mode_none_erodes_to_zero_at_border() {
+        let morph = Morphology::new(MorphologyOperator::Erode, 1, 1).with_edge_mode(EdgeMode::None);
+        let src = Array2::<f64>::ones((3, 3));
+        let out = morph.apply(&src, 0, 0, 1, 1);
+        // The top-left pixel's neighbourhood reaches out of bounds, which `EdgeMode::None`
+        // resolves as 0.0, so the minimum is 0.0.
+        assert_eq!(out[[0, 0]], 0.0);
+    }
+}
@@ -1,6 +1,7 @@
 //!Re-exports from [`gdal`] crate.
 pub use gdal::Dataset;
 pub use gdal::spatial_ref;
+pub use gdal::vector::Layer;
 pub use gdal::vector::LayerAccess;
 pub use gdal::raster::ResampleAlg;
 pub use gdal::config;
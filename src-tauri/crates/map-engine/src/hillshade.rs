@@ -0,0 +1,198 @@
+//! Relief shading of single-band elevation rasters.
+//!
+//! Like [`crate::focal::Kernel`], this needs the neighbourhood around each output pixel (to
+//! estimate a surface normal from `dz/dx`/`dz/dy`), so it reuses the same enlarged-window read
+//! strategy: [`crate::raster::Raster::read_hillshade_tile`] grows the requested
+//! [`crate::windows::Window`] by [`Hillshade::padding`] before reading, exactly as
+//! [`crate::raster::Raster::read_focal_tile`] does for a [`crate::focal::Kernel`].
+use crate::focal::EdgeMode;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+/// Relief-shading parameters for an elevation band, following the SVG
+/// `feDiffuseLighting`/`feSpecularLighting` lighting model: a directional light (`azimuth`,
+/// `altitude`) is combined with the per-pixel surface normal to produce a diffuse intensity, with
+/// an optional specular highlight using the halfway vector between the light and a fixed,
+/// straight-down viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Hillshade {
+    azimuth: f64,
+    altitude: f64,
+    z_factor: f64,
+    diffuse: f64,
+    specular: f64,
+    specular_exponent: f64,
+    edge_mode: EdgeMode,
+}
+
+impl Hillshade {
+    /// Create a hillshade with a purely diffuse light.
+    ///
+    /// # Arguments
+    ///
+    /// * `azimuth` - Light direction in degrees clockwise from north (0 = north, 90 = east).
+    /// * `altitude` - Light elevation above the horizon, in degrees (0 = horizon, 90 = overhead).
+    pub fn new(azimuth: f64, altitude: f64) -> Self {
+        Self {
+            azimuth,
+            altitude,
+            z_factor: 1.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            specular_exponent: 1.0,
+            edge_mode: EdgeMode::Duplicate,
+        }
+    }
+
+    /// Scale elevation relative to the raster's horizontal ground resolution, e.g. to exaggerate
+    /// relief or to convert an elevation unit other than the CRS's linear unit.
+    pub fn with_z_factor(mut self, z_factor: f64) -> Self {
+        self.z_factor = z_factor;
+        self
+    }
+
+    /// `kd` in `kd · max(0, N·L)`. Defaults to `1.0`.
+    pub fn with_diffuse(mut self, diffuse: f64) -> Self {
+        self.diffuse = diffuse;
+        self
+    }
+
+    /// `ks` and the shininess exponent in `ks · max(0, N·H)^specular_exponent`. Defaults to
+    /// `0.0` (no specular term).
+    pub fn with_specular(mut self, specular: f64, specular_exponent: f64) -> Self {
+        self.specular = specular;
+        self.specular_exponent = specular_exponent;
+        self
+    }
+
+    pub fn with_edge_mode(mut self, edge_mode: EdgeMode) -> Self {
+        self.edge_mode = edge_mode;
+        self
+    }
+
+    /// How many extra pixels `(left, right, top, bottom)` a source window must be grown by (via
+    /// [`crate::windows::Window::grow`]) to estimate every output pixel's normal from real
+    /// neighbours instead of the window's own edge.
+    pub fn padding(&self) -> (usize, usize, usize, usize) {
+        (1, 1, 1, 1)
+    }
+
+    /// The light vector `L`, pointing from the surface towards the light, in a right-handed
+    /// `(east, north, up)` frame.
+    fn light_vector(&self) -> (f64, f64, f64) {
+        let azimuth = self.azimuth.to_radians();
+        let altitude = self.altitude.to_radians();
+        (
+            azimuth.sin() * altitude.cos(),
+            azimuth.cos() * altitude.cos(),
+            altitude.sin(),
+        )
+    }
+
+    /// Shade a `(out_width, out_height)` window whose top-left corner sits at
+    /// `(row_offset, col_offset)` within `elevation`, returning a grayscale intensity in
+    /// `[0.0, 1.0]` per pixel. Lookups that fall outside `elevation` (because the window was
+    /// grown past the edge of the available data) are resolved via [`EdgeMode`].
+    ///
+    /// `pixel_size` is the `(x, y)` ground resolution of `elevation`, used to scale `dz/dx` and
+    /// `dz/dy` into the same units as the elevation values (see
+    /// [`crate::windows::Window::geotransform`]).
+    pub fn shade(
+        &self,
+        elevation: &Array2<f64>,
+        row_offset: isize,
+        col_offset: isize,
+        out_width: usize,
+        out_height: usize,
+        pixel_size: (f64, f64),
+    ) -> Array2<f64> {
+        let (px, py) = pixel_size;
+        let (lx, ly, lz) = self.light_vector();
+
+        Array2::from_shape_fn((out_height, out_width), |(row, col)| {
+            let y = row_offset + row as isize;
+            let x = col_offset + col as isize;
+            let sample = |dy: isize, dx: isize| self.edge_mode.sample(elevation, y + dy, x + dx);
+
+            // Horn's 3x3 weighted method, the same one `gdaldem hillshade` uses.
+            let dzdx = ((sample(-1, 1) + 2.0 * sample(0, 1) + sample(1, 1))
+                - (sample(-1, -1) + 2.0 * sample(0, -1) + sample(1, -1)))
+                / (8.0 * px)
+                * self.z_factor;
+            let dzdy = ((sample(-1, -1) + 2.0 * sample(-1, 0) + sample(-1, 1))
+                - (sample(1, -1) + 2.0 * sample(1, 0) + sample(1, 1)))
+                / (8.0 * py)
+                * self.z_factor;
+
+            let norm = (dzdx * dzdx + dzdy * dzdy + 1.0).sqrt();
+            let (nx, ny, nz) = (-dzdx / norm, -dzdy / norm, 1.0 / norm);
+
+            let diffuse = self.diffuse * (nx * lx + ny * ly + nz * lz).max(0.0);
+
+            let specular = if self.specular > 0.0 {
+                // Halfway vector between the light and a fixed, straight-up viewer.
+                let (hx, hy, hz) = (lx, ly, lz + 1.0);
+                let h_norm = (hx * hx + hy * hy + hz * hz).sqrt();
+                let n_dot_h = (nx * hx + ny * hy + nz * hz) / h_norm;
+                self.specular * n_dot_h.max(0.0).powf(self.specular_exponent)
+            } else {
+                0.0
+            };
+
+            (diffuse + specular).clamp(0.0, 1.0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plane(rows: usize, cols: usize, slope_per_col: f64) -> Array2<f64> {
+        Array2::from_shape_fn((rows, cols), |(_, c)| c as f64 * slope_per_col)
+    }
+
+    #[test]
+    fn test_flat_surface_faces_straight_up() {
+        let hillshade = Hillshade::new(315.0, 45.0);
+        let flat = Array2::<f64>::zeros((5, 5));
+        let out = hillshade.shade(&flat, 1, 1, 3, 3, (1.0, 1.0));
+        let expected = 45f64.to_radians().sin();
+        for v in out.iter() {
+            assert!((v - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_slope_facing_the_light_is_brighter_than_facing_away() {
+        // Light from the east (azimuth 90): a surface rising towards the west (its normal points
+        // east, toward the light) should catch more light than one rising towards the east (its
+        // normal points away from the light).
+        let hillshade = Hillshade::new(90.0, 45.0);
+        let rising_east = plane(5, 5, 1.0);
+        let rising_west = plane(5, 5, -1.0);
+
+        let lit = hillshade.shade(&rising_west, 1, 1, 3, 3, (1.0, 1.0));
+        let unlit = hillshade.shade(&rising_east, 1, 1, 3, 3, (1.0, 1.0));
+        assert!(lit[[1, 1]] > unlit[[1, 1]]);
+    }
+
+    #[test]
+    fn test_specular_term_is_disabled_by_default() {
+        let hillshade = Hillshade::new(315.0, 45.0);
+        let surface = plane(5, 5, 2.0);
+        let without = hillshade.shade(&surface, 1, 1, 3, 3, (1.0, 1.0));
+        let with_specular = hillshade
+            .with_specular(1.0, 8.0)
+            .shade(&surface, 1, 1, 3, 3, (1.0, 1.0));
+        assert!(with_specular[[1, 1]] >= without[[1, 1]]);
+    }
+
+    #[test]
+    fn test_intensity_is_clamped_to_one() {
+        let hillshade = Hillshade::new(0.0, 90.0).with_diffuse(10.0);
+        let flat = Array2::<f64>::zeros((3, 3));
+        let out = hillshade.shade(&flat, 1, 1, 1, 1, (1.0, 1.0));
+        assert_eq!(out[[0, 0]], 1.0);
+    }
+}
@@ -65,13 +65,19 @@ extern crate lazy_static;
 mod tests;
 
 pub mod affine;
+pub mod cache;
 pub mod cmap;
 pub mod colour;
+pub mod compositing;
 pub mod errors;
+pub mod filters;
+pub mod focal;
 pub mod gdal;
+pub mod hillshade;
 pub mod mercator;
 pub mod png;
 pub mod raster;
+pub mod reclass;
 pub mod vector;
 pub mod tiles;
 pub mod windows;
@@ -80,6 +86,4 @@ pub mod windows;
 pub const MAXZOOMLEVEL: u32 = 32;
 
 /// Available tile formats to request.
-///
-/// At the moment, only PNG8 tiles are supported.
-pub const SUPPORTED_FORMATS: &[&str] = &["png"];
+pub const SUPPORTED_FORMATS: &[&str] = &["png", "jpg", "jpeg", "webp"];
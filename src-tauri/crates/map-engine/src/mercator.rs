@@ -26,6 +26,55 @@ impl GlobalMercator {
         }
         zoom
     }
+
+    /// The integer zoom and `(lat, lon)` center that fits `bbox` (`[min_lon, min_lat, max_lon,
+    /// max_lat]`, degrees) into a `viewport_w`x`viewport_h` pixel viewport, clamped to
+    /// `[0, max_zoom]`. This is the same problem mapbox-gl's `LatLngBoundsToCamera` solves: project
+    /// both bbox corners into normalized Web Mercator fractions, then pick the zoom where the
+    /// bbox's fractional span just fills the viewport (the tighter of the x/y fits wins, so the
+    /// whole bbox stays visible), and center on the bbox's mercator-space midpoint.
+    pub fn fit_bounds(
+        &self,
+        bbox: [f64; 4],
+        viewport_w: f64,
+        viewport_h: f64,
+        max_zoom: u32,
+    ) -> (u32, (f64, f64)) {
+        let [min_lon, min_lat, max_lon, max_lat] = bbox;
+
+        let (x0, y0) = self.mercator_fraction(min_lon, max_lat);
+        let (x1, y1) = self.mercator_fraction(max_lon, min_lat);
+
+        // A degenerate (point or hairline) bbox has zero span; floor it so the zoom below stays
+        // finite instead of blowing up to +inf.
+        let dx = (x1 - x0).abs().max(1e-9);
+        let dy = (y1 - y0).abs().max(1e-9);
+
+        let zoom_x = (viewport_w / (self.tile_size as f64 * dx)).log2();
+        let zoom_y = (viewport_h / (self.tile_size as f64 * dy)).log2();
+        let zoom = zoom_x.min(zoom_y).floor().clamp(0.0, max_zoom as f64) as u32;
+
+        let (center_lon, center_lat) =
+            self.inverse_mercator_fraction((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+
+        (zoom, (center_lat, center_lon))
+    }
+
+    /// Project `(lon, lat)` (degrees) to normalized Web Mercator fractions in `[0, 1]`.
+    fn mercator_fraction(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let lat_rad = lat.to_radians();
+        let x = (lon + 180.0) / 360.0;
+        let y = (1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / PI) / 2.0;
+        (x, y)
+    }
+
+    /// Inverse of [`Self::mercator_fraction`]: normalized Web Mercator fractions back to `(lon,
+    /// lat)` degrees.
+    fn inverse_mercator_fraction(&self, x: f64, y: f64) -> (f64, f64) {
+        let lon = x * 360.0 - 180.0;
+        let lat = (PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees();
+        (lon, lat)
+    }
 }
 
 impl Default for GlobalMercator {
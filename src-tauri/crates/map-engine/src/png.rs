@@ -1,16 +1,38 @@
-//!Empty PNG image.
+//!Empty tile images, one per format `get_tile` can serve.
 use crate::errors::MapEngineError;
 use crate::raster::{pixels::driver::Driver, StyledPixels};
 use crate::tiles::TILE_SIZE;
 use lazy_static::lazy_static;
 use ndarray::{Array, Array3};
 
+fn empty_tile() -> Array3<u8> {
+    Array::zeros((4, TILE_SIZE, TILE_SIZE))
+}
+
 /// Fully-transparent tile served when the requested tile does not intersect the map extent
 pub fn empty_png() -> Result<Vec<u8>, MapEngineError> {
-    let arr: Array3<u8> = Array::zeros((4, TILE_SIZE, TILE_SIZE));
-    StyledPixels::new(arr, Driver::Generic).into_png()
+    StyledPixels::new(empty_tile(), Driver::Generic).into_png()
 }
 
 lazy_static! {
     pub static ref EMPTY_PNG: Vec<u8> = empty_png().unwrap();
 }
+
+/// JPEG equivalent of [`EMPTY_PNG`]. JPEG has no alpha channel, so this encodes as plain black
+/// rather than transparent.
+pub fn empty_jpeg() -> Result<Vec<u8>, MapEngineError> {
+    StyledPixels::new(empty_tile(), Driver::Generic).into_jpeg(85, [0, 0, 0])
+}
+
+lazy_static! {
+    pub static ref EMPTY_JPEG: Vec<u8> = empty_jpeg().unwrap();
+}
+
+/// WebP equivalent of [`EMPTY_PNG`].
+pub fn empty_webp() -> Vec<u8> {
+    StyledPixels::new(empty_tile(), Driver::Generic).into_webp(80.0)
+}
+
+lazy_static! {
+    pub static ref EMPTY_WEBP: Vec<u8> = empty_webp();
+}
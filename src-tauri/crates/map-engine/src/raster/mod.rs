@@ -1,10 +1,15 @@
 //! Types and helpers to work with raster images.
 pub mod pixels;
+pub mod stats;
+pub mod stretch;
 
 use crate::{
     affine::GeoTransform,
     errors::MapEngineError,
-    tiles::{Tile, TILE_SIZE},
+    focal::FocalFilter,
+    hillshade::Hillshade,
+    reclass::ReclassTable,
+    tiles::{Metatile, Tile, TILE_SIZE},
     windows::intersection,
     windows::Window,
 };
@@ -14,9 +19,11 @@ use gdal::{
     Dataset,
     DriverManager,
 };
-use ndarray::{s, Array, Array2, Array3};
-use num_traits::{Num, NumCast};
-pub use pixels::{driver::MBTILES, RawPixels, StyledPixels};
+use ndarray::{s, Array, Array2, Array3, Axis};
+use num_traits::{Num, NumCast, ToPrimitive};
+pub use pixels::{driver::MBTILES, RawPixels, StyledPixels, DEFAULT_JPEG_BACKGROUND};
+pub use stats::{BandStats, Histogram};
+pub use stretch::StretchMode;
 use std::cmp;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
@@ -67,6 +74,142 @@ impl SpatialInfo {
     }
 }
 
+/// Destination CRS/resolution/resample algorithm that a skewed (reprojected) tile read is
+/// warped into, replacing a hardcoded Web Mercator target.
+///
+/// Modelled on odc/datacube's `load(output_crs=..., resolution=...)`: [`Default`] preserves the
+/// crate's original behavior (EPSG:3857, resolution derived from the source window).
+#[derive(Debug, Clone)]
+pub struct WarpTarget {
+    spatial_info: SpatialInfo,
+    resolution: Option<(f64, f64)>,
+    resample_alg: ResampleAlg,
+}
+
+impl Default for WarpTarget {
+    fn default() -> Self {
+        WarpTarget {
+            spatial_info: SpatialInfo {
+                epsg_code: Some(3857),
+                proj4: None,
+                wkt: None,
+                esri: None,
+            },
+            resolution: None,
+            resample_alg: ResampleAlg::NearestNeighbour,
+        }
+    }
+}
+
+impl WarpTarget {
+    /// Target an arbitrary destination CRS (e.g. EPSG:4326 graticule tiles or a national grid),
+    /// keeping the default resolution-from-source-window behavior and nearest-neighbour resample.
+    pub fn new(spatial_info: SpatialInfo) -> Self {
+        WarpTarget {
+            spatial_info,
+            ..Default::default()
+        }
+    }
+
+    /// Fix the destination pixel size in `(x, y)` target-CRS units, instead of deriving it from
+    /// the source window's geotransform.
+    pub fn with_resolution(mut self, x: f64, y: f64) -> Self {
+        self.resolution = Some((x, y));
+        self
+    }
+
+    /// Resample algorithm used for the final warp into the destination grid.
+    pub fn with_resample_alg(mut self, resample_alg: ResampleAlg) -> Self {
+        self.resample_alg = resample_alg;
+        self
+    }
+}
+
+/// One variable GDAL found inside a multidimensional source (NetCDF/HDF/Zarr) that exposes more
+/// than one, e.g. `NETCDF:"ocean.nc":temperature`. See [`subdatasets`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subdataset {
+    /// GDAL dataset name, openable directly with [`Dataset::open`].
+    pub name: String,
+    /// Human-readable description GDAL reports alongside `name`.
+    pub description: String,
+}
+
+/// List the subdatasets GDAL reports for `src`, e.g. one per variable in a NetCDF/HDF file.
+/// Empty for an ordinary single-variable 2D source, which is the common case.
+pub fn subdatasets(src: &Dataset) -> Vec<Subdataset> {
+    let Some(entries) = src.metadata_domain("SUBDATASETS") else {
+        return Vec::new();
+    };
+
+    // GDAL reports these as flat `SUBDATASET_{n}_NAME`/`SUBDATASET_{n}_DESC` pairs; pair them
+    // back up by index and keep them in GDAL's own order.
+    let mut by_index: std::collections::BTreeMap<usize, (Option<String>, Option<String>)> =
+        Default::default();
+    for entry in entries {
+        let Some((key, value)) = entry.split_once('=') else { continue };
+        let Some(rest) = key.strip_prefix("SUBDATASET_") else { continue };
+        let Some((n, field)) = rest.split_once('_') else { continue };
+        let Ok(n) = n.parse::<usize>() else { continue };
+        let slot = by_index.entry(n).or_default();
+        match field {
+            "NAME" => slot.0 = Some(value.to_string()),
+            "DESC" => slot.1 = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    by_index
+        .into_values()
+        .filter_map(|(name, description)| {
+            Some(Subdataset {
+                name: name?,
+                description: description.unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Resolve a CF dimension selector (e.g. `{"time": "2020-01"}`) to the single band of `src` that
+/// slice corresponds to, by matching each requested dimension against the `NETCDF_DIM_{dim}` band
+/// metadata GDAL's netCDF driver attaches (one band per non-spatial coordinate combination).
+///
+/// Returns `Ok(None)` if `dimensions` is empty, and an error if no band (or more than one) matches
+/// every requested dimension.
+pub fn select_band_for_dimensions(
+    src: &Dataset,
+    dimensions: &std::collections::HashMap<String, String>,
+) -> Result<Option<isize>, MapEngineError> {
+    if dimensions.is_empty() {
+        return Ok(None);
+    }
+
+    let mut matches = Vec::new();
+    for i in 1..=src.raster_count() {
+        let band = src.rasterband(i)?;
+        let is_match = dimensions.iter().all(|(dim, value)| {
+            band.metadata_item(&format!("NETCDF_DIM_{}", dim), "")
+                .as_deref()
+                == Some(value.as_str())
+        });
+        if is_match {
+            matches.push(i);
+        }
+    }
+
+    match matches.as_slice() {
+        [band] => Ok(Some(*band)),
+        [] => Err(MapEngineError::Msg(format!(
+            "no band of this source matches dimension selector {:?}",
+            dimensions
+        ))),
+        _ => Err(MapEngineError::Msg(format!(
+            "dimension selector {:?} is ambiguous: matched bands {:?}",
+            dimensions, matches
+        ))),
+    }
+}
+
 /// A Raster image.
 #[derive(Debug, Clone)]
 pub struct Raster {
@@ -77,6 +220,18 @@ pub struct Raster {
     raster_count: isize,
     raster_size: (usize, usize),
     min_max: Vec<(f64, f64)>,
+    /// Per-band `(scale_factor, add_offset)`, read once in [`Raster::new`]/[`Raster::from_src`]
+    /// and falling back to `(1.0, 0.0)` when a band carries no CF packing metadata.
+    scale_offset: Vec<(f64, f64)>,
+    /// Set by [`Raster::with_cf_decoding`] to opt into CF-convention (`scale_factor`/`add_offset`/
+    /// `_FillValue`) decoding, with the value the caller wants raw nodata cells mapped to.
+    cf_masked_value: Option<f64>,
+    /// Destination CRS/resolution/resample algorithm for skewed (reprojected) tile reads. See
+    /// [`Raster::with_warp_target`].
+    warp_target: WarpTarget,
+    /// Contrast stretch applied by [`Raster::read_tile`] before casting into its output type. See
+    /// [`Raster::with_stretch_mode`].
+    stretch_mode: StretchMode,
 }
 
 impl Raster {
@@ -85,16 +240,22 @@ impl Raster {
     /// This will open a [`Dataset`] and store some metadata into the `Raster` struct. This serves
     /// as a cache to avoid constantly reading from the file.
     pub fn new(path: PathBuf) -> Result<Self, MapEngineError> {
+        Self::new_with_stats(path, true)
+    }
+
+    /// Like [`Raster::new`], but lets the caller choose whether band statistics (and the
+    /// `compute_raster_min_max` fallback, if nothing is already stored) may come from an
+    /// overview-based approximation (`approx = true`, what [`Raster::new`] uses) instead of an
+    /// exact full-resolution scan.
+    ///
+    /// This is what lets a tiling server open thousands of cloud-optimized GeoTIFFs without
+    /// paying a full scan per file: [`band_min_max`] tries already-computed statistics (GDAL
+    /// metadata or a sidecar `.aux.xml` PAM file) before falling back to a scan at all.
+    pub fn new_with_stats(path: PathBuf, approx: bool) -> Result<Self, MapEngineError> {
         let src = Dataset::open(&path)?;
         let geo = src.geo_transform()?;
         let geo = GeoTransform::from_gdal(&geo);
-        let mut min_max: Vec<(f64, f64)> = vec![];
-        for b in 1..=src.raster_count() {
-            let band = src.rasterband(b)?;
-            let minmax = band.compute_raster_min_max(true)?;
-            let skip = (minmax.max - minmax.min) * 0.02;
-            min_max.push((minmax.min + skip, minmax.max - skip));
-        }
+        let (min_max, scale_offset) = band_stats(&src, approx, true)?;
 
         Ok(Self {
             path,
@@ -104,6 +265,10 @@ impl Raster {
             raster_count: src.raster_count(),
             raster_size: src.raster_size(),
             min_max,
+            scale_offset,
+            cf_masked_value: None,
+            warp_target: WarpTarget::default(),
+            stretch_mode: StretchMode::default(),
         })
     }
 
@@ -112,16 +277,20 @@ impl Raster {
     /// Usually, you would want to use `Raster::new` but this method is available in case you
     /// already opened a `Dataset`.
     pub fn from_src(path: PathBuf, src: &Dataset) -> Result<Self, MapEngineError> {
+        Self::from_src_with_stats(path, src, true)
+    }
+
+    /// Like [`Raster::from_src`], with the same `approx` statistics knob as
+    /// [`Raster::new_with_stats`].
+    pub fn from_src_with_stats(
+        path: PathBuf,
+        src: &Dataset,
+        approx: bool,
+    ) -> Result<Self, MapEngineError> {
         let geo = src.geo_transform()?;
         let geo = GeoTransform::from_gdal(&geo);
         let spatial_ref = src.spatial_ref()?;
-
-        let mut min_max: Vec<(f64, f64)> = vec![];
-        for b in 1..=src.raster_count() {
-            let band = src.rasterband(b)?;
-            let minmax = band.compute_raster_min_max(true)?;
-            min_max.push((minmax.min, minmax.max));
-        }
+        let (min_max, scale_offset) = band_stats(src, approx, false)?;
 
         Ok(Self {
             path,
@@ -131,9 +300,39 @@ impl Raster {
             raster_count: src.raster_count(),
             raster_size: src.raster_size(),
             min_max,
+            scale_offset,
+            cf_masked_value: None,
+            warp_target: WarpTarget::default(),
+            stretch_mode: StretchMode::default(),
         })
     }
 
+    /// Opt into CF-convention (`scale_factor`/`add_offset`/`_FillValue`) decoding.
+    ///
+    /// Once set, [`Raster::read_tile`] applies each band's affine packing
+    /// (`value = raw * scale_factor + add_offset`) in floating point and replaces raw nodata
+    /// cells with `masked_value` (e.g. `f64::NAN` for float output), and [`Raster::min_max`]
+    /// reports the decoded physical range instead of the packed one, so downstream styling
+    /// stretches against real units.
+    pub fn with_cf_decoding(mut self, masked_value: f64) -> Self {
+        self.cf_masked_value = Some(masked_value);
+        self
+    }
+
+    /// Warp skewed (reprojected) tile reads into `target` instead of the default Web Mercator
+    /// (EPSG:3857) grid, e.g. to serve EPSG:4326 graticule tiles or a national grid.
+    pub fn with_warp_target(mut self, target: WarpTarget) -> Self {
+        self.warp_target = target;
+        self
+    }
+
+    /// Contrast-stretch [`Raster::read_tile`] output instead of letting an overflowing cast into
+    /// a narrower output type saturate.
+    pub fn with_stretch_mode(mut self, mode: StretchMode) -> Self {
+        self.stretch_mode = mode;
+        self
+    }
+
     /// Read a tile from raster file.
     ///
     /// # Arguments
@@ -142,6 +341,12 @@ impl Raster {
     /// * `bands` - Bands to read (1-indexed).
     /// * `e_resample_alg` - Resample algorith to use in case interpolations are needed.
     ///
+    /// Values are raw DN unless [`Raster::with_cf_decoding`] was called, in which case each
+    /// band's `scale_factor`/`add_offset` is applied and nodata cells are replaced with the
+    /// configured masked value before this returns. If [`Raster::with_stretch_mode`] set a
+    /// [`StretchMode`] other than [`StretchMode::None`], values are then linearly remapped onto
+    /// `[0, 255]` before the final cast into `P`, instead of that cast saturating on overflow.
+    ///
     /// # Examples
     ///
     /// ```
@@ -187,40 +392,99 @@ impl Raster {
             bands = &all_bands;
         }
 
-        let mut container_arr = Array3::<P>::zeros((bands.len(), TILE_SIZE, TILE_SIZE));
+        let mut container_arr = match try_multiband::<P>(&src, bands, &win, is_skewed, e_resample_alg)
+        {
+            Some(arr) => arr,
+            None => {
+                // Edge tile (boundless) or a skewed (reprojected) window: fall back to reading
+                // one band at a time, which is the only path that can pad with nodata or resample
+                // from an overview per band.
+                let mut container_arr = Array3::<P>::zeros((bands.len(), TILE_SIZE, TILE_SIZE));
+                for (out_idx, band_index) in bands.iter().enumerate() {
+                    let band = src.rasterband(*band_index)?;
+
+                    let band_data = try_boundless(
+                        &src,
+                        &band,
+                        &win,
+                        geo,
+                        &self.spatial_info,
+                        tile_bounds_xy,
+                        is_skewed,
+                        e_resample_alg,
+                        &self.warp_target,
+                    );
+                    let band_data = if let Some(d) = band_data {
+                        d
+                    } else {
+                        try_overview(
+                            &band,
+                            &win,
+                            // req_overview as f64,
+                            geo,
+                            &self.spatial_info,
+                            tile_bounds_xy,
+                            is_skewed,
+                            e_resample_alg,
+                            &self.warp_target,
+                        )?
+                    };
+
+                    // println!("read band data : {:?}", band_data.dim());
+                    container_arr
+                        .slice_mut(s![out_idx, .., ..])
+                        .assign(&band_data);
+                }
+                container_arr
+            }
+        };
 
-        for (out_idx, band_index) in bands.iter().enumerate() {
-            let band = src.rasterband(*band_index)?;
+        if let Some(masked_value) = self.cf_masked_value {
+            for (out_idx, band_index) in bands.iter().enumerate() {
+                let band = src.rasterband(*band_index)?;
+                let no_data = band.no_data_value();
+                let (scale, offset) = self.scale_offset[(*band_index - 1) as usize];
+                container_arr
+                    .slice_mut(s![out_idx, .., ..])
+                    .mapv_inplace(|v| {
+                        let raw = v.to_f64().unwrap_or(0.0);
+                        let decoded = match no_data {
+                            Some(nd) if raw == nd => masked_value,
+                            _ => raw * scale + offset,
+                        };
+                        P::from(decoded).unwrap_or(v)
+                    });
+            }
+        }
 
-            let band_data = try_boundless(
-                &src,
-                &band,
-                &win,
-                geo,
-                &self.spatial_info,
-                tile_bounds_xy,
-                is_skewed,
-                e_resample_alg,
-            );
-            let band_data = if let Some(d) = band_data {
-                d
-            } else {
-                try_overview(
-                    &band,
-                    &win,
-                    // req_overview as f64,
-                    geo,
-                    &self.spatial_info,
-                    tile_bounds_xy,
-                    is_skewed,
-                    e_resample_alg,
-                )?
-            };
-
-            // println!("read band data : {:?}", band_data.dim());
-            container_arr
-                .slice_mut(s![out_idx, .., ..])
-                .assign(&band_data);
+        if self.stretch_mode != StretchMode::None {
+            for (out_idx, band_index) in bands.iter().enumerate() {
+                // `self.min_max()`, not the raw `self.min_max` field: with CF decoding active the
+                // values being stretched below are already in physical units (see the block
+                // above), so the domain they're stretched against has to be too.
+                let domain = self
+                    .min_max()
+                    .get((*band_index - 1) as usize)
+                    .copied()
+                    .unwrap_or((0.0, 255.0));
+                let (lo, hi) = match self.stretch_mode {
+                    StretchMode::None => unreachable!(),
+                    StretchMode::MinMax => domain,
+                    StretchMode::Percentile { low, high } => {
+                        let histogram = self
+                            .statistics_with_range(*band_index as usize, Some(win), Some(domain))?
+                            .histogram()
+                            .clone();
+                        (histogram.value_at_fraction(low), histogram.value_at_fraction(high))
+                    }
+                };
+                container_arr
+                    .slice_mut(s![out_idx, .., ..])
+                    .mapv_inplace(|v| {
+                        let raw = v.to_f64().unwrap_or(0.0);
+                        P::from(stretch::stretch(raw, lo, hi)).unwrap_or(v)
+                    });
+            }
         }
 
         // TODO: evaluate if we have to read this every time
@@ -231,6 +495,302 @@ impl Raster {
         Ok(RawPixels::new(container_arr, driver_name))
     }
 
+    /// Read an arbitrary pixel `window` (not necessarily tile-aligned, e.g. from
+    /// [`Window::from_bounds`] over a caller-supplied geographic extent) resampled to
+    /// `out_width`x`out_height`, instead of [`Raster::read_tile`]'s fixed `TILE_SIZE` grid.
+    ///
+    /// `window` is first clipped to the raster's own pixel extent, so a request that only
+    /// partially overlaps the raster (or misses it entirely) reads what it can instead of
+    /// erroring; the clipped data lands in the proportional sub-rectangle of the output it
+    /// covers, leaving the rest zeroed.
+    pub fn read_window<P>(
+        &self,
+        window: &Window,
+        out_width: usize,
+        out_height: usize,
+        bands: Option<&[isize]>,
+        e_resample_alg: Option<ResampleAlg>,
+    ) -> Result<RawPixels<P>, MapEngineError>
+    where
+        P: GdalType + Copy + Num + NumCast,
+    {
+        let src = Dataset::open(&self.path)?;
+        let driver_name = self.driver_name();
+
+        let (raster_w, raster_h) = self.raster_size();
+        let full = Window::new(0, 0, raster_w, raster_h);
+        let (rows, cols) = window.toranges();
+        let (clipped, (row_offset, col_offset)) = full.from_slices(rows, cols, true)?;
+
+        let all_bands: Vec<_> = (1..=self.raster_count()).collect();
+        let bands = bands.unwrap_or(&all_bands);
+        let mut container_arr = Array3::<P>::zeros((bands.len(), out_height, out_width));
+
+        if clipped.is_zero() || window.is_zero() {
+            return Ok(RawPixels::new(container_arr, driver_name));
+        }
+
+        // Where the clipped sub-window lands in the output grid, proportional to how much of the
+        // requested window it covers. Offsets are capped one short of the output edge so there's
+        // always at least one destination pixel left for `dst_width`/`dst_height` to clamp into,
+        // even when the requested window barely overlaps the raster near its far edge.
+        let dst_col_off = (col_offset as f64 * out_width as f64 / window.width as f64).round() as usize;
+        let dst_row_off = (row_offset as f64 * out_height as f64 / window.height as f64).round() as usize;
+        let dst_col_off = dst_col_off.min(out_width - 1);
+        let dst_row_off = dst_row_off.min(out_height - 1);
+        let dst_width = ((clipped.width as f64 * out_width as f64 / window.width as f64).round() as usize)
+            .clamp(1, out_width - dst_col_off);
+        let dst_height = ((clipped.height as f64 * out_height as f64 / window.height as f64).round() as usize)
+            .clamp(1, out_height - dst_row_off);
+
+        for (out_idx, band_index) in bands.iter().enumerate() {
+            let band = src.rasterband(*band_index)?;
+            let data = band.read_as_array::<P>(
+                (clipped.col_off, clipped.row_off),
+                (clipped.width, clipped.height),
+                (dst_width, dst_height),
+                e_resample_alg,
+            )?;
+            container_arr
+                .slice_mut(s![
+                    out_idx,
+                    dst_row_off..dst_row_off + dst_height,
+                    dst_col_off..dst_col_off + dst_width
+                ])
+                .assign(&data);
+        }
+
+        Ok(RawPixels::new(container_arr, driver_name))
+    }
+
+    /// Read the per-band value(s) at a single pixel, or `None` if `(row, col)` falls outside the
+    /// raster (negative, or at/past `raster_size()`). Intended for point queries rather than
+    /// tiling, so it reads straight from GDAL rather than going through [`Raster::read_tile`]'s
+    /// style/stretch pipeline.
+    pub fn read_point(
+        &self,
+        row: i32,
+        col: i32,
+        bands: Option<&[isize]>,
+    ) -> Result<Option<Vec<f64>>, MapEngineError> {
+        let (raster_w, raster_h) = self.raster_size();
+        if row < 0 || col < 0 || row as usize >= raster_h || col as usize >= raster_w {
+            return Ok(None);
+        }
+
+        let src = Dataset::open(&self.path)?;
+        let all_bands: Vec<_> = (1..=self.raster_count()).collect();
+        let bands = bands.unwrap_or(&all_bands);
+
+        let values = bands
+            .iter()
+            .map(|band_index| {
+                let band = src.rasterband(*band_index)?;
+                let data =
+                    band.read_as_array::<f64>((col as isize, row as isize), (1, 1), (1, 1), None)?;
+                Ok(data[[0, 0]])
+            })
+            .collect::<Result<Vec<f64>, MapEngineError>>()?;
+
+        Ok(Some(values))
+    }
+
+    /// Read a whole [`Metatile`] in a single windowed GDAL read, then slice it into its
+    /// constituent [`TILE_SIZE`]x[`TILE_SIZE`] tiles, instead of issuing one [`Raster::read_tile`]
+    /// call per tile.
+    ///
+    /// This amortises the CRS transform setup [`Tile::to_window`] pays and the disk seek GDAL
+    /// pays, across the whole block. Unlike [`Raster::read_tile`], it does not apply
+    /// [`Raster::with_cf_decoding`]/[`Raster::with_stretch_mode`]; callers needing those should
+    /// read tile-by-tile instead.
+    ///
+    /// Returns `(tile, pixels)` pairs in [`Metatile::tiles`]'s row-major order.
+    pub fn read_metatile<P>(
+        &self,
+        metatile: &Metatile,
+        bands: Option<&[isize]>,
+        e_resample_alg: Option<ResampleAlg>,
+    ) -> Result<Vec<(Tile, RawPixels<P>)>, MapEngineError>
+    where
+        P: GdalType + Copy + Num + NumCast,
+        P: ndarray::ScalarOperand,
+    {
+        let driver_name = self.driver_name();
+        let out_size = metatile.size as usize * TILE_SIZE;
+        let (window, _is_skewed) = metatile.to_window(self)?;
+
+        let block: RawPixels<P> =
+            self.read_window(&window, out_size, out_size, bands, e_resample_alg)?;
+        let block = block.as_array();
+
+        Ok(metatile
+            .tiles()
+            .into_iter()
+            .enumerate()
+            .map(|(i, tile)| {
+                let row = i / metatile.size as usize;
+                let col = i % metatile.size as usize;
+                let row_off = row * TILE_SIZE;
+                let col_off = col * TILE_SIZE;
+                let sub = block
+                    .slice(s![.., row_off..row_off + TILE_SIZE, col_off..col_off + TILE_SIZE])
+                    .to_owned();
+                (tile, RawPixels::new(sub, driver_name))
+            })
+            .collect())
+    }
+
+    /// Read a single band and apply a [`FocalFilter`] (e.g. a convolution [`crate::focal::Kernel`]
+    /// or a [`crate::focal::Morphology`] erode/dilate) to it.
+    ///
+    /// Unlike [`Raster::read_tile`], this reads a window enlarged by [`FocalFilter::padding`] so
+    /// the filter has real neighbouring pixels at every output position, then crops the result
+    /// back down to the tile's window so tile seams stay correct. Pixels the filter reaches past
+    /// the edge of the raster are resolved using its own `EdgeMode`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - Tile to read.
+    /// * `band` - Band to read (1-indexed).
+    /// * `filter` - Focal filter to apply.
+    /// * `e_resample_alg` - Resample algorithm to use in case interpolations are needed.
+    pub fn read_focal_tile<F: FocalFilter>(
+        &self,
+        tile: &Tile,
+        band: isize,
+        filter: &F,
+        e_resample_alg: Option<ResampleAlg>,
+    ) -> Result<RawPixels<f64>, MapEngineError> {
+        let src = Dataset::open(&self.path)?;
+        let driver_name = self.driver_name();
+        let (win, _) = tile.to_window(self)?;
+
+        let (left, right, top, bottom) = filter.padding();
+        let grown = win.grow(left as isize, right as isize, top as isize, bottom as isize);
+
+        let (raster_w, raster_h) = self.raster_size();
+        let col_start = grown.col_off.max(0);
+        let row_start = grown.row_off.max(0);
+        let col_end = (grown.col_off + grown.width as isize).min(raster_w as isize);
+        let row_end = (grown.row_off + grown.height as isize).min(raster_h as isize);
+
+        let out = if col_end <= col_start || row_end <= row_start {
+            Array2::<f64>::zeros((win.height, win.width))
+        } else {
+            let rasterband = src.rasterband(band)?;
+            let read_width = (col_end - col_start) as usize;
+            let read_height = (row_end - row_start) as usize;
+            let data = rasterband.read_as_array::<f64>(
+                (col_start, row_start),
+                (read_width, read_height),
+                (read_width, read_height),
+                e_resample_alg,
+            )?;
+            filter.apply(
+                &data,
+                win.row_off - row_start,
+                win.col_off - col_start,
+                win.width,
+                win.height,
+            )
+        };
+
+        Ok(RawPixels::new(out.insert_axis(Axis(0)), driver_name))
+    }
+
+    /// Read a single band and compute per-pixel [`Hillshade`] intensity from it.
+    ///
+    /// Like [`Raster::read_focal_tile`], this reads a window enlarged by [`Hillshade::padding`]
+    /// so the surface normal at every output pixel is estimated from real neighbours, then crops
+    /// the result back down to the tile's window so tile seams stay correct. The ground
+    /// resolution needed to scale `dz/dx`/`dz/dy` comes from [`Window::geotransform`] applied to
+    /// this raster's affine transform.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - Tile to read.
+    /// * `band` - Band to read (1-indexed), treated as elevation.
+    /// * `hillshade` - Lighting parameters to apply.
+    /// * `e_resample_alg` - Resample algorithm to use in case interpolations are needed.
+    pub fn read_hillshade_tile(
+        &self,
+        tile: &Tile,
+        band: isize,
+        hillshade: &Hillshade,
+        e_resample_alg: Option<ResampleAlg>,
+    ) -> Result<RawPixels<f64>, MapEngineError> {
+        let src = Dataset::open(&self.path)?;
+        let driver_name = self.driver_name();
+        let (win, _) = tile.to_window(self)?;
+
+        let (left, right, top, bottom) = hillshade.padding();
+        let grown = win.grow(left as isize, right as isize, top as isize, bottom as isize);
+
+        let (raster_w, raster_h) = self.raster_size();
+        let col_start = grown.col_off.max(0);
+        let row_start = grown.row_off.max(0);
+        let col_end = (grown.col_off + grown.width as isize).min(raster_w as isize);
+        let row_end = (grown.row_off + grown.height as isize).min(raster_h as isize);
+
+        let win_geo = win.geotransform(&self.geo);
+        let pixel_size = (win_geo.geo[0], -win_geo.geo[4]);
+
+        let out = if col_end <= col_start || row_end <= row_start {
+            Array2::<f64>::zeros((win.height, win.width))
+        } else {
+            let rasterband = src.rasterband(band)?;
+            let read_width = (col_end - col_start) as usize;
+            let read_height = (row_end - row_start) as usize;
+            let data = rasterband.read_as_array::<f64>(
+                (col_start, row_start),
+                (read_width, read_height),
+                (read_width, read_height),
+                e_resample_alg,
+            )?;
+            hillshade.shade(
+                &data,
+                win.row_off - row_start,
+                win.col_off - col_start,
+                win.width,
+                win.height,
+                pixel_size,
+            )
+        };
+
+        Ok(RawPixels::new(out.insert_axis(Axis(0)), driver_name))
+    }
+
+    /// Reclassify a single band, mapping each pixel's value to [`ReclassTable::get`]'s output.
+    ///
+    /// Values the table has no rule for fall back to the band's nodata value (or `0.0` if it has
+    /// none). Run [`ReclassTable::validate`] against this band's domain beforehand to guarantee
+    /// every pixel lands in exactly one rule instead of relying on that fallback.
+    ///
+    /// # Arguments
+    ///
+    /// * `band` - Band to read (1-indexed).
+    /// * `table` - Value-range rules to reclassify against.
+    pub fn reclassify<T>(&self, band: isize, table: &ReclassTable) -> Result<Array2<T>, MapEngineError>
+    where
+        T: GdalType + Copy + NumCast,
+    {
+        let src = Dataset::open(&self.path)?;
+        let rasterband = src.rasterband(band)?;
+        let no_data = rasterband.no_data_value();
+        let (raster_w, raster_h) = self.raster_size();
+        let data = rasterband.read_as_array::<f64>(
+            (0, 0),
+            (raster_w, raster_h),
+            (raster_w, raster_h),
+            None,
+        )?;
+
+        Ok(data.mapv(|v| {
+            let mapped = table.get(v).unwrap_or_else(|| no_data.unwrap_or(0.0));
+            T::from(mapped).unwrap_or_else(|| T::from(0.0).unwrap())
+        }))
+    }
+
     pub fn geo(&self) -> &GeoTransform {
         &self.geo
     }
@@ -257,7 +817,14 @@ impl Raster {
     }
 
     pub fn min_max(&self) -> Vec<(f64, f64)> {
-        self.min_max.clone()
+        if self.cf_masked_value.is_none() {
+            return self.min_max.clone();
+        }
+        self.min_max
+            .iter()
+            .zip(&self.scale_offset)
+            .map(|(&(min, max), &(scale, offset))| (min * scale + offset, max * scale + offset))
+            .collect()
     }
 
     pub fn intersects(&self, tile: &Tile) -> Result<bool, MapEngineError> {
@@ -267,6 +834,56 @@ impl Raster {
     }
 }
 
+/// Resolve a band's `(min, max)`, preferring statistics GDAL already has on hand over a forced
+/// raster scan.
+///
+/// Tries, in order: [`RasterBand::get_statistics`] without forcing a scan (picks up stats cached
+/// in a sidecar `.aux.xml` PAM file or written back by a prior `gdalinfo -stats`), the
+/// `STATISTICS_MINIMUM`/`STATISTICS_MAXIMUM` metadata items, and only then
+/// [`RasterBand::compute_raster_min_max`], which does force a scan (optionally approximated via
+/// `approx`, sampling overviews instead of full resolution).
+fn band_min_max(band: &RasterBand, approx: bool) -> Result<(f64, f64), MapEngineError> {
+    if let Ok(Some(stats)) = band.get_statistics(approx, false) {
+        return Ok((stats.min, stats.max));
+    }
+
+    if let (Some(min), Some(max)) = (
+        band.metadata_item("STATISTICS_MINIMUM", "")
+            .and_then(|v| v.parse::<f64>().ok()),
+        band.metadata_item("STATISTICS_MAXIMUM", "")
+            .and_then(|v| v.parse::<f64>().ok()),
+    ) {
+        return Ok((min, max));
+    }
+
+    let minmax = band.compute_raster_min_max(approx)?;
+    Ok((minmax.min, minmax.max))
+}
+
+/// Collect per-band `(min, max)` (via [`band_min_max`]) and `(scale_factor, add_offset)` for
+/// every band in `src`. `apply_skip` matches [`Raster::new`]'s original behavior of shrinking the
+/// stored range by 2% on each side, to avoid stretching against outlier extremes.
+fn band_stats(
+    src: &Dataset,
+    approx: bool,
+    apply_skip: bool,
+) -> Result<(Vec<(f64, f64)>, Vec<(f64, f64)>), MapEngineError> {
+    let mut min_max = vec![];
+    let mut scale_offset = vec![];
+    for b in 1..=src.raster_count() {
+        let band = src.rasterband(b)?;
+        let (min, max) = band_min_max(&band, approx)?;
+        if apply_skip {
+            let skip = (max - min) * 0.02;
+            min_max.push((min + skip, max - skip));
+        } else {
+            min_max.push((min, max));
+        }
+        scale_offset.push((band.scale().unwrap_or(1.0), band.offset().unwrap_or(0.0)));
+    }
+    Ok((min_max, scale_offset))
+}
+
 fn array_to_mem_dataset<N>(
     arr: Array2<N>,
     transform: &GeoTransform,
@@ -309,6 +926,7 @@ fn reproject<N>(
     destination: Array2<N>,
     dst_transform: &GeoTransform,
     dst_spatial_info: &SpatialInfo,
+    resample_alg: ResampleAlg,
 ) -> Result<Array2<N>, MapEngineError>
 where
     N: GdalType + Copy,
@@ -322,12 +940,7 @@ where
     let dst_band = dst_dataset.rasterband(1)?;
 
     dst_band
-        .read_as_array::<N>(
-            (0, 0),
-            dst_shape,
-            (TILE_SIZE, TILE_SIZE),
-            Some(gdal::raster::ResampleAlg::NearestNeighbour),
-        )
+        .read_as_array::<N>((0, 0), dst_shape, (TILE_SIZE, TILE_SIZE), Some(resample_alg))
         .map_err(From::from)
 }
 
@@ -338,6 +951,7 @@ fn read_and_reproject<N>(
     spatial_info: &SpatialInfo,
     tile_bounds_xy: (f64, f64, f64, f64),
     e_resample_alg: Option<ResampleAlg>,
+    warp_target: &WarpTarget,
 ) -> Result<Array2<N>, MapEngineError>
 where
     N: GdalType + Copy + Num,
@@ -353,15 +967,24 @@ where
 
     let arr = Array::from_iter(d.data).into_shape((win.width as usize, win.height as usize))?;
 
-    let res_x = win_geo.geo[1];
-    let res_y = win_geo.geo[5];
+    let (res_x, res_y) = warp_target
+        .resolution
+        .unwrap_or((win_geo.geo[1], win_geo.geo[5]));
     let (min_x, max_y, max_x, min_y) = tile_bounds_xy;
-    let mercator_geo = &GeoTransform::new(&[min_x, res_x, 0.0, max_y, 0.0, res_y]);
+    let dst_geo = &GeoTransform::new(&[min_x, res_x, 0.0, max_y, 0.0, res_y]);
     let dst_cols = ((max_x - min_x) / res_x) as usize;
     let dst_rows = ((max_y - min_y) / -res_y) as usize;
     let dst_shape = (dst_cols, dst_rows);
     let dst_arr = Array2::<N>::zeros(dst_shape);
-    reproject(arr, &win_geo, spatial_info, dst_arr, mercator_geo, &SpatialInfo { epsg_code: Some(3857), proj4: None, wkt: None, esri: None })
+    reproject(
+        arr,
+        &win_geo,
+        spatial_info,
+        dst_arr,
+        dst_geo,
+        &warp_target.spatial_info,
+        warp_target.resample_alg,
+    )
 }
 
 fn try_overview<N>(
@@ -373,12 +996,21 @@ fn try_overview<N>(
     tile_bounds_xy: (f64, f64, f64, f64),
     is_skewed: bool,
     e_resample_alg: Option<ResampleAlg>,
+    warp_target: &WarpTarget,
 ) -> Result<Array2<N>, MapEngineError>
 where
     N: GdalType + Copy + Num,
 {
     if is_skewed {
-        read_and_reproject(band, win, geo, spatial_info, tile_bounds_xy, e_resample_alg)
+        read_and_reproject(
+            band,
+            win,
+            geo,
+            spatial_info,
+            tile_bounds_xy,
+            e_resample_alg,
+            warp_target,
+        )
     } else {
         band.read_as_array::<N>(
             // (new_win.col_off, new_win.row_off),
@@ -392,6 +1024,48 @@ where
     }
 }
 
+/// Read every band in `bands` in a single dataset-level `RasterIO` call, into one band-sequential
+/// `(bands.len(), TILE_SIZE, TILE_SIZE)` buffer, instead of opening and reading each
+/// [`RasterBand`] separately.
+///
+/// Only handles the common case where `win` sits entirely within the raster's extent and doesn't
+/// need reprojecting: returns `None` for a skewed window or one that reaches past the raster's
+/// edge, so the caller falls back to [`try_boundless`]/[`try_overview`] per band, which know how
+/// to pad with nodata and resample from overviews.
+fn try_multiband<P>(
+    src: &Dataset,
+    bands: &[isize],
+    win: &Window,
+    is_skewed: bool,
+    e_resample_alg: Option<ResampleAlg>,
+) -> Option<Array3<P>>
+where
+    P: GdalType + Copy,
+{
+    if is_skewed || win.col_off < 0 || win.row_off < 0 {
+        return None;
+    }
+
+    let (raster_w, raster_h) = src.raster_size();
+    if win.col_off + win.width as isize > raster_w as isize
+        || win.row_off + win.height as isize > raster_h as isize
+    {
+        return None;
+    }
+
+    let buf = src
+        .read_as::<P>(
+            (win.col_off, win.row_off),
+            (win.width, win.height),
+            (bands.len(), TILE_SIZE, TILE_SIZE),
+            Some(bands.to_vec()),
+            e_resample_alg,
+        )
+        .ok()?;
+
+    Array3::from_shape_vec((bands.len(), TILE_SIZE, TILE_SIZE), buf.data).ok()
+}
+
 // Read pixels within a Window
 #[allow(clippy::too_many_arguments)]
 fn try_boundless<N>(
@@ -403,6 +1077,7 @@ fn try_boundless<N>(
     tile_bounds_xy: (f64, f64, f64, f64),
     is_skewed: bool,
     e_resample_alg: Option<ResampleAlg>,
+    warp_target: &WarpTarget,
 ) -> Option<Array2<N>>
 where
     N: GdalType + Copy + Num + NumCast,
@@ -446,7 +1121,15 @@ where
     // println!("factor: {:?}", factor);
 
     let data = if is_skewed {
-        read_and_reproject(band, &inter, geo, spatial_info, tile_bounds_xy, e_resample_alg)
+        read_and_reproject(
+            band,
+            &inter,
+            geo,
+            spatial_info,
+            tile_bounds_xy,
+            e_resample_alg,
+            warp_target,
+        )
     } else {
         let into_shape = (
             (TILE_SIZE as f64 / factor.0).floor() as usize,
@@ -520,6 +1203,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         )
         .unwrap();
         assert_eq!(arr.shape(), &[256, 256]);
@@ -538,6 +1222,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         )
         .unwrap();
         assert_eq!(arr.shape(), &[256, 256]);
@@ -554,6 +1239,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         )
         .unwrap();
         assert_eq!(arr.shape(), &[256, 256]);
@@ -573,6 +1259,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         )
         .unwrap();
         assert_eq!(arr.shape(), &[256, 256]);
@@ -593,6 +1280,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         );
         assert!(arr.is_some());
 
@@ -607,10 +1295,46 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         );
         assert!(arr.is_some());
     }
 
+    #[test]
+    fn test_try_multiband_matches_per_band_overview_read() {
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let src = Dataset::open(path).unwrap();
+        let band = src.rasterband(1).unwrap();
+
+        // A small window fully inside the raster, so the single RasterIO fast path applies.
+        let win = Window::new(0, 0, 64, 64);
+        let fast: Array3<f64> =
+            try_multiband(&src, &[1], &win, false, Some(ResampleAlg::Average)).unwrap();
+        assert_eq!(fast.shape(), &[1, TILE_SIZE, TILE_SIZE]);
+
+        let per_band = band
+            .read_as_array::<f64>(
+                (win.col_off, win.row_off),
+                (win.width, win.height),
+                (TILE_SIZE, TILE_SIZE),
+                Some(ResampleAlg::Average),
+            )
+            .unwrap();
+        assert_eq!(fast.slice(s![0, .., ..]), per_band);
+    }
+
+    #[test]
+    fn test_try_multiband_bails_out_of_bounds() {
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let src = Dataset::open(path).unwrap();
+
+        // Reaches past the raster's edge, so the caller must fall back to the per-band path.
+        let win = Window::new(-10, -10, 64, 64);
+        let fast: Option<Array3<f64>> =
+            try_multiband(&src, &[1], &win, false, Some(ResampleAlg::Average));
+        assert!(fast.is_none());
+    }
+
     #[test]
     fn test_non_intersecting_returns_no_data_tile() {
         let path = Path::new("src/tests/data/categorical_optimised.tif");
@@ -636,6 +1360,7 @@ mod test {
             tile_bounds_xy,
             false,
             None,
+            &WarpTarget::default(),
         )
         .unwrap();
 
@@ -670,6 +1395,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         )
         .unwrap();
 
@@ -690,6 +1416,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         )
         .unwrap();
 
@@ -725,6 +1452,7 @@ mod test {
             tile_bounds_xy,
             false,
             Some(ResampleAlg::Average),
+            &WarpTarget::default(),
         )
         .unwrap();
 
@@ -766,4 +1494,107 @@ mod test {
         let tile1 = Tile::new(303, 624, 10);
         assert!(!raster.intersects(&tile1).unwrap());
     }
+
+    #[test]
+    fn test_read_focal_tile_matches_tile_shape() {
+        use crate::focal::Kernel;
+
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let raster = Raster::new(path.into()).unwrap();
+        let tile = Tile::new(304, 624, 10);
+        let kernel = Kernel::new(vec![1.0; 9], 3, 3);
+
+        let arr = raster
+            .read_focal_tile(&tile, 1, &kernel, Some(ResampleAlg::Average))
+            .unwrap();
+        assert_eq!(arr.as_array().shape(), &[1, TILE_SIZE, TILE_SIZE]);
+    }
+
+    #[test]
+    fn test_read_focal_tile_with_morphology_matches_tile_shape() {
+        use crate::focal::{Morphology, MorphologyOperator};
+
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let raster = Raster::new(path.into()).unwrap();
+        let tile = Tile::new(304, 624, 10);
+        let morph = Morphology::new(MorphologyOperator::Dilate, 1, 1);
+
+        let arr = raster
+            .read_focal_tile(&tile, 1, &morph, Some(ResampleAlg::Average))
+            .unwrap();
+        assert_eq!(arr.as_array().shape(), &[1, TILE_SIZE, TILE_SIZE]);
+    }
+
+    #[test]
+    fn test_read_hillshade_tile_matches_tile_shape() {
+        use crate::hillshade::Hillshade;
+
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let raster = Raster::new(path.into()).unwrap();
+        let tile = Tile::new(304, 624, 10);
+        let hillshade = Hillshade::new(315.0, 45.0);
+
+        let arr = raster
+            .read_hillshade_tile(&tile, 1, &hillshade, Some(ResampleAlg::Average))
+            .unwrap();
+        assert_eq!(arr.as_array().shape(), &[1, TILE_SIZE, TILE_SIZE]);
+    }
+
+    #[test]
+    fn test_warp_target_default_is_web_mercator() {
+        let target = WarpTarget::default();
+        assert_eq!(target.spatial_info.epsg_code, Some(3857));
+        assert_eq!(target.resolution, None);
+        assert_eq!(target.resample_alg, ResampleAlg::NearestNeighbour);
+    }
+
+    #[test]
+    fn test_warp_target_new_overrides_crs_only() {
+        let wgs84 = SpatialInfo {
+            epsg_code: Some(4326),
+            proj4: None,
+            wkt: None,
+            esri: None,
+        };
+        let target = WarpTarget::new(wgs84.clone())
+            .with_resolution(0.01, -0.01)
+            .with_resample_alg(ResampleAlg::Average);
+        assert_eq!(target.spatial_info, wgs84);
+        assert_eq!(target.resolution, Some((0.01, -0.01)));
+        assert_eq!(target.resample_alg, ResampleAlg::Average);
+    }
+
+    #[test]
+    fn test_band_min_max_falls_back_to_compute_when_no_stats_stored() {
+        let path = Path::new("src/tests/data/chile_no_meta.tif");
+        let src = Dataset::open(path).unwrap();
+        let band = src.rasterband(1).unwrap();
+        let (min, max) = band_min_max(&band, true).unwrap();
+        assert!(min <= max);
+    }
+
+    #[test]
+    fn test_new_with_stats_matches_new() {
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let raster = Raster::new(path.into()).unwrap();
+        let raster_exact = Raster::new_with_stats(path.into(), false).unwrap();
+        assert_eq!(raster.min_max(), raster_exact.min_max());
+    }
+
+    #[test]
+    fn test_reclassify_maps_value_ranges_to_output_codes() {
+        use crate::reclass::ReclassRule;
+
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let raster = Raster::new(path.into()).unwrap();
+        let table = ReclassTable::new(vec![
+            ReclassRule::new(f64::MIN..1000.0, 1.0),
+            ReclassRule::new(1000.0..f64::MAX, 2.0),
+        ]);
+
+        let (raster_w, raster_h) = raster.raster_size();
+        let classified: Array2<u8> = raster.reclassify(1, &table).unwrap();
+        assert_eq!(classified.len(), raster_w * raster_h);
+        assert!(classified.iter().all(|&v| v == 1 || v == 2));
+    }
 }
@@ -2,13 +2,16 @@
 use crate::errors::MapEngineError;
 use crate::{
     cmap::{Composite, HandleGet},
+    filters::FilterChain,
     tiles::TILE_SIZE,
 };
+use exr::prelude::*;
 use gdal::raster::GdalType;
-use ndarray::{Array, Array3, Axis};
+use ndarray::{s, Array, Array3, Axis};
 use num_traits::{Num, NumCast};
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, Cursor, Write};
+use std::ops::{Index, IndexMut};
 use std::path::Path;
 
 /// Raw pixels read from a raster file.
@@ -33,6 +36,11 @@ where
 
                 Self { data, driver }
             }
+            #[cfg(feature = "gpu")]
+            driver::WGPU => {
+                let driver = Box::new(driver::Wgpu {});
+                Self { data, driver }
+            }
             _ => {
                 let driver = Box::new(driver::Generic {});
                 Self { data, driver }
@@ -60,8 +68,129 @@ where
     pub fn as_array(&self) -> &Array3<P> {
         &self.data
     }
+
+    /// The `(bands, rows, cols)` shape of the underlying pixel array.
+    pub fn shape(&self) -> (usize, usize, usize) {
+        let shape = self.data.shape();
+        (shape[0], shape[1], shape[2])
+    }
+
+    /// Encode the raw (unstyled) pixels as a lossless OpenEXR image.
+    ///
+    /// Unlike [`RawPixels::style`], this keeps the original pixel values
+    /// (cast to `f32`) instead of quantizing them through a colour map, which
+    /// is what scientific rasters (DEMs, radiance, ...) need for a faithful
+    /// export. Each raster band becomes its own EXR channel: a single band is
+    /// exported as a `Y` (luminance) channel, three bands as `R`/`G`/`B`, and
+    /// any other band count as `B0`, `B1`, ...
+    ///
+    /// # Arguments
+    ///
+    /// * `no_data_values` - Pixel values (one per band) to be encoded as `NaN`.
+    pub fn into_exr(&self, no_data_values: &[f64]) -> Result<Vec<u8>, MapEngineError> {
+        let shape = self.data.shape();
+        let (n_bands, height, width) = (shape[0], shape[1], shape[2]);
+
+        let channel_names: Vec<String> = match n_bands {
+            1 => vec!["Y".to_string()],
+            3 => vec!["R".to_string(), "G".to_string(), "B".to_string()],
+            _ => (0..n_bands).map(|band| format!("B{}", band)).collect(),
+        };
+
+        let channels: Vec<AnyChannel<FlatSamples>> = (0..n_bands)
+            .map(|band| {
+                let no_data_value = no_data_values.get(band).copied();
+                let samples: Vec<f32> = self
+                    .data
+                    .slice(s![band, .., ..])
+                    .iter()
+                    .map(|v| {
+                        let v: f64 = NumCast::from(*v).unwrap_or(0.0);
+                        match no_data_value {
+                            Some(nd) if (v - nd).abs() < f64::EPSILON => f32::NAN,
+                            _ => v as f32,
+                        }
+                    })
+                    .collect();
+                AnyChannel::new(channel_names[band].as_str(), FlatSamples::F32(samples))
+            })
+            .collect();
+
+        let layer = Layer::new(
+            (width, height),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(channels),
+        );
+        let image = Image::from_layer(layer);
+
+        let mut buffer = Cursor::new(Vec::<u8>::new());
+        image.write().to_buffered(&mut buffer)?;
+        Ok(buffer.into_inner())
+    }
+
+    /// Write the raw pixels to disk as an OpenEXR file.
+    ///
+    /// # Arguments
+    ///
+    /// * `out_path` - Path were to write the tile.
+    /// * `no_data_values` - Pixel values (one per band) to be encoded as `NaN`.
+    pub fn write_to_disk_exr(
+        &self,
+        out_path: &Path,
+        no_data_values: &[f64],
+    ) -> Result<(), MapEngineError> {
+        let exr_data = self.into_exr(no_data_values)?;
+        let mut file = File::create(out_path)?;
+        file.write_all(&exr_data[..])?;
+        Ok(())
+    }
+}
+
+/// Per-band pixel access by `(band, row, col)`, for callers who don't want to pull in `ndarray`
+/// just to post-process a tile (nodata fill, band math, ...).
+impl<P> Index<(usize, usize, usize)> for RawPixels<P>
+where
+    P: GdalType + Copy + Num + NumCast,
+    P: ndarray::ScalarOperand,
+{
+    type Output = P;
+
+    fn index(&self, (band, row, col): (usize, usize, usize)) -> &P {
+        &self.data[[band, row, col]]
+    }
+}
+
+/// In-place per-band pixel edits, e.g. burning a mask straight into the buffer.
+impl<P> IndexMut<(usize, usize, usize)> for RawPixels<P>
+where
+    P: GdalType + Copy + Num + NumCast,
+    P: ndarray::ScalarOperand,
+{
+    fn index_mut(&mut self, (band, row, col): (usize, usize, usize)) -> &mut P {
+        &mut self.data[[band, row, col]]
+    }
+}
+
+/// Yields pixels in band-sequential order: every pixel of band 0, then every pixel of band 1,
+/// and so on.
+impl<P> IntoIterator for RawPixels<P>
+where
+    P: GdalType + Copy + Num + NumCast,
+    P: ndarray::ScalarOperand,
+{
+    type Item = P;
+    type IntoIter = <Array3<P> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
 }
 
+/// Opaque white, the background [`StyledPixels::into_jpeg`] flattens nodata/transparent pixels
+/// onto when the caller doesn't ask for a different one.
+pub const DEFAULT_JPEG_BACKGROUND: [u8; 3] = [255, 255, 255];
+
 /// Pixels styled using the [`RawPixels::style`] method.
 pub struct StyledPixels {
     data: Array3<u8>,
@@ -81,12 +210,72 @@ impl StyledPixels {
             encoder.set_color(png::ColorType::Rgba);
             encoder.set_depth(png::BitDepth::Eight);
             let mut writer = encoder.write_header()?;
-            match self.driver {
-                driver::Driver::Generic => writer.write_image_data(&self.data.into_raw_vec())?,
-                driver::Driver::Mbtile => {
-                    writer.write_image_data(&self.data.into_iter().collect::<Vec<u8>>()[..])?
-                }
-            }
+            writer.write_image_data(&self.rgba_bytes())?;
+        }
+        w.flush()?;
+        drop(w);
+        Ok(buffer)
+    }
+
+    /// This buffer's pixels as flat `TILE_SIZE x TILE_SIZE` RGBA bytes in image row-major order,
+    /// honouring [`driver::Driver`]'s differing in-memory layouts the same way every encoder here
+    /// needs to.
+    fn rgba_bytes(&self) -> Vec<u8> {
+        match self.driver {
+            driver::Driver::Generic => self.data.clone().into_raw_vec(),
+            driver::Driver::Mbtile => self.data.clone().into_iter().collect(),
+        }
+    }
+
+    /// Encode this buffer as JPEG at `quality` (1-100, higher is better/larger). JPEG has no
+    /// alpha channel, so every pixel is alpha-composited onto `background` (an opaque RGB
+    /// triple) instead of simply discarding its alpha byte.
+    pub fn into_jpeg(self, quality: u8, background: [u8; 3]) -> Result<Vec<u8>, MapEngineError> {
+        let rgb: Vec<u8> = self
+            .rgba_bytes()
+            .chunks_exact(4)
+            .flat_map(|px| {
+                let alpha = px[3] as f32 / 255.0;
+                [0, 1, 2].map(|i| {
+                    (px[i] as f32 * alpha + background[i] as f32 * (1.0 - alpha)).round() as u8
+                })
+            })
+            .collect();
+        let mut buffer = Vec::<u8>::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality).encode(
+            &rgb,
+            TILE_SIZE as u32,
+            TILE_SIZE as u32,
+            image::ColorType::Rgb8,
+        )?;
+        Ok(buffer)
+    }
+
+    /// Encode this buffer as lossy WebP at `quality` (0.0-100.0, higher is better/larger),
+    /// keeping alpha.
+    pub fn into_webp(self, quality: f32) -> Vec<u8> {
+        webp::Encoder::from_rgba(&self.rgba_bytes(), TILE_SIZE as u32, TILE_SIZE as u32)
+            .encode(quality)
+            .to_vec()
+    }
+
+    /// Encode this buffer as a PNG using its own `(height, width)` dimensions, instead of the
+    /// fixed [`TILE_SIZE`].
+    ///
+    /// Only supports the [`driver::Driver::Generic`] layout (the one produced by
+    /// [`RawPixels::style`] and [`Composite::render_legend`](crate::cmap::Composite::render_legend)).
+    /// Use this for non-tile-shaped output; [`StyledPixels::into_png`] remains the right choice
+    /// for actual `TILE_SIZE`x`TILE_SIZE` tiles.
+    pub fn into_png_sized(self) -> Result<Vec<u8>, MapEngineError> {
+        let (height, width, _) = self.data.dim();
+        let mut buffer = Vec::<u8>::new();
+        let mut w: BufWriter<&mut Vec<u8>> = BufWriter::new(buffer.as_mut());
+        {
+            let mut encoder = png::Encoder::new(&mut w, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.data.into_raw_vec())?;
         }
         w.flush()?;
         drop(w);
@@ -105,15 +294,68 @@ impl StyledPixels {
         Ok(())
     }
 
+    /// Run a [`FilterChain`] over the styled RGBA buffer.
+    ///
+    /// Intended to run between [`RawPixels::style`] and [`StyledPixels::into_png`], e.g. to
+    /// apply a per-map post-processing pipeline (colour matrices, blurs, ...) before encoding.
+    pub fn filter(mut self, chain: &FilterChain) -> Self {
+        self.data = chain.apply(&self.data);
+        self
+    }
+
     #[allow(dead_code)]
     fn as_array(&self) -> &Array3<u8> {
         &self.data
     }
 
-    #[allow(dead_code)]
-    fn into_array(self) -> Array3<u8> {
+    /// The underlying `(height, width, 4)` straight (non-premultiplied) RGBA buffer.
+    pub(crate) fn into_array(self) -> Array3<u8> {
         self.data
     }
+
+    /// The `(bands, rows, cols)` shape of the underlying pixel array, i.e. `(4, height, width)`
+    /// for the RGBA buffer this type wraps.
+    pub fn shape(&self) -> (usize, usize, usize) {
+        let shape = self.data.shape();
+        (shape[2], shape[0], shape[1])
+    }
+}
+
+/// Per-band pixel access by `(band, row, col)`, mirroring [`RawPixels`]'s `(bands, rows, cols)`
+/// indexing even though the RGBA buffer itself is stored `(height, width, 4)`.
+impl Index<(usize, usize, usize)> for StyledPixels {
+    type Output = u8;
+
+    fn index(&self, (band, row, col): (usize, usize, usize)) -> &u8 {
+        &self.data[[row, col, band]]
+    }
+}
+
+/// In-place per-band pixel edits, e.g. burning a mask straight into the buffer.
+impl IndexMut<(usize, usize, usize)> for StyledPixels {
+    fn index_mut(&mut self, (band, row, col): (usize, usize, usize)) -> &mut u8 {
+        &mut self.data[[row, col, band]]
+    }
+}
+
+/// Yields pixels in band-sequential order: every pixel of band 0 (red), then band 1 (green),
+/// and so on, to match [`RawPixels`]'s iteration order despite the different storage layout.
+impl IntoIterator for StyledPixels {
+    type Item = u8;
+    type IntoIter = std::vec::IntoIter<u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let (bands, rows, cols) = self.shape();
+        let mut out = Vec::with_capacity(bands * rows * cols);
+        for band in 0..bands {
+            for row in 0..rows {
+                for col in 0..cols {
+                    out.push(self.data[[row, col, band]]);
+                }
+            }
+        }
+        out.into_iter()
+    }
 }
 
 impl Default for StyledPixels {
@@ -131,6 +373,8 @@ pub(crate) mod driver {
     pub struct Mbtile;
     pub struct Generic;
     pub const MBTILES: &str = "MBTiles";
+    /// Selects [`Wgpu`], the GPU-accelerated gradient driver (requires the `gpu` feature).
+    pub const WGPU: &str = "wgpu";
     pub enum Driver {
         Mbtile,
         Generic,
@@ -201,6 +445,237 @@ pub(crate) mod driver {
             ))
         }
     }
+
+    /// GPU-accelerated gradient styling (`RawPixels::new(..., WGPU)`).
+    ///
+    /// Offloads the per-pixel LUT lookup done by [`Generic`] to a wgpu compute shader. Only
+    /// single-band gradient composites can run on the GPU; anything else (RGB composites,
+    /// discrete palettes, multi-band data, or a device/adapter failing to initialise) falls
+    /// back to [`Generic::style`] so the behaviour is always correct, just not always fast.
+    #[cfg(feature = "gpu")]
+    pub struct Wgpu;
+
+    #[cfg(feature = "gpu")]
+    impl<P> Style<P> for Wgpu
+    where
+        P: GdalType + Copy + Num + NumCast,
+        P: ndarray::ScalarOperand,
+    {
+        fn style(
+            &self,
+            raw: &RawPixels<P>,
+            cmap: Composite,
+            no_data_values: Vec<f64>,
+        ) -> Result<StyledPixels, MapEngineError> {
+            let height = raw.data.shape()[1];
+            let width = raw.data.shape()[2];
+
+            if raw.data.shape()[0] == 1 {
+                if let Some((vmin, vmax, lut)) = cmap.gradient_lut(256) {
+                    let values: Vec<f32> = raw
+                        .data
+                        .index_axis(Axis(0), 0)
+                        .iter()
+                        .map(|v| NumCast::from(*v).unwrap_or(0.0))
+                        .collect();
+                    if let Some(pixels) =
+                        gpu::style_gradient(width, height, &values, vmin, vmax, &lut, &no_data_values)
+                    {
+                        let arr = unsafe {
+                            Array::from_shape_vec_unchecked((height, width, 4), pixels)
+                        };
+                        return Ok(StyledPixels::new(arr, driver::Driver::Generic));
+                    }
+                }
+            }
+
+            // Not a single-band gradient, or no GPU adapter available: use the CPU path.
+            Generic.style(raw, cmap, no_data_values)
+        }
+    }
+}
+
+/// GPU compute backend used by [`driver::Wgpu`]. Gated behind the `gpu` feature so the rest of
+/// the crate has no hard dependency on a GPU being available.
+#[cfg(feature = "gpu")]
+mod gpu {
+    use once_cell::sync::OnceCell;
+
+    struct GpuContext {
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+    }
+
+    static GPU_CONTEXT: OnceCell<Option<GpuContext>> = OnceCell::new();
+
+    const SHADER: &str = include_str!("gradient.wgsl");
+
+    fn context() -> Option<&'static GpuContext> {
+        GPU_CONTEXT
+            .get_or_init(|| {
+                pollster::block_on(async {
+                    let instance = wgpu::Instance::new(wgpu::Backends::all());
+                    let adapter = instance
+                        .request_adapter(&wgpu::RequestAdapterOptions::default())
+                        .await?;
+                    let (device, queue) = adapter
+                        .request_device(&wgpu::DeviceDescriptor::default(), None)
+                        .await
+                        .ok()?;
+                    Some(GpuContext { device, queue })
+                })
+            })
+            .as_ref()
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+    struct Params {
+        vmin: f32,
+        vmax: f32,
+        width: u32,
+        height: u32,
+        no_data_count: u32,
+        _padding: [u32; 3],
+        no_data: [f32; 8],
+    }
+
+    /// Run the gradient LUT lookup on the GPU. Returns `None` if no adapter/device is
+    /// available, in which case the caller should fall back to the CPU implementation.
+    pub(super) fn style_gradient(
+        width: usize,
+        height: usize,
+        values: &[f32],
+        vmin: f64,
+        vmax: f64,
+        lut: &[[u8; 4]],
+        no_data_values: &[f64],
+    ) -> Option<Vec<u8>> {
+        use wgpu::util::DeviceExt;
+
+        let ctx = context()?;
+
+        let mut no_data = [0f32; 8];
+        for (dst, src) in no_data.iter_mut().zip(no_data_values.iter()) {
+            *dst = *src as f32;
+        }
+        let params = Params {
+            vmin: vmin as f32,
+            vmax: vmax as f32,
+            width: width as u32,
+            height: height as u32,
+            no_data_count: no_data_values.len().min(8) as u32,
+            _padding: [0; 3],
+            no_data,
+        };
+        let lut_u32: Vec<u32> = lut.iter().map(|p| u32::from_le_bytes(*p)).collect();
+
+        let values_buf = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("wgpu_gradient_values"),
+                contents: bytemuck::cast_slice(values),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let lut_buf = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("wgpu_gradient_lut"),
+                contents: bytemuck::cast_slice(&lut_u32),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let params_buf = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("wgpu_gradient_params"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let out_size = (width * height * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+        let out_buf = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("wgpu_gradient_out"),
+            size: out_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging_buf = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("wgpu_gradient_staging"),
+            size: out_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let shader = ctx
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("wgpu_gradient_shader"),
+                source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+            });
+        let pipeline = ctx
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("wgpu_gradient_pipeline"),
+                layout: None,
+                module: &shader,
+                entry_point: "main",
+            });
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("wgpu_gradient_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: values_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: lut_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: out_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("wgpu_gradient_encoder"),
+            });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("wgpu_gradient_pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                (width as u32 + 15) / 16,
+                (height as u32 + 15) / 16,
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&out_buf, 0, &staging_buf, 0, out_size);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let slice = staging_buf.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = tx.send(res);
+        });
+        ctx.device.poll(wgpu::Maintain::Wait);
+        rx.recv().ok()?.ok()?;
+
+        let pixels = slice.get_mapped_range().to_vec();
+        staging_buf.unmap();
+        Some(pixels)
+    }
 }
 
 #[cfg(test)]
@@ -210,7 +685,7 @@ mod test {
     use crate::raster::Raster;
     use crate::tiles::Tile;
     use gdal::Dataset;
-    use ndarray::{arr3, s};
+    use ndarray::arr3;
     use std::path::PathBuf;
 
     #[test]
@@ -292,6 +767,80 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_into_exr() {
+        let arr = RawPixels::new(arr3(&[[[0.0, 0.25], [0.5, 1.]]]), "");
+        let exr_data = arr.into_exr(&[0.25]).unwrap();
+        // A valid EXR file starts with its magic number.
+        assert_eq!(&exr_data[0..4], &[0x76, 0x2f, 0x31, 0x01]);
+    }
+
+    #[test]
+    fn test_into_exr_accepts_other_band_counts() {
+        let arr = RawPixels::new(
+            arr3(&[[[0.0, 0.0]], [[0.0, 0.0]], [[0.0, 0.0]], [[0.0, 0.0]]]),
+            "",
+        );
+        assert!(arr.into_exr(&[0.0, 0.0, 0.0, 0.0]).is_ok());
+    }
+
+    #[test]
+    fn test_raw_pixels_shape_and_index() {
+        let arr = RawPixels::new(
+            arr3(&[[[0.0, 0.25], [0.5, 1.]], [[2.0, 2.25], [2.5, 3.]]]),
+            "",
+        );
+        assert_eq!(arr.shape(), (2, 2, 2));
+        assert_eq!(arr[(0, 1, 0)], 0.5);
+        assert_eq!(arr[(1, 0, 1)], 2.25);
+    }
+
+    #[test]
+    fn test_raw_pixels_index_mut_burns_in_place() {
+        let mut arr = RawPixels::new(arr3(&[[[0.0, 0.25], [0.5, 1.]]]), "");
+        arr[(0, 0, 1)] = 9.0;
+        assert_eq!(arr[(0, 0, 1)], 9.0);
+    }
+
+    #[test]
+    fn test_raw_pixels_into_iter_is_band_sequential() {
+        let arr = RawPixels::new(arr3(&[[[0.0, 1.0], [2.0, 3.0]], [[4.0, 5.0], [6.0, 7.0]]]), "");
+        let pixels: Vec<f64> = arr.into_iter().collect();
+        assert_eq!(pixels, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn test_styled_pixels_shape_and_index() {
+        let arr = RawPixels::new(arr3(&[[[0.0, 0.25], [0.5, 1.]]]), "");
+        let styled = arr
+            .style(Composite::new_gradient(0.0, 1., &viridis), vec![0.25])
+            .unwrap();
+        assert_eq!(styled.shape(), (4, 2, 2));
+        assert_eq!(
+            [
+                styled[(0, 0, 0)],
+                styled[(1, 0, 0)],
+                styled[(2, 0, 0)],
+                styled[(3, 0, 0)],
+            ],
+            [68, 1, 84, 255]
+        );
+    }
+
+    #[test]
+    fn test_styled_pixels_into_iter_is_band_sequential() {
+        let arr = RawPixels::new(arr3(&[[[0.0, 0.25], [0.5, 1.]]]), "");
+        let styled = arr
+            .style(Composite::new_gradient(0.0, 1., &viridis), vec![0.25])
+            .unwrap();
+        let by_index: Vec<u8> = (0..4)
+            .flat_map(|band| (0..2).flat_map(move |row| (0..2).map(move |col| (band, row, col))))
+            .map(|coord| styled[coord])
+            .collect();
+        let by_iter: Vec<u8> = styled.into_iter().collect();
+        assert_eq!(by_iter, by_index);
+    }
+
     #[test]
     #[should_panic]
     fn test_style_rgb_tile_fails() {
@@ -0,0 +1,354 @@
+//! Per-band raster statistics and histograms.
+use super::*;
+
+/// Number of equal-width buckets a [`Raster::statistics`] histogram uses by default.
+const HISTOGRAM_BUCKETS: usize = 256;
+
+/// A fixed set of equal-width buckets over `[lo, hi]`.
+///
+/// Samples outside the range saturate into the first/last bucket instead of being dropped,
+/// mirroring how an overflowing cast into a narrower type clamps to that type's `MAX` elsewhere
+/// in this crate (see the `try_boundless` tests).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    lo: f64,
+    hi: f64,
+    buckets: Vec<u64>,
+}
+
+impl Histogram {
+    fn new(lo: f64, hi: f64) -> Self {
+        Histogram {
+            lo,
+            hi,
+            buckets: vec![0; HISTOGRAM_BUCKETS],
+        }
+    }
+
+    /// Rebuild a histogram from its already-computed bounds and bucket counts, bypassing
+    /// [`Histogram::add`]. Used by [`crate::cache`] to restore a histogram decoded from its
+    /// on-disk format without replaying every sample.
+    pub(crate) fn from_parts(lo: f64, hi: f64, buckets: Vec<u64>) -> Self {
+        Histogram { lo, hi, buckets }
+    }
+
+    fn add(&mut self, value: f64) {
+        let n = self.buckets.len();
+        let width = (self.hi - self.lo) / n as f64;
+        let idx = if width > 0.0 {
+            ((value - self.lo) / width) as isize
+        } else {
+            0
+        };
+        let idx = idx.clamp(0, n as isize - 1) as usize;
+        self.buckets[idx] += 1;
+    }
+
+    /// The `[lo, hi]` range the buckets span.
+    pub fn bounds(&self) -> (f64, f64) {
+        (self.lo, self.hi)
+    }
+
+    /// Per-bucket sample counts, in ascending value order.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// The approximate value at the given cumulative fraction of samples (e.g. `0.02` for the
+    /// 2nd percentile), used by [`crate::raster::stretch::StretchMode::Percentile`].
+    ///
+    /// Walks buckets in ascending order until their running share of the total reaches
+    /// `fraction`, then reports that bucket's lower edge. An empty histogram reports [`Self::bounds`]'s
+    /// lower bound.
+    pub fn value_at_fraction(&self, fraction: f64) -> f64 {
+        let total: u64 = self.buckets.iter().sum();
+        if total == 0 {
+            return self.lo;
+        }
+
+        let width = (self.hi - self.lo) / self.buckets.len() as f64;
+        let target = (fraction.clamp(0.0, 1.0) * total as f64).ceil() as u64;
+        let mut cumulative = 0u64;
+        for (i, &count) in self.buckets.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= target {
+                return self.lo + i as f64 * width;
+            }
+        }
+        self.hi
+    }
+}
+
+/// Per-band summary statistics computed in a single pass over a window's pixels (or the whole
+/// raster), skipping the band's nodata value.
+///
+/// Mirrors `gdalinfo -stats`: min/max/mean/standard deviation/valid-pixel count, plus a bucketed
+/// [`Histogram`]. As with a sorted map's `min`/`max`, an empty (or all-nodata) window reports
+/// `None` for every statistic instead of a meaningless default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandStats {
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: Option<f64>,
+    std_dev: Option<f64>,
+    count: u64,
+    histogram: Histogram,
+}
+
+impl BandStats {
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        self.mean
+    }
+
+    pub fn std_dev(&self) -> Option<f64> {
+        self.std_dev
+    }
+
+    /// Number of non-nodata pixels the statistics were computed over.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    /// Rebuild already-computed statistics from their parts, bypassing [`compute_band_stats`].
+    /// Used by [`crate::cache`] to restore statistics decoded from their on-disk format.
+    pub(crate) fn from_parts(
+        min: Option<f64>,
+        max: Option<f64>,
+        mean: Option<f64>,
+        std_dev: Option<f64>,
+        count: u64,
+        histogram: Histogram,
+    ) -> Self {
+        BandStats {
+            min,
+            max,
+            mean,
+            std_dev,
+            count,
+            histogram,
+        }
+    }
+}
+
+impl Raster {
+    /// Compute [`BandStats`] (min, max, mean, standard deviation, valid-pixel count and a value
+    /// histogram) for `band` over `window` (the whole raster when `None`), skipping nodata.
+    ///
+    /// The histogram buckets the observed value range from this raster's cached
+    /// [`Raster::min_max`] for `band`; use [`Raster::statistics_with_range`] to bucket against a
+    /// caller-supplied range instead (e.g. to compare histograms across tiles on a shared scale).
+    pub fn statistics(
+        &self,
+        band: usize,
+        window: Option<Window>,
+    ) -> Result<BandStats, MapEngineError> {
+        self.statistics_with_range(band, window, None)
+    }
+
+    /// Like [`Raster::statistics`], but buckets the histogram against `range` instead of this
+    /// band's cached min/max when `Some`.
+    pub fn statistics_with_range(
+        &self,
+        band: usize,
+        window: Option<Window>,
+        range: Option<(f64, f64)>,
+    ) -> Result<BandStats, MapEngineError> {
+        let src = Dataset::open(&self.path)?;
+        let rasterband = src.rasterband(band as isize)?;
+        let no_data = rasterband.no_data_value();
+
+        let (raster_w, raster_h) = self.raster_size();
+        let win = window.unwrap_or_else(|| Window::new(0, 0, raster_w, raster_h));
+        let data = rasterband.read_as_array::<f64>(
+            (win.col_off, win.row_off),
+            (win.width, win.height),
+            (win.width, win.height),
+            None,
+        )?;
+
+        // `self.min_max()` (unlike the raw `self.min_max` field) already decodes via
+        // `with_cf_decoding`'s scale/offset, so the fallback range stays in the same units as the
+        // data below.
+        let range = range.unwrap_or_else(|| self.min_max()[band - 1]);
+
+        // With CF decoding active, `no_data` (read straight off the GDAL band) is still a raw,
+        // packed DN, so it has to be compared against the raw sample *before* decoding it into
+        // physical units; decoded samples are passed on with `no_data: None` since nodata has
+        // already been filtered out here.
+        let scale_offset = self.cf_masked_value.map(|_| self.scale_offset[band - 1]);
+        let values = data.iter().filter_map(move |&raw| {
+            if let Some(nd) = no_data {
+                if raw == nd {
+                    return None;
+                }
+            }
+            Some(match scale_offset {
+                Some((scale, offset)) => raw * scale + offset,
+                None => raw,
+            })
+        });
+
+        Ok(compute_band_stats(values, None, range))
+    }
+}
+
+/// Single-pass core of [`Raster::statistics`]: running min/max, Welford's online mean/variance,
+/// valid-pixel count and a [`Histogram`] over `range`, skipping samples equal to `no_data`.
+fn compute_band_stats(
+    values: impl Iterator<Item = f64>,
+    no_data: Option<f64>,
+    range: (f64, f64),
+) -> BandStats {
+    let mut histogram = Histogram::new(range.0, range.1);
+
+    let mut count = 0u64;
+    let mut min = f64::INFINITY;
+    let mut max = f64::NEG_INFINITY;
+    let mut mean = 0.0;
+    let mut m2 = 0.0;
+
+    for value in values {
+        if let Some(nd) = no_data {
+            if value == nd {
+                continue;
+            }
+        }
+        count += 1;
+        min = min.min(value);
+        max = max.max(value);
+        let delta = value - mean;
+        mean += delta / count as f64;
+        let delta2 = value - mean;
+        m2 += delta * delta2;
+        histogram.add(value);
+    }
+
+    if count == 0 {
+        return BandStats {
+            min: None,
+            max: None,
+            mean: None,
+            std_dev: None,
+            count: 0,
+            histogram,
+        };
+    }
+
+    BandStats {
+        min: Some(min),
+        max: Some(max),
+        mean: Some(mean),
+        std_dev: Some((m2 / count as f64).sqrt()),
+        count,
+        histogram,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_statistics_skips_nodata_and_matches_count() {
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let raster = Raster::new(path.into()).unwrap();
+        let (raster_w, raster_h) = raster.raster_size();
+        let stats = raster.statistics(1, None).unwrap();
+
+        assert!(stats.count() > 0);
+        assert!(stats.count() <= (raster_w * raster_h) as u64);
+        assert!(stats.min().unwrap() <= stats.max().unwrap());
+        assert!(stats.std_dev().unwrap() >= 0.0);
+        assert_eq!(
+            stats.histogram().buckets().iter().sum::<u64>(),
+            stats.count()
+        );
+    }
+
+    #[test]
+    fn test_compute_band_stats_on_all_nodata_is_none() {
+        let values = vec![2.0, 2.0, 2.0, 2.0];
+        let stats = compute_band_stats(values.into_iter(), Some(2.0), (0.0, 10.0));
+
+        assert_eq!(stats.count(), 0);
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.max(), None);
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.std_dev(), None);
+    }
+
+    #[test]
+    fn test_compute_band_stats_matches_known_mean_and_std_dev() {
+        let values = vec![1.0, 2.0, 3.0, 4.0];
+        let stats = compute_band_stats(values.into_iter(), None, (0.0, 4.0));
+
+        assert_eq!(stats.count(), 4);
+        assert_eq!(stats.min(), Some(1.0));
+        assert_eq!(stats.max(), Some(4.0));
+        assert_eq!(stats.mean(), Some(2.5));
+        assert!((stats.std_dev().unwrap() - 1.118_033_988_75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_statistics_with_range_overrides_default_bucketing() {
+        let path = Path::new("src/tests/data/chile_optimised.tif");
+        let raster = Raster::new(path.into()).unwrap();
+        let win = Window::new(0, 0, 4, 4);
+
+        let default_stats = raster.statistics(1, Some(win)).unwrap();
+        let ranged_stats = raster
+            .statistics_with_range(1, Some(win), Some((0.0, 1.0)))
+            .unwrap();
+
+        assert_eq!(default_stats.count(), ranged_stats.count());
+        assert_ne!(
+            default_stats.histogram().bounds(),
+            ranged_stats.histogram().bounds()
+        );
+        assert_eq!(ranged_stats.histogram().bounds(), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_histogram_value_at_fraction_finds_percentile_bucket() {
+        let mut histogram = Histogram::new(0.0, 100.0);
+        for value in 0..100 {
+            histogram.add(value as f64);
+        }
+
+        assert!(histogram.value_at_fraction(0.0) <= 1.0);
+        assert!((histogram.value_at_fraction(0.5) - 50.0).abs() < 5.0);
+        assert!(histogram.value_at_fraction(1.0) >= 95.0);
+    }
+
+    #[test]
+    fn test_histogram_value_at_fraction_empty_is_lower_bound() {
+        let histogram = Histogram::new(0.0, 10.0);
+        assert_eq!(histogram.value_at_fraction(0.5), 0.0);
+    }
+
+    #[test]
+    fn test_histogram_saturates_out_of_range_samples() {
+        let mut histogram = Histogram::new(0.0, 10.0);
+        histogram.add(-5.0);
+        histogram.add(15.0);
+        histogram.add(5.0);
+
+        let buckets = histogram.buckets();
+        assert_eq!(buckets[0], 1);
+        assert_eq!(buckets[buckets.len() - 1], 1);
+        assert_eq!(buckets.iter().sum::<u64>(), 3);
+    }
+}
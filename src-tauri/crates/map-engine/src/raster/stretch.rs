@@ -0,0 +1,59 @@
+//! Contrast stretches applied when [`Raster::read_tile`](super::Raster::read_tile) downcasts a
+//! band's values into a narrower output type (e.g. `u8` tiles).
+//!
+//! A raw GDAL read into a narrower type just truncates/saturates out-of-range values, which
+//! flattens high-bit-depth imagery into a washed-out tile. [`StretchMode`] makes that the
+//! explicit [`StretchMode::None`] default and adds two linear alternatives that remap an input
+//! range onto `[0, 255]` before the final cast.
+
+/// How to remap a band's input values onto `[0, 255]` before [`Raster::read_tile`](super::Raster::read_tile)
+/// casts into its output type.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StretchMode {
+    /// Saturating cast: values outside the output type's range clamp to its min/max. The crate's
+    /// original (and still default) behavior.
+    #[default]
+    None,
+    /// Linearly map this band's cached [`Raster::min_max`](super::Raster::min_max) onto `[0,
+    /// 255]`.
+    MinMax,
+    /// Linearly map the values at the `low`/`high` cumulative-histogram fractions of the window
+    /// being read (e.g. `0.02` and `0.98`) onto `[0, 255]`, clamping outside that range. Discards
+    /// the tail of outlier DNs that would otherwise wash out a [`StretchMode::MinMax`] stretch.
+    Percentile { low: f64, high: f64 },
+}
+
+/// Linearly remap `value` from `[lo, hi]` onto `[0, 255]`, clamping outside that range.
+///
+/// A degenerate `lo == hi` range (every sampled value identical) maps to `0.0` rather than
+/// dividing by zero.
+pub(crate) fn stretch(value: f64, lo: f64, hi: f64) -> f64 {
+    let span = hi - lo;
+    if span <= 0.0 {
+        return 0.0;
+    }
+    (((value - lo) / span) * 255.0).clamp(0.0, 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stretch_maps_range_onto_0_255() {
+        assert_eq!(stretch(0.0, 0.0, 10.0), 0.0);
+        assert_eq!(stretch(10.0, 0.0, 10.0), 255.0);
+        assert_eq!(stretch(5.0, 0.0, 10.0), 127.5);
+    }
+
+    #[test]
+    fn test_stretch_clamps_outside_range() {
+        assert_eq!(stretch(-5.0, 0.0, 10.0), 0.0);
+        assert_eq!(stretch(15.0, 0.0, 10.0), 255.0);
+    }
+
+    #[test]
+    fn test_stretch_degenerate_range_is_zero() {
+        assert_eq!(stretch(5.0, 3.0, 3.0), 0.0);
+    }
+}
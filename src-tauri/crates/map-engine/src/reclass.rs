@@ -0,0 +1,164 @@
+//! Value-range reclassification ("remap") of a raster band.
+//!
+//! A standard GIS "reclass" tool: maps input value ranges (e.g. elevation bands) to output
+//! values (e.g. landcover codes) via [`crate::raster::Raster::reclassify`].
+//! [`ReclassTable::validate`] borrows the exhaustive-integer-range analysis from pattern matching
+//! to catch overlapping or incomplete rules ahead of time, instead of letting them silently fall
+//! through to nodata.
+use std::ops::{Range, RangeInclusive};
+use thiserror::Error;
+
+/// A single half-open `[start, end)` input range mapped to `output`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReclassRule {
+    pub range: Range<f64>,
+    pub output: f64,
+}
+
+impl ReclassRule {
+    pub fn new(range: Range<f64>, output: f64) -> Self {
+        Self { range, output }
+    }
+}
+
+/// An ordered set of [`ReclassRule`]s mapping input value ranges to output values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReclassTable {
+    rules: Vec<ReclassRule>,
+}
+
+impl ReclassTable {
+    pub fn new(rules: Vec<ReclassRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Map `value` to its rule's output, or `None` if no rule's range contains it.
+    pub fn get(&self, value: f64) -> Option<f64> {
+        self.rules
+            .iter()
+            .find(|rule| rule.range.contains(&value))
+            .map(|rule| rule.output)
+    }
+
+    /// Check that this table's rules map every value in `domain` to exactly one output.
+    ///
+    /// Mirrors exhaustiveness checking over pattern-match ranges: rules are sorted by their
+    /// range's start, then walked to detect overlap (`next.start < prev.end`) and gaps
+    /// (`next.start > prev.end`) between adjacent rules, and finally checked to cover `domain` end
+    /// to end. Reports the first problem found, in ascending value order.
+    pub fn validate(&self, domain: RangeInclusive<f64>) -> Result<(), ReclassError> {
+        let (domain_start, domain_end) = (*domain.start(), *domain.end());
+
+        if self.rules.is_empty() {
+            return Err(ReclassError::Uncovered(domain_start..domain_end));
+        }
+
+        let mut sorted: Vec<&ReclassRule> = self.rules.iter().collect();
+        sorted.sort_by(|a, b| a.range.start.partial_cmp(&b.range.start).unwrap());
+
+        if sorted[0].range.start > domain_start {
+            return Err(ReclassError::Uncovered(domain_start..sorted[0].range.start));
+        }
+
+        for pair in sorted.windows(2) {
+            let (prev, next) = (pair[0], pair[1]);
+            if next.range.start < prev.range.end {
+                return Err(ReclassError::Overlap(prev.range.clone(), next.range.clone()));
+            }
+            if next.range.start > prev.range.end {
+                return Err(ReclassError::Gap(prev.range.end..next.range.start));
+            }
+        }
+
+        let last = sorted.last().unwrap();
+        if last.range.end < domain_end {
+            return Err(ReclassError::Uncovered(last.range.end..domain_end));
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors surfaced by [`ReclassTable::validate`].
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ReclassError {
+    #[error("reclass rules {0:?} and {1:?} overlap")]
+    Overlap(Range<f64>, Range<f64>),
+    #[error("reclass rules leave a gap at {0:?}")]
+    Gap(Range<f64>),
+    #[error("reclass rules do not cover {0:?} of the band's domain")]
+    Uncovered(Range<f64>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rules: &[(Range<f64>, f64)]) -> ReclassTable {
+        ReclassTable::new(
+            rules
+                .iter()
+                .map(|(range, output)| ReclassRule::new(range.clone(), *output))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_get_maps_value_to_rule_output() {
+        let t = table(&[(0.0..10.0, 1.0), (10.0..20.0, 2.0)]);
+        assert_eq!(t.get(5.0), Some(1.0));
+        assert_eq!(t.get(15.0), Some(2.0));
+        assert_eq!(t.get(25.0), None);
+    }
+
+    #[test]
+    fn test_validate_accepts_exhaustive_table() {
+        let t = table(&[(0.0..10.0, 1.0), (10.0..20.0, 2.0), (20.0..30.0, 3.0)]);
+        assert!(t.validate(0.0..=30.0).is_ok());
+    }
+
+    #[test]
+    fn test_validate_detects_overlap() {
+        let t = table(&[(0.0..15.0, 1.0), (10.0..20.0, 2.0)]);
+        assert!(matches!(
+            t.validate(0.0..=20.0),
+            Err(ReclassError::Overlap(..))
+        ));
+    }
+
+    #[test]
+    fn test_validate_detects_internal_gap() {
+        let t = table(&[(0.0..10.0, 1.0), (15.0..20.0, 2.0)]);
+        assert_eq!(
+            t.validate(0.0..=20.0),
+            Err(ReclassError::Gap(10.0..15.0))
+        );
+    }
+
+    #[test]
+    fn test_validate_detects_uncovered_domain_start() {
+        let t = table(&[(5.0..20.0, 1.0)]);
+        assert_eq!(
+            t.validate(0.0..=20.0),
+            Err(ReclassError::Uncovered(0.0..5.0))
+        );
+    }
+
+    #[test]
+    fn test_validate_detects_uncovered_domain_end() {
+        let t = table(&[(0.0..15.0, 1.0)]);
+        assert_eq!(
+            t.validate(0.0..=20.0),
+            Err(ReclassError::Uncovered(15.0..20.0))
+        );
+    }
+
+    #[test]
+    fn test_validate_empty_table_is_uncovered() {
+        let t = ReclassTable::default();
+        assert_eq!(
+            t.validate(0.0..=20.0),
+            Err(ReclassError::Uncovered(0.0..20.0))
+        );
+    }
+}
@@ -47,3 +47,38 @@ fn test_translation_scale_shear() {
     let res = &(&trans * &scale) * &shear;
     assert_eq!(res.to_gdal().to_array(), GDAL_GEO);
 }
+
+#[test]
+fn test_rotation_matches_cos_sin_layout() {
+    let rot = GeoTransform::rotation(90.0);
+    let (a, b, c, d, e, f) = rot.to_tuple();
+    assert!((a - 0.0).abs() < 1e-10);
+    assert!((b - -1.0).abs() < 1e-10);
+    assert!((d - 1.0).abs() < 1e-10);
+    assert!((e - 0.0).abs() < 1e-10);
+    assert_eq!((c, f), (0.0, 0.0));
+}
+
+#[test]
+fn test_decompose_recovers_translation_scale_and_rotation() {
+    let trans = GeoTransform::translation(10.0, -20.0);
+    let scale = GeoTransform::scale(2.0, 3.0);
+    let rot = GeoTransform::rotation(30.0);
+    let combined = &(&trans * &rot) * &scale;
+
+    let (translation, (sx, sy), rotation, shear) = combined.decompose();
+    assert_eq!(translation, (10.0, -20.0));
+    assert!((sx - 2.0).abs() < 1e-10);
+    assert!((sy - 3.0).abs() < 1e-10);
+    assert!((rotation - 30.0).abs() < 1e-10);
+    assert!(shear.abs() < 1e-10);
+}
+
+#[test]
+fn test_decompose_is_identity_for_north_up_transform() {
+    let geo_transform = GeoTransform::from_gdal(&GDAL_GEO);
+    let (translation, _, rotation, shear) = geo_transform.decompose();
+    assert_eq!(translation, (geo_transform.xoff(), geo_transform.yoff()));
+    assert!((rotation - 0.0).abs() < 1e-10);
+    assert!(shear.abs() < 1e-10);
+}
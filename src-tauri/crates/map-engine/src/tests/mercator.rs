@@ -6,3 +6,27 @@ fn test_mercator() {
     assert_eq!(mercator.tile_size, 256);
     assert_eq!(mercator.zoom_for_pixel_size(&150.0), 10);
 }
+
+#[test]
+fn test_fit_bounds_centers_on_bbox_midpoint() {
+    let mercator: GlobalMercator = Default::default();
+    let (_, (lat, lon)) = mercator.fit_bounds([-10.0, -10.0, 10.0, 10.0], 1024.0, 1024.0, 20);
+    assert!(lon.abs() < 1e-9);
+    assert!(lat.abs() < 1e-9);
+}
+
+#[test]
+fn test_fit_bounds_clamps_to_max_zoom() {
+    let mercator: GlobalMercator = Default::default();
+    // A near-zero bbox would otherwise need an enormous zoom to "fill" the viewport.
+    let (zoom, _) = mercator.fit_bounds([0.0, 0.0, 1e-7, 1e-7], 1024.0, 1024.0, 18);
+    assert_eq!(zoom, 18);
+}
+
+#[test]
+fn test_fit_bounds_smaller_bbox_zooms_in_more() {
+    let mercator: GlobalMercator = Default::default();
+    let (world_zoom, _) = mercator.fit_bounds([-180.0, -85.0, 180.0, 85.0], 1024.0, 1024.0, 20);
+    let (city_zoom, _) = mercator.fit_bounds([-0.1, -0.1, 0.1, 0.1], 1024.0, 1024.0, 20);
+    assert!(city_zoom > world_zoom);
+}
@@ -1,5 +1,5 @@
 use crate::raster::{Raster, RawPixels};
-use crate::tiles::Tile;
+use crate::tiles::{Metatile, Tile};
 use gdal::raster::ResampleAlg;
 use ndarray::{s, Array, Array2, Array3};
 use std::path::PathBuf;
@@ -28,6 +28,23 @@ fn test_read_tile_all_bands() {
     assert_eq!(arr.as_array().shape(), &[2, 256, 256]);
 }
 
+#[test]
+fn test_read_metatile_matches_read_tile() {
+    let path = PathBuf::from("src/tests/data/chile_optimised.tif");
+    let raster = Raster::new(path).unwrap();
+    let metatile = Metatile::new(152, 312, 10, 2);
+    let block: Vec<(Tile, RawPixels<f64>)> =
+        raster.read_metatile(&metatile, Some(&[1]), None).unwrap();
+    assert_eq!(block.len(), 4);
+    assert_eq!(block[0].0, Tile::new(304, 624, 10));
+
+    for (tile, pixels) in &block {
+        assert_eq!(pixels.as_array().shape(), &[1, 256, 256]);
+        let expected: RawPixels<f64> = raster.read_tile(tile, Some(&[1]), None).unwrap();
+        assert_eq!(pixels.as_array(), expected.as_array());
+    }
+}
+
 #[test]
 fn test_read_tile_int() {
     let path = PathBuf::from("src/tests/data/categorical_optimised.tif");
@@ -41,6 +58,50 @@ fn test_read_tile_int() {
     assert_eq!(arr.as_array().slice(s![0, 253..256, 253..256]), expected);
 }
 
+#[test]
+fn test_read_tile_cf_decoding_without_packing_metadata_is_identity() {
+    // `chile_optimised.tif` carries no `scale_factor`/`add_offset`, so with_cf_decoding should
+    // fall back to scale=1.0/offset=0.0 and leave values unchanged.
+    let path = PathBuf::from("src/tests/data/chile_optimised.tif");
+    let raster = Raster::new(path).unwrap().with_cf_decoding(f64::NAN);
+    let tile = Tile::new(304, 624, 10);
+    let arr: RawPixels<f64> = raster.read_tile(&tile, Some(&[1]), None).unwrap();
+    assert_eq!(arr.as_array().shape(), &[1, 256, 256]);
+    let expected = Array::from_iter([
+        3671., 3648., 3480., 3696., 3821., 3807., 3599., 3760., 3843.,
+    ])
+    .into_shape((3, 3))
+    .unwrap();
+    assert_eq!(arr.as_array().slice(s![0, 253..256, 253..256]), expected);
+}
+
+#[test]
+fn test_read_tile_stretch_mode_minmax_spans_full_output_range() {
+    use crate::raster::StretchMode;
+
+    let path = PathBuf::from("src/tests/data/chile_optimised.tif");
+    let raster = Raster::new(path).unwrap().with_stretch_mode(StretchMode::MinMax);
+    let tile = Tile::new(304, 624, 10);
+    let arr: RawPixels<u8> = raster.read_tile(&tile, Some(&[1]), None).unwrap();
+    assert_eq!(arr.as_array().shape(), &[1, 256, 256]);
+    assert!(arr.as_array().iter().all(|&v| v <= 255));
+}
+
+#[test]
+fn test_read_tile_stretch_mode_percentile_clips_outliers() {
+    use crate::raster::StretchMode;
+
+    let path = PathBuf::from("src/tests/data/chile_optimised.tif");
+    let raster = Raster::new(path).unwrap().with_stretch_mode(StretchMode::Percentile {
+        low: 0.02,
+        high: 0.98,
+    });
+    let tile = Tile::new(304, 624, 10);
+    let arr: RawPixels<u8> = raster.read_tile(&tile, Some(&[1]), None).unwrap();
+    assert_eq!(arr.as_array().shape(), &[1, 256, 256]);
+    assert!(arr.as_array().iter().all(|&v| v <= 255));
+}
+
 #[test]
 fn test_read_tile_overview() {
     let path = PathBuf::from("src/tests/data/chile_optimised.tif");
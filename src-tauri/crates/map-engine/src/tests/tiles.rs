@@ -1,4 +1,101 @@
-use crate::tiles::Tile;
+use crate::tiles::{Tile, TileScheme};
+
+#[test]
+fn test_tile_covering() {
+    let tiles: Vec<_> = Tile::covering((-1.0, -1.0, 1.0, 1.0), 1).collect();
+    assert_eq!(tiles.len(), 4);
+    assert!(tiles.contains(&Tile::new(0, 0, 1)));
+    assert!(tiles.contains(&Tile::new(1, 0, 1)));
+    assert!(tiles.contains(&Tile::new(0, 1, 1)));
+    assert!(tiles.contains(&Tile::new(1, 1, 1)));
+}
+
+#[test]
+fn test_tile_covering_antimeridian() {
+    let tiles: Vec<_> = Tile::covering((170.0, -1.0, -170.0, 1.0), 1).collect();
+    assert_eq!(tiles.len(), 4);
+    assert!(tiles.contains(&Tile::new(0, 0, 1)));
+    assert!(tiles.contains(&Tile::new(1, 1, 1)));
+}
+
+#[test]
+fn test_tile_scheme_round_trip() {
+    let xyz = Tile::new(1, 2, 3);
+    let tms = xyz.to_tms();
+    assert_eq!(tms.x, 1);
+    assert_eq!(tms.y, 5); // 2^3 - 1 - 2
+    assert_eq!(tms.to_xyz(), xyz);
+    assert_eq!(tms.with_scheme(TileScheme::Tms).y, 5);
+}
+
+#[test]
+fn test_tile_scheme_preserves_geography() {
+    let xyz = Tile::new(1, 2, 3);
+    let tms = xyz.to_tms();
+    assert_eq!(xyz.ul(), tms.ul());
+    assert_eq!(xyz.bounds(), tms.bounds());
+}
+
+#[test]
+fn test_tile_neighbor() {
+    let tile = Tile::new(1, 1, 2);
+    assert_eq!(tile.neighbor(1, 0), Some(Tile::new(2, 1, 2)));
+    assert_eq!(tile.neighbor(0, -1), Some(Tile::new(1, 0, 2)));
+    // Wraps around the antimeridian.
+    assert_eq!(Tile::new(0, 1, 2).neighbor(-1, 0), Some(Tile::new(3, 1, 2)));
+    // No wrap at the poles.
+    assert_eq!(Tile::new(1, 0, 2).neighbor(0, -1), None);
+    assert_eq!(Tile::new(1, 3, 2).neighbor(0, 1), None);
+}
+
+#[test]
+fn test_tile_siblings() {
+    let siblings = Tile::new(3, 5, 4).siblings();
+    assert_eq!(
+        siblings,
+        [
+            Tile::new(2, 4, 4),
+            Tile::new(3, 4, 4),
+            Tile::new(3, 5, 4),
+            Tile::new(2, 5, 4),
+        ]
+    );
+    assert!(siblings.contains(&Tile::new(3, 5, 4)));
+}
+
+#[test]
+fn test_tile_ground_distance_equator() {
+    // A zoom-0 tile spans the whole globe; at the equator its width should be close to the
+    // WGS84 equatorial circumference.
+    let tile = Tile::new(0, 0, 0);
+    let (ew, _ns) = tile.ground_distance_m();
+    assert!((ew - 40_075_017.0).abs() < 10_000.0);
+}
+
+#[test]
+fn test_tile_ground_distance_shrinks_towards_poles() {
+    let equator_tile = Tile::from_lat_lng(0.0, 0.0, 4);
+    let polar_tile = Tile::from_lat_lng(0.0, 80.0, 4);
+    let (equator_ew, _) = equator_tile.ground_distance_m();
+    let (polar_ew, _) = polar_tile.ground_distance_m();
+    assert!(polar_ew < equator_ew);
+}
+
+#[test]
+fn test_tile_area_m2() {
+    let tile = Tile::new(1, 2, 3);
+    let (ew, ns) = tile.ground_distance_m();
+    assert_eq!(tile.area_m2(), ew * ns);
+}
+
+#[test]
+fn test_tile_quadkey() {
+    assert_eq!(Tile::new(3, 5, 3).quadkey(), "213");
+    assert_eq!(Tile::new(0, 0, 0).quadkey(), "");
+    assert_eq!(Tile::from_quadkey("213").unwrap(), Tile::new(3, 5, 3));
+    assert_eq!(Tile::from_quadkey("").unwrap(), Tile::new(0, 0, 0));
+    assert!(Tile::from_quadkey("04").is_err());
+}
 
 #[test]
 fn test_tile_from_lat_lng() {
@@ -1,3 +1,4 @@
+use crate::affine::GeoTransform;
 use crate::windows::{intersection, Window};
 
 #[test]
@@ -22,3 +23,50 @@ fn test_scale_window() {
     assert_eq!(win, Window::new(-1, -1, 102, 102));
     assert_eq!(win * (1.0 / 1.02), Window::new(0, 0, 100, 100));
 }
+
+#[test]
+fn test_grow_window() {
+    let win = Window::new(10, 10, 100, 100);
+    assert_eq!(win.grow(1, 2, 3, 4), Window::new(9, 7, 103, 107));
+    assert_eq!(win.grow(0, 0, 0, 0), win);
+}
+
+#[test]
+fn test_from_bounds_round_trips_with_bounds() {
+    let geo = GeoTransform::new(&[10.0, 0.0, 500000.0, 0.0, -10.0, 4000000.0]);
+    let win = Window::new(5, 5, 10, 10);
+    let (left, top, right, bottom) = win.bounds(&geo);
+    let rebuilt = Window::from_bounds(left, bottom, right, top, &geo).unwrap();
+    assert_eq!(rebuilt, win);
+}
+
+#[test]
+fn test_from_slices_non_boundless_in_range() {
+    let raster = Window::new(0, 0, 256, 256);
+    let (win, offset) = raster.from_slices((10, 20), (30, 50), false).unwrap();
+    assert_eq!(win, Window::new(30, 10, 20, 10));
+    assert_eq!(offset, (0, 0));
+}
+
+#[test]
+fn test_from_slices_non_boundless_out_of_range_errors() {
+    let raster = Window::new(0, 0, 256, 256);
+    assert!(raster.from_slices((-5, 20), (0, 50), false).is_err());
+}
+
+#[test]
+fn test_from_slices_boundless_clips_and_reports_offset() {
+    let raster = Window::new(0, 0, 256, 256);
+    // Requested window starts 5 pixels before the raster and 5 pixels before its top.
+    let (win, offset) = raster.from_slices((-5, 10), (-5, 10), true).unwrap();
+    assert_eq!(win, Window::new(0, 0, 10, 10));
+    assert_eq!(offset, (5, 5));
+}
+
+#[test]
+fn test_from_slices_boundless_fully_outside_is_empty() {
+    let raster = Window::new(0, 0, 256, 256);
+    let (win, offset) = raster.from_slices((300, 310), (300, 310), true).unwrap();
+    assert_eq!(win, Window::default());
+    assert_eq!(offset, (0, 0));
+}
@@ -9,21 +9,41 @@ use std::f64::consts::PI;
 
 const RE: f64 = 6378137.0;
 const EPSILON: f64 = 1e-14;
-// const LL_EPSILON: f64 = 1e-11;
+const LL_EPSILON: f64 = 1e-11;
 /// Size of the Tile in pixels
 pub const TILE_SIZE: usize = 256;
+/// Web Mercator's maximum representable latitude.
+const MERCATOR_LAT_LIMIT: f64 = 85.0511;
+
+/// Row-indexing convention a [`Tile`]'s `y` is expressed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileScheme {
+    /// Google/OSM convention (the default): row `0` is the top (north pole) tile.
+    Xyz,
+    /// OSGeo Tile Map Service convention: row `0` is the bottom (south pole) tile, as used by
+    /// TMS-indexed MBTiles archives.
+    Tms,
+}
+
+impl Default for TileScheme {
+    fn default() -> Self {
+        TileScheme::Xyz
+    }
+}
 
 /// An XYZ web mercator tile
 #[derive(Debug, PartialEq)]
 pub struct Tile {
     /// Column index
     pub x: u32,
-    /// Row index
+    /// Row index, expressed in `scheme`'s convention
     pub y: u32,
     /// Zoom level
     pub z: u32,
     /// Image extension
     ext: Option<String>,
+    /// Row-indexing convention `y` is expressed in.
+    scheme: TileScheme,
 }
 
 impl Tile {
@@ -33,9 +53,45 @@ impl Tile {
             y,
             z,
             ext: Some("png".to_string()),
+            scheme: TileScheme::Xyz,
         }
     }
 
+    /// This tile's `y`, translated to the XYZ convention regardless of `self.scheme`. Every
+    /// geographic calculation below is written in terms of XYZ and goes through this.
+    fn xyz_y(&self) -> u32 {
+        match self.scheme {
+            TileScheme::Xyz => self.y,
+            TileScheme::Tms => 2u32.pow(self.z) - 1 - self.y,
+        }
+    }
+
+    /// Return an equivalent `Tile` with `y` re-expressed in `scheme`'s convention.
+    pub fn with_scheme(&self, scheme: TileScheme) -> Self {
+        let xyz_y = self.xyz_y();
+        let y = match scheme {
+            TileScheme::Xyz => xyz_y,
+            TileScheme::Tms => 2u32.pow(self.z) - 1 - xyz_y,
+        };
+        Self {
+            x: self.x,
+            y,
+            z: self.z,
+            ext: self.ext.clone(),
+            scheme,
+        }
+    }
+
+    /// Convert to the TMS row convention, e.g. for reading from a TMS-indexed MBTiles archive.
+    pub fn to_tms(&self) -> Self {
+        self.with_scheme(TileScheme::Tms)
+    }
+
+    /// Convert to the XYZ (Google/OSM) row convention.
+    pub fn to_xyz(&self) -> Self {
+        self.with_scheme(TileScheme::Xyz)
+    }
+
     pub fn set_extension(&mut self, ext: &str) -> Result<(), MapEngineError> {
         if !SUPPORTED_FORMATS.contains(&ext) {
             return Err(MapEngineError::Msg(format!(
@@ -53,7 +109,8 @@ impl Tile {
 
     /// Return the coordinates (lat, long) of the upper-left tile corner
     pub fn ul(&self) -> (f64, f64) {
-        let (xtile, ytile, zoom) = self.to_tuple();
+        let (xtile, zoom) = (self.x, self.z);
+        let ytile = self.xyz_y();
         let z2: f64 = 2u32.pow(zoom).into();
         let lon_deg = (xtile as f64) / z2 * 360.0 - 180.0;
         let lat_rad = (PI * (1.0 - 2.0 * (ytile as f64) / z2)).sinh().atan();
@@ -71,7 +128,8 @@ impl Tile {
     ///
     /// The order of the output is (min_lng_deg, max_lat_deg, max_lng_deg, min_lat_deg)
     pub fn bounds(&self) -> (f64, f64, f64, f64) {
-        let (xtile, ytile, zoom) = self.to_tuple();
+        let (xtile, zoom) = (self.x, self.z);
+        let ytile = self.xyz_y();
         let z2: f64 = 2u32.pow(zoom).into();
 
         let min_lng_deg = (xtile as f64) / z2 * 360.0 - 180.0;
@@ -107,6 +165,39 @@ impl Tile {
         ]
     }
 
+    /// Return the east-west and north-south great-circle extents of the tile, in meters.
+    ///
+    /// The east-west distance is computed piecewise (three equal longitude increments summed
+    /// with [`haversine`], evaluated at the tile's center latitude) to avoid the "wrong way
+    /// around the globe" ambiguity a single two-point haversine call has for wide, low-zoom
+    /// tiles.
+    pub fn ground_distance_m(&self) -> (f64, f64) {
+        let (min_lng, max_lat, max_lng, min_lat) = self.bounds();
+        let (mut west, mut east) = (min_lng, max_lng);
+        if east < west {
+            std::mem::swap(&mut west, &mut east);
+        }
+        let center_lat = (min_lat + max_lat) / 2.0;
+
+        let delta = (east - west) / 3.0;
+        let e1 = west + delta;
+        let e2 = e1 + delta;
+        let ew_distance = haversine(west, center_lat, e1, center_lat)
+            + haversine(e1, center_lat, e2, center_lat)
+            + haversine(e2, center_lat, east, center_lat);
+
+        let ns_distance = haversine(west, min_lat, west, max_lat);
+
+        (ew_distance, ns_distance)
+    }
+
+    /// Return the tile's ground area, in square meters, approximated as `width * height` from
+    /// [`Tile::ground_distance_m`].
+    pub fn area_m2(&self) -> f64 {
+        let (ew, ns) = self.ground_distance_m();
+        ew * ns
+    }
+
     /// Return a tile from a lower zoom level that contains this tile
     pub fn zoom_out(&self, zoom: Option<u32>) -> Option<Self> {
         if self.z == 0 {
@@ -156,6 +247,78 @@ impl Tile {
         Some(tiles)
     }
 
+    /// Return the tile offset by `(dx, dy)` at the same zoom level.
+    ///
+    /// `x` wraps around the antimeridian modulo `2^z` (the map is cylindrical east-west).
+    /// `None` is returned if the resulting `y` falls outside `0..2^z`, since there's no
+    /// corresponding wrap at the poles.
+    pub fn neighbor(&self, dx: i64, dy: i64) -> Option<Self> {
+        let z2 = 2i64.pow(self.z);
+        let new_x = (self.x as i64 + dx).rem_euclid(z2) as u32;
+        let new_y = self.y as i64 + dy;
+        if new_y < 0 || new_y >= z2 {
+            return None;
+        }
+        Some(Tile::new(new_x, new_y as u32, self.z))
+    }
+
+    /// Return the four children of this tile's parent (including this tile itself), useful for
+    /// seamless edge resampling or building a 3x3 neighborhood for focal/kernel operations.
+    pub fn siblings(&self) -> [Self; 4] {
+        let parent_x = self.x - self.x % 2;
+        let parent_y = self.y - self.y % 2;
+        [
+            Tile::new(parent_x, parent_y, self.z),
+            Tile::new(parent_x + 1, parent_y, self.z),
+            Tile::new(parent_x + 1, parent_y + 1, self.z),
+            Tile::new(parent_x, parent_y + 1, self.z),
+        ]
+    }
+
+    /// Encode this tile as a Bing-style quadkey, e.g. the tile `(1, 2, 3)` becomes `"021"`.
+    ///
+    /// The resulting string's length always equals `self.z`.
+    pub fn quadkey(&self) -> String {
+        let mut quadkey = String::with_capacity(self.z as usize);
+        for i in (1..=self.z).rev() {
+            let mask = 1 << (i - 1);
+            let mut digit = 0u8;
+            if self.x & mask != 0 {
+                digit += 1;
+            }
+            if self.y & mask != 0 {
+                digit += 2;
+            }
+            quadkey.push((b'0' + digit) as char);
+        }
+        quadkey
+    }
+
+    /// Decode a Bing-style quadkey back into a `Tile`, inferring the zoom level from its length.
+    pub fn from_quadkey(qk: &str) -> Result<Self, MapEngineError> {
+        let z = qk.len() as u32;
+        let (mut x, mut y) = (0u32, 0u32);
+        for (i, c) in qk.chars().enumerate() {
+            let mask = 1 << (z - 1 - i as u32);
+            match c.to_digit(10) {
+                Some(1) => x |= mask,
+                Some(2) => y |= mask,
+                Some(3) => {
+                    x |= mask;
+                    y |= mask;
+                }
+                Some(0) => {}
+                _ => {
+                    return Err(MapEngineError::TileError(format!(
+                        "invalid quadkey digit {:?} in {:?}",
+                        c, qk
+                    )))
+                }
+            }
+        }
+        Ok(Self::new(x, y, z))
+    }
+
     /// Find the `Tile` intersecting the coordinate at a given zoom level
     pub fn from_lat_lng(lng: f64, lat: f64, zoom: u32) -> Self {
         let (x, y) = _xy(lng, lat);
@@ -179,6 +342,42 @@ impl Tile {
         Self::new(xtile, ytile, zoom)
     }
 
+    /// Enumerate every `Tile` at `zoom` intersecting `bounds` (`west, south, east, north`, in
+    /// degrees), clamped to the Web Mercator limits.
+    ///
+    /// `west > east` is treated as a bbox crossing the antimeridian and split into the two
+    /// sub-boxes either side of it.
+    pub fn covering(
+        bounds: (f64, f64, f64, f64),
+        zoom: u32,
+    ) -> impl Iterator<Item = Tile> {
+        let (west, south, east, north) = bounds;
+        let bboxes = if west > east {
+            vec![(-180.0, south, east, north), (west, south, 180.0, north)]
+        } else {
+            vec![(west, south, east, north)]
+        };
+
+        bboxes
+            .into_iter()
+            .flat_map(move |(w, s, e, n)| {
+                let w = f64::max(-180.0, w);
+                let s = f64::max(-MERCATOR_LAT_LIMIT, s);
+                let e = f64::min(180.0, e);
+                let n = f64::min(MERCATOR_LAT_LIMIT, n);
+
+                let ul_tile = Tile::from_lat_lng(w, n, zoom);
+                let lr_tile = Tile::from_lat_lng(e - LL_EPSILON, s + LL_EPSILON, zoom);
+
+                let range_x = ul_tile.x..=lr_tile.x;
+                let range_y = ul_tile.y..=lr_tile.y;
+                range_x
+                    .flat_map(move |x| range_y.clone().map(move |y| (x, y)))
+                    .map(move |(x, y)| Tile::new(x, y, zoom))
+                    .collect::<Vec<_>>()
+            })
+    }
+
     // pub fn to_window(&self, geo: &GeoTransform) -> Result<Window, MapEngineError> {
     //     let mercator = GlobalMercator::new(TILE_SIZE);
     //     let res = geo.geo[0];
@@ -237,6 +436,62 @@ impl Tile {
     }
 }
 
+/// A square block of `size x size` adjacent [`Tile`]s at the same zoom, read in a single
+/// windowed GDAL pass by [`crate::raster::Raster::read_metatile`] instead of one `read_tile` call
+/// per constituent tile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metatile {
+    /// Column index of the top-left constituent tile, in metatile units (i.e. `x * size` is that
+    /// tile's own `x`).
+    pub x: u32,
+    /// Row index of the top-left constituent tile, in metatile units.
+    pub y: u32,
+    /// Zoom level.
+    pub z: u32,
+    /// Side length in tiles. One of `1`, `2`, `4` or `8`.
+    pub size: u32,
+}
+
+impl Metatile {
+    pub fn new(x: u32, y: u32, z: u32, size: u32) -> Self {
+        Self { x, y, z, size }
+    }
+
+    /// The `size * size` constituent [`Tile`]s, in row-major order (so index `row * size + col`
+    /// is the tile at offset `(col, row)` from the top-left one).
+    pub fn tiles(&self) -> Vec<Tile> {
+        (0..self.size)
+            .flat_map(|dy| (0..self.size).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| Tile::new(self.x * self.size + dx, self.y * self.size + dy, self.z))
+            .collect()
+    }
+
+    /// Transform this metatile to the pixel [`Window`] spanning all of its constituent tiles, by
+    /// unioning the top-left tile's window with the bottom-right tile's window the same way
+    /// [`Tile::to_window`] unions its four vertices.
+    pub fn to_window(&self, raster: &Raster) -> Result<(Window, bool), MapEngineError> {
+        let tiles = self.tiles();
+        let (ul_win, ul_skewed) = tiles.first().expect("size >= 1").to_window(raster)?;
+        let (lr_win, lr_skewed) = tiles.last().expect("size >= 1").to_window(raster)?;
+
+        let col_off = cmp::min(ul_win.col_off, lr_win.col_off);
+        let row_off = cmp::min(ul_win.row_off, lr_win.row_off);
+        let width = cmp::max(
+            ul_win.col_off + ul_win.width as isize,
+            lr_win.col_off + lr_win.width as isize,
+        ) - col_off;
+        let height = cmp::max(
+            ul_win.row_off + ul_win.height as isize,
+            lr_win.row_off + lr_win.height as isize,
+        ) - row_off;
+
+        Ok((
+            Window::new(col_off, row_off, width as usize, height as usize),
+            ul_skewed || lr_skewed,
+        ))
+    }
+}
+
 fn get_row_cols(
     xs: &[f64],
     ys: &[f64],
@@ -339,30 +594,15 @@ fn _xy(lng: f64, lat: f64) -> (f64, f64) {
     (x, y)
 }
 
-// fn tiles(west: f64, south: f64, east: f64, north: f64, zoom: u32) -> impl Iterator<Item = Tile> {
-//     let bboxes = if west > east {
-//         let bbox_west = (-180.0, south, east, north);
-//         let bbox_east = (west, south, 180.0, north);
-//         vec![bbox_west, bbox_east]
-//     } else {
-//         vec![(west, south, east, north)]
-//     };
-
-//     bboxes
-//         .iter()
-//         .map(move |(mut w, mut s, mut e, mut n)| {
-//             w = f64::max(-180.0, w);
-//             s = f64::max(-85.051129, s);
-//             e = f64::min(180.0, e);
-//             n = f64::min(85.051129, n);
-//             let u_tile = tile(w, n, zoom);
-//             let lr_tile = tile(e - LL_EPSILON, s + LL_EPSILON, zoom);
-//             let range_x = u_tile.x..=lr_tile.x;
-//             let range_y = u_tile.y..=lr_tile.y;
-//             iproduct!(range_x, range_y).map(move |(i, j)| Tile::new(i, j, zoom.clone()))
-//         })
-//         .flatten()
-// }
+/// Great-circle distance between two lat/lng points, in meters, using the WGS84
+/// equatorial radius [`RE`].
+fn haversine(lng1: f64, lat1: f64, lng2: f64, lat2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lng = (lng2 - lng1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lng / 2.0).sin().powi(2);
+    2.0 * RE * a.sqrt().asin()
+}
 
 #[cfg(test)]
 mod tests {
@@ -1,14 +1,24 @@
 //! Types and helpers to work with vectors.
 use crate::{
     errors::MapEngineError,
+    mercator::GlobalMercator,
     tiles::{Tile, TILE_SIZE},
 };
 use quick_xml::de::from_str;
 use quick_xml::se::to_string;
+pub use rust_mapnik::mapnik::OutputFormat;
 use rust_mapnik::mapnik::MapnikMap;
+use rust_mapnik::pool::MapPool;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+/// Number of pre-loaded [`MapnikMap`]s [`Vector::tile_with_options`] keeps per distinct rendered
+/// style, so that many concurrent requests for the same style/size don't serialize on a single
+/// `mapnik_map_t`.
+const MAP_POOL_SIZE: usize = 4;
+
 /// Mapnik is used to render map tiles from vector data
 /// Following are mapnik stylesheet definiations
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -47,6 +57,49 @@ pub struct PolygonSymbolizer {
     pub fill_opacity: f64,
 }
 
+/// Where Mapnik's freetype text engine anchors a label relative to the feature's geometry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextPlacement {
+    Point,
+    Line,
+    Vertex,
+}
+
+/// Renders a label derived from a feature's attributes, e.g. a street name along a line or a
+/// place name at a point.
+///
+/// `face_name` must resolve against a font already registered via
+/// [`Vector::mapnik_register`]'s `font_dir`; an unregistered face name is a Mapnik render error,
+/// not a parse error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextSymbolizer {
+    /// Attribute/field expression to label with, e.g. `"[name]"`.
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@face-name")]
+    pub face_name: String,
+    #[serde(rename = "@size")]
+    pub size: f64,
+    #[serde(rename = "@fill")]
+    pub fill: String,
+    #[serde(rename = "@halo-fill")]
+    pub halo_fill: String,
+    #[serde(rename = "@halo-radius")]
+    pub halo_radius: f64,
+    #[serde(rename = "@placement")]
+    pub placement: TextPlacement,
+    #[serde(rename = "@dx")]
+    pub dx: f64,
+    #[serde(rename = "@dy")]
+    pub dy: f64,
+    #[serde(rename = "@allow-overlap")]
+    pub allow_overlap: bool,
+    /// Minimum pixel distance from other labels' anchors, for collision avoidance.
+    #[serde(rename = "@minimum-distance")]
+    pub minimum_distance: f64,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum VectorSymbolizer {
     #[serde(rename = "MarkerSymbolizer")]
@@ -55,24 +108,182 @@ pub enum VectorSymbolizer {
     Line(LineSymbolizer),
     #[serde(rename = "PolygonSymbolizer")]
     Polygon(PolygonSymbolizer),
+    #[serde(rename = "TextSymbolizer")]
+    Text(TextSymbolizer),
+}
+
+/// A predicate over a feature's attributes and the current zoom, matching Mapnik's rule/filter
+/// model. `And`/`Or` compose sub-selectors so a rule can test several conditions at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Selector {
+    MinZoom(u32),
+    MaxZoom(u32),
+    HasField(String),
+    FieldEquals(String, String),
+    FieldInRange(String, f64, f64),
+    And(Vec<Selector>),
+    Or(Vec<Selector>),
+}
+
+impl Selector {
+    /// Evaluate this selector against `zoom` and a feature's `attrs`. `MinZoom`/`MaxZoom` compare
+    /// inclusively, `And` requires every sub-selector to match and `Or` requires at least one.
+    pub fn matches(&self, zoom: u32, attrs: &HashMap<String, String>) -> bool {
+        match self {
+            Selector::MinZoom(min) => zoom >= *min,
+            Selector::MaxZoom(max) => zoom <= *max,
+            Selector::HasField(field) => attrs.contains_key(field),
+            Selector::FieldEquals(field, value) => attrs.get(field) == Some(value),
+            Selector::FieldInRange(field, low, high) => attrs
+                .get(field)
+                .and_then(|v| v.parse::<f64>().ok())
+                .map(|v| v >= *low && v < *high)
+                .unwrap_or(false),
+            Selector::And(selectors) => selectors.iter().all(|s| s.matches(zoom, attrs)),
+            Selector::Or(selectors) => selectors.iter().any(|s| s.matches(zoom, attrs)),
+        }
+    }
+
+    /// Render the attribute-testing part of this selector as a Mapnik `<Filter>` expression body
+    /// (e.g. `[field]='value'`, `[field]>=a and [field]<b`). `MinZoom`/`MaxZoom` have no filter
+    /// expression (zoom is expressed via `MinScaleDenominator`/`MaxScaleDenominator` instead) and
+    /// render as `None`.
+    fn to_filter_expr(&self) -> Option<String> {
+        match self {
+            Selector::MinZoom(_) | Selector::MaxZoom(_) => None,
+            Selector::HasField(field) => Some(format!("[{field}] != null")),
+            Selector::FieldEquals(field, value) => Some(format!(
+                "[{}]='{}'",
+                escape_filter_literal(field),
+                escape_filter_literal(value)
+            )),
+            Selector::FieldInRange(field, low, high) => {
+                Some(format!("[{field}]>={low} and [{field}]<{high}"))
+            }
+            Selector::And(selectors) => join_filter_exprs(selectors, " and "),
+            Selector::Or(selectors) => join_filter_exprs(selectors, " or "),
+        }
+    }
+}
+
+/// Escape a `'` about to be interpolated into a Mapnik filter-expression string literal (e.g.
+/// `[field]='value'`), so an embedded quote (real-world attribute data like `O'Brien`) can't
+/// prematurely close the literal and corrupt the generated `<Filter>` expression.
+fn escape_filter_literal(value: &str) -> String {
+    value.replace('\'', "\\'")
+}
+
+/// Join the filter expressions of `selectors` that have one (i.e. skipping `MinZoom`/`MaxZoom`)
+/// with `joiner`, returning `None` if none of them produced an expression.
+fn join_filter_exprs(selectors: &[Selector], joiner: &str) -> Option<String> {
+    let exprs: Vec<String> = selectors.iter().filter_map(Selector::to_filter_expr).collect();
+    if exprs.is_empty() {
+        None
+    } else {
+        Some(exprs.join(joiner))
+    }
+}
+
+/// A high-level, data-driven vector style, compiled into one or more Mapnik [`Rule`]s (each with
+/// its own `<Filter>` and `MinScaleDenominator`/`MaxScaleDenominator`) by [`VectorStyle::compile_rules`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VectorStyle {
+    /// One symbolizer applied to every feature, unconditionally.
+    SingleSymbol(VectorSymbolizer),
+    /// One symbolizer per exact attribute value.
+    Categorized(Vec<(String, VectorSymbolizer)>),
+    /// One symbolizer per half-open `[previous upper bound, upper bound)` numeric break, in
+    /// ascending order; the first break's lower bound is unbounded.
+    Graduated(Vec<(f64, VectorSymbolizer)>),
+}
+
+impl VectorStyle {
+    /// Compile this style into Mapnik [`Rule`]s that test `field`, with
+    /// `MinScaleDenominator`/`MaxScaleDenominator` derived from `zoom`.
+    pub fn compile_rules(&self, field: &str, zoom: u32) -> Vec<Rule> {
+        let (min_scale_denominator, max_scale_denominator) = scale_denominators_for_zoom(zoom);
+
+        match self {
+            VectorStyle::SingleSymbol(symbolizer) => vec![Rule {
+                filter: None,
+                min_scale_denominator,
+                max_scale_denominator,
+                symbolizer: vec![symbolizer.clone()],
+            }],
+            VectorStyle::Categorized(categories) => categories
+                .iter()
+                .map(|(value, symbolizer)| Rule {
+                    filter: Selector::FieldEquals(field.to_string(), value.clone())
+                        .to_filter_expr()
+                        .map(|text| Filter { text }),
+                    min_scale_denominator,
+                    max_scale_denominator,
+                    symbolizer: vec![symbolizer.clone()],
+                })
+                .collect(),
+            VectorStyle::Graduated(breaks) => {
+                let mut lower = f64::NEG_INFINITY;
+                breaks
+                    .iter()
+                    .map(|(upper, symbolizer)| {
+                        let filter =
+                            Selector::FieldInRange(field.to_string(), lower, *upper)
+                                .to_filter_expr()
+                                .map(|text| Filter { text });
+                        lower = *upper;
+                        Rule {
+                            filter,
+                            min_scale_denominator,
+                            max_scale_denominator,
+                            symbolizer: vec![symbolizer.clone()],
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
 }
 
-// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-// pub enum CategoryValue {
-//     IntegerValue(u32),
-//     FloatValue(f64),
-//     StringValue(String),
-// }
+/// Standard OGC/Mapnik "pixel size" (meters/pixel at 1:1 scale), used to turn a ground resolution
+/// into a scale denominator: `scale_denominator = resolution_m_per_px / OGC_PIXEL_SIZE_M`.
+const OGC_PIXEL_SIZE_M: f64 = 0.00028;
+
+/// `(min, max)` `ScaleDenominator` bounds for `zoom`: the scale at `zoom + 1` (more zoomed in, a
+/// smaller denominator) through the scale at `zoom` itself, so a compiled rule only applies at
+/// the tile's own zoom level.
+fn scale_denominators_for_zoom(zoom: u32) -> (Option<f64>, Option<f64>) {
+    let mercator = GlobalMercator::new(TILE_SIZE);
+    let min = mercator.resolution(&(zoom + 1)) / OGC_PIXEL_SIZE_M;
+    let max = mercator.resolution(&zoom) / OGC_PIXEL_SIZE_M;
+    (Some(min), Some(max))
+}
 
-// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-// pub enum VectorStyle {
-//     SingleSymbol(VectorSymbolizer),
-//     Categorized(Vec<(CategoryValue, VectorSymbolizer)>),
-//     Graduated(Vec<(f64, VectorSymbolizer)>),
-// }
+/// A Mapnik `<Filter>` element's text content, e.g. `[field]='value'`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Filter {
+    #[serde(rename = "$text")]
+    pub text: String,
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rule {
+    #[serde(rename = "Filter", skip_serializing_if = "Option::is_none", default)]
+    pub filter: Option<Filter>,
+
+    #[serde(
+        rename = "MinScaleDenominator",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub min_scale_denominator: Option<f64>,
+
+    #[serde(
+        rename = "MaxScaleDenominator",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub max_scale_denominator: Option<f64>,
+
     #[serde(rename = "$value")]
     pub symbolizer: Vec<VectorSymbolizer>,
 }
@@ -82,6 +293,24 @@ pub struct Style {
     #[serde(rename = "@name")]
     pub name: String,
 
+    /// Overall layer opacity, `0.0`-`1.0`.
+    #[serde(rename = "@opacity", skip_serializing_if = "Option::is_none", default)]
+    pub opacity: Option<f64>,
+
+    /// A raw Mapnik image filter string, e.g. `"agg-stack-blur(2,2)"`, `"gray"` or
+    /// `"colorize-alpha(...)"`, applied to this style's rendered layer before compositing.
+    #[serde(
+        rename = "@image-filters",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub image_filters: Option<String>,
+
+    /// Porter-Duff/separable blend mode this style's layer is composited with (e.g. `"multiply"`,
+    /// `"screen"`, `"overlay"`, `"darken"`).
+    #[serde(rename = "@comp-op", skip_serializing_if = "Option::is_none", default)]
+    pub comp_op: Option<String>,
+
     #[serde(rename = "Rule")]
     pub rule: Vec<Rule>,
 }
@@ -137,16 +366,41 @@ pub struct Map {
 ///
 /// could be any formats supported by gdal, eg. shapefile, geojson
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Vector {
     // unique name/id
     pub name: String,
 
     // mapnik xml stylesheet
     pub style: Map,
+
+    /// Data-driven styles keyed by the `<Style name="...">` they replace at render time: the
+    /// attribute field to test and the [`VectorStyle`] to compile into `<Rule>`s for the tile
+    /// being rendered. Empty for a plain XML-sourced [`Vector::from`], which renders its parsed
+    /// rules unchanged.
+    vector_styles: HashMap<String, (String, VectorStyle)>,
+
+    /// [`MapPool`]s backing [`Vector::tile_with_options`], keyed by `"{size}:{style_xml}"` so
+    /// that every call rendering the exact same style at the exact same size shares one pool
+    /// instead of loading a fresh [`MapnikMap`] per call. Shared (not re-created) across clones of
+    /// this `Vector`, since [`crate::vector::Vector`] is cloned out of [`HashMap`]-backed state on
+    /// every request.
+    pools: Arc<Mutex<HashMap<String, MapPool>>>,
+}
+
+/// Ignores [`Vector::pools`]: two `Vector`s are equal when their rendered config matches,
+/// regardless of which map pools happen to be warmed for either.
+impl PartialEq for Vector {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.style == other.style && self.vector_styles == other.vector_styles
+    }
 }
 
 impl Vector {
+    /// Register Mapnik's input plugins and fonts, process-wide, before creating any [`Vector`].
+    ///
+    /// `font_dir` is what [`TextSymbolizer::face_name`] resolves against, so it must be
+    /// registered before rendering any rule with a `TextSymbolizer`.
     pub fn mapnik_register(plugin_dir: String, font_dir: String) {
         MapnikMap::mapnik_register(plugin_dir, font_dir);
     }
@@ -155,16 +409,60 @@ impl Vector {
         Ok(Self {
             name: Uuid::new_v4().to_string(),
             style: from_str(&xml)?,
+            vector_styles: HashMap::new(),
+            pools: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    /// Replace the named Mapnik `<Style>`'s rules with `style` compiled against `field`,
+    /// evaluated per tile zoom in [`Vector::tile`] instead of once at parse time.
+    pub fn with_vector_style(mut self, style_name: String, field: String, style: VectorStyle) -> Self {
+        self.vector_styles.insert(style_name, (field, style));
+        self
+    }
+
     pub fn tile(&self, tile: &Tile) -> Result<Vec<u8>, MapEngineError> {
-        let mut m = MapnikMap::from_string(TILE_SIZE, TILE_SIZE, to_string(&self.style)?)?;
+        self.tile_with_options(tile, OutputFormat::Png, 1.0)
+    }
+
+    /// Like [`Vector::tile`], but lets the caller pick the output encoding (e.g. `.jpg`/`.webp`
+    /// raster tiles, or `.svg` for a vector-native response) and a `scale_factor` for high-DPI
+    /// (`@2x`/`@3x`) tiles: the map is rendered at `TILE_SIZE * scale_factor` pixels, with
+    /// `scale_factor` also passed through to Mapnik so stroke widths and labels scale up rather
+    /// than just the output resolution.
+    pub fn tile_with_options(
+        &self,
+        tile: &Tile,
+        format: OutputFormat,
+        scale_factor: f64,
+    ) -> Result<Vec<u8>, MapEngineError> {
+        let mut style = self.style.clone();
+        for mapnik_style in style.style.iter_mut() {
+            if let Some((field, vector_style)) = self.vector_styles.get(&mapnik_style.name) {
+                mapnik_style.rule = vector_style.compile_rules(field, tile.z);
+            }
+        }
+
+        let scaled_size = (TILE_SIZE as f64 * scale_factor).round() as usize;
+        let style_xml = to_string(&style)?;
+        let map = self.pool_for(scaled_size, &style_xml)?.get();
 
         let (minx, maxy, maxx, miny) = tile.bounds_xy();
-        let buf = m.read_extent(minx, miny, maxx, maxy)?;
-        m.free()?;
-        Ok(buf)
+        map.read_extent(minx, miny, maxx, maxy, format, scale_factor)
+    }
+
+    /// The [`MapPool`] pre-loaded with `style_xml` at `size`x`size`, creating and caching one the
+    /// first time this exact (size, style) combination is requested.
+    fn pool_for(&self, size: usize, style_xml: &str) -> Result<MapPool, MapEngineError> {
+        let key = format!("{size}:{style_xml}");
+        let mut pools = self.pools.lock().expect("pool cache mutex is never poisoned");
+        if let Some(pool) = pools.get(&key) {
+            return Ok(pool.clone());
+        }
+
+        let pool = MapPool::new(size, size, style_xml.to_string(), MAP_POOL_SIZE)?;
+        pools.insert(key, pool.clone());
+        Ok(pool)
     }
 }
 
@@ -219,7 +517,13 @@ mod test {
             srs: "epsg:3857".into(),
             style: vec![Style {
                 name: "My Style".into(),
+                opacity: None,
+                image_filters: None,
+                comp_op: None,
                 rule: vec![Rule {
+                    filter: None,
+                    min_scale_denominator: None,
+                    max_scale_denominator: None,
                     symbolizer: vec![
                         VectorSymbolizer::Polygon(PolygonSymbolizer {
                             fill: "red".into(),
@@ -338,4 +642,222 @@ mod test {
             .unwrap();
         file.write_all(&t).unwrap();
     }
+
+    fn attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_selector_min_max_zoom_are_inclusive() {
+        assert!(Selector::MinZoom(5).matches(5, &attrs(&[])));
+        assert!(!Selector::MinZoom(5).matches(4, &attrs(&[])));
+        assert!(Selector::MaxZoom(5).matches(5, &attrs(&[])));
+        assert!(!Selector::MaxZoom(5).matches(6, &attrs(&[])));
+    }
+
+    #[test]
+    fn test_selector_field_equals_and_in_range() {
+        let a = attrs(&[("class", "river"), ("pop", "42")]);
+        assert!(Selector::FieldEquals("class".into(), "river".into()).matches(0, &a));
+        assert!(!Selector::FieldEquals("class".into(), "road".into()).matches(0, &a));
+        assert!(Selector::FieldInRange("pop".into(), 0.0, 100.0).matches(0, &a));
+        assert!(!Selector::FieldInRange("pop".into(), 100.0, 200.0).matches(0, &a));
+        assert!(!Selector::HasField("missing".into()).matches(0, &a));
+    }
+
+    #[test]
+    fn test_field_equals_filter_expr_escapes_embedded_quotes() {
+        let selector = Selector::FieldEquals("name".into(), "O'Brien".into());
+        assert_eq!(selector.to_filter_expr().unwrap(), "[name]='O\\'Brien'");
+    }
+
+    #[test]
+    fn test_selector_and_requires_all_or_requires_any() {
+        let a = attrs(&[("class", "river")]);
+        let and = Selector::And(vec![
+            Selector::MinZoom(3),
+            Selector::FieldEquals("class".into(), "river".into()),
+        ]);
+        assert!(and.matches(3, &a));
+        assert!(!and.matches(2, &a));
+
+        let or = Selector::Or(vec![
+            Selector::FieldEquals("class".into(), "road".into()),
+            Selector::FieldEquals("class".into(), "river".into()),
+        ]);
+        assert!(or.matches(0, &a));
+    }
+
+    #[test]
+    fn test_vector_style_single_symbol_compiles_one_unconditional_rule() {
+        let style = VectorStyle::SingleSymbol(VectorSymbolizer::Polygon(PolygonSymbolizer {
+            fill: "red".into(),
+            fill_opacity: 1.0,
+        }));
+
+        let rules = style.compile_rules("class", 10);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].filter, None);
+        assert!(rules[0].min_scale_denominator.is_some());
+        assert!(rules[0].max_scale_denominator.is_some());
+
+        let xml = to_string(&rules[0]).unwrap();
+        let round_tripped: Rule = from_str(&xml).unwrap();
+        assert_eq!(round_tripped, rules[0]);
+    }
+
+    #[test]
+    fn test_vector_style_categorized_compiles_one_rule_per_value() {
+        let style = VectorStyle::Categorized(vec![
+            (
+                "river".into(),
+                VectorSymbolizer::Line(LineSymbolizer {
+                    stroke: "blue".into(),
+                    stroke_opacity: 1.0,
+                    stroke_width: 0.1,
+                }),
+            ),
+            (
+                "road".into(),
+                VectorSymbolizer::Line(LineSymbolizer {
+                    stroke: "gray".into(),
+                    stroke_opacity: 1.0,
+                    stroke_width: 0.2,
+                }),
+            ),
+        ]);
+
+        let rules = style.compile_rules("class", 10);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].filter.as_ref().unwrap().text, "[class]='river'");
+        assert_eq!(rules[1].filter.as_ref().unwrap().text, "[class]='road'");
+
+        for rule in &rules {
+            let xml = to_string(rule).unwrap();
+            let round_tripped: Rule = from_str(&xml).unwrap();
+            assert_eq!(&round_tripped, rule);
+        }
+    }
+
+    #[test]
+    fn test_vector_style_graduated_compiles_half_open_breaks() {
+        let style = VectorStyle::Graduated(vec![
+            (
+                10.0,
+                VectorSymbolizer::Polygon(PolygonSymbolizer {
+                    fill: "#ffeda0".into(),
+                    fill_opacity: 1.0,
+                }),
+            ),
+            (
+                100.0,
+                VectorSymbolizer::Polygon(PolygonSymbolizer {
+                    fill: "#f03b20".into(),
+                    fill_opacity: 1.0,
+                }),
+            ),
+        ]);
+
+        let rules = style.compile_rules("pop", 10);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            rules[0].filter.as_ref().unwrap().text,
+            "[pop]>=-inf and [pop]<10"
+        );
+        assert_eq!(
+            rules[1].filter.as_ref().unwrap().text,
+            "[pop]>=10 and [pop]<100"
+        );
+
+        for rule in &rules {
+            let xml = to_string(rule).unwrap();
+            let round_tripped: Rule = from_str(&xml).unwrap();
+            assert_eq!(&round_tripped, rule);
+        }
+    }
+
+    #[test]
+    fn test_text_symbolizer_line_placed_label_round_trips() {
+        let rule = Rule {
+            filter: None,
+            min_scale_denominator: None,
+            max_scale_denominator: None,
+            symbolizer: vec![VectorSymbolizer::Text(TextSymbolizer {
+                name: "[name]".into(),
+                face_name: "DejaVu Sans Book".into(),
+                size: 10.0,
+                fill: "#333333".into(),
+                halo_fill: "#ffffff".into(),
+                halo_radius: 1.0,
+                placement: TextPlacement::Line,
+                dx: 0.0,
+                dy: 0.0,
+                allow_overlap: false,
+                minimum_distance: 8.0,
+            })],
+        };
+
+        let xml = to_string(&rule).unwrap();
+        assert!(xml.contains("TextSymbolizer"));
+        assert!(xml.contains(r#"placement="line""#));
+
+        let round_tripped: Rule = from_str(&xml).unwrap();
+        assert_eq!(round_tripped, rule);
+    }
+
+    #[test]
+    fn test_style_image_filters_and_comp_op_round_trip() {
+        let style = Style {
+            name: "halo".into(),
+            opacity: Some(0.8),
+            image_filters: Some("agg-stack-blur(2,2)".into()),
+            comp_op: Some("multiply".into()),
+            rule: vec![Rule {
+                filter: None,
+                min_scale_denominator: None,
+                max_scale_denominator: None,
+                symbolizer: vec![VectorSymbolizer::Line(LineSymbolizer {
+                    stroke: "#000000".into(),
+                    stroke_opacity: 1.0,
+                    stroke_width: 2.0,
+                })],
+            }],
+        };
+
+        let xml = to_string(&style).unwrap();
+        assert!(xml.contains(r#"image-filters="agg-stack-blur(2,2)""#));
+        assert!(xml.contains(r#"comp-op="multiply""#));
+        assert!(xml.contains(r#"opacity="0.8""#));
+
+        let round_tripped: Style = from_str(&xml).unwrap();
+        assert_eq!(round_tripped, style);
+    }
+
+    #[test]
+    fn test_style_without_filters_or_comp_op_serializes_unchanged() {
+        let style = Style {
+            name: "plain".into(),
+            opacity: None,
+            image_filters: None,
+            comp_op: None,
+            rule: vec![Rule {
+                filter: None,
+                min_scale_denominator: None,
+                max_scale_denominator: None,
+                symbolizer: vec![VectorSymbolizer::Line(LineSymbolizer {
+                    stroke: "#000000".into(),
+                    stroke_opacity: 1.0,
+                    stroke_width: 2.0,
+                })],
+            }],
+        };
+
+        let xml = to_string(&style).unwrap();
+        assert!(!xml.contains("image-filters"));
+        assert!(!xml.contains("comp-op"));
+        assert!(!xml.contains(" opacity="));
+    }
 }
@@ -1,5 +1,6 @@
 //! Types and helpers to work with data windows.
 use crate::affine::GeoTransform;
+use crate::errors::MapEngineError;
 use crate::raster::SpatialInfo;
 use serde::{Deserialize, Serialize};
 use std::cmp;
@@ -91,24 +92,82 @@ impl Window {
         (xs[0], ys[0], xs[1], ys[1])
     }
 
-    // pub fn from_slices(self, rows: (i32, i32), cols: (i32, i32), boudless: bool) -> Self {}
-    // pub fn from_bounds(
-    //     self,
-    //     left: f64,
-    //     bottom: f64,
-    //     right: f64,
-    //     top: f64,
-    //     transform: GeoTransform,
-    // ) -> Self {
-    //     let (row_start, col_start) = transform.rowcol(left, top);
-    //     let (row_stop, col_stop) = transform.rowcol(right, bottom);
-    //     Self::new(
-    //         row_start,
-    //         col_start,
-    //         col_stop - col_start,
-    //         row_stop - row_start,
-    //     )
-    // }
+    /// Grow the window by the given number of pixels on each side.
+    ///
+    /// Unlike the [`Mul`] scaling operator, this grows (or, with negative arguments, shrinks)
+    /// each side independently, which is what a focal filter needs to request the exact
+    /// neighbourhood its kernel reaches around the window it has to produce.
+    pub fn grow(&self, left: isize, right: isize, top: isize, bottom: isize) -> Self {
+        Window::new(
+            self.col_off - left,
+            self.row_off - top,
+            (self.width as isize + left + right).max(0) as usize,
+            (self.height as isize + top + bottom).max(0) as usize,
+        )
+    }
+
+    /// Build a window from row and column pixel-index ranges (`(start, stop)`, `stop` exclusive),
+    /// treating `self` as the valid pixel extent (e.g. the full raster) those ranges are clipped
+    /// against.
+    ///
+    /// When `boundless` is `false`, the requested range must fit entirely inside `self` or this
+    /// errors. When `true`, a range extending past `self` is clamped to it instead, and the
+    /// returned `(row, col)` offset is where, relative to the *requested* range, the clamped
+    /// window's data begins — what a boundless read pads with nodata before that offset.
+    ///
+    /// This is what lets a tile at the edge of a raster's extent read the real data it overlaps
+    /// and render transparent padding for the rest, instead of failing outright.
+    pub fn from_slices(
+        &self,
+        rows: (isize, isize),
+        cols: (isize, isize),
+        boundless: bool,
+    ) -> Result<(Window, (usize, usize)), MapEngineError> {
+        let requested = Window::new(
+            cols.0,
+            rows.0,
+            (cols.1 - cols.0).max(0) as usize,
+            (rows.1 - rows.0).max(0) as usize,
+        );
+
+        if !boundless {
+            return match intersection(&[*self, requested]) {
+                Some(clipped) if clipped == requested => Ok((requested, (0, 0))),
+                _ => Err(MapEngineError::Msg(format!(
+                    "requested window {:?} falls outside of {:?}",
+                    requested, self
+                ))),
+            };
+        }
+
+        match intersection(&[*self, requested]) {
+            Some(clipped) => {
+                let row_offset = (clipped.row_off - requested.row_off) as usize;
+                let col_offset = (clipped.col_off - requested.col_off) as usize;
+                Ok((clipped, (row_offset, col_offset)))
+            }
+            None => Ok((Window::default(), (0, 0))),
+        }
+    }
+
+    /// Build a window covering the pixels between the spatial bounds `(left, bottom, right, top)`
+    /// under `transform`, by inverting it with [`GeoTransform::rowcol`].
+    pub fn from_bounds(
+        left: f64,
+        bottom: f64,
+        right: f64,
+        top: f64,
+        transform: &GeoTransform,
+    ) -> Result<Self, MapEngineError> {
+        let (row_start, col_start) = transform.rowcol(left, top)?;
+        let (row_stop, col_stop) = transform.rowcol(right, bottom)?;
+        Ok(Self::new(
+            col_start as isize,
+            row_start as isize,
+            (col_stop - col_start).max(0) as usize,
+            (row_stop - row_start).max(0) as usize,
+        ))
+    }
 }
 
 impl Mul<f64> for Window {
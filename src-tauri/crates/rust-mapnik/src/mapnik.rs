@@ -12,6 +12,18 @@ pub struct MapnikMap {
     pub map: *mut mapnik_map_t,
 }
 
+/// Output encoding for [`MapnikMap::read_extent`], mirroring mapnik's `image_util` format
+/// dispatch (`mapnik_image_to_*_blob`) plus the vector `svg-ng` render path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Png,
+    /// JPEG quality, `0`-`100`.
+    Jpeg(u8),
+    /// WebP quality, `0`-`100`.
+    WebP(u8),
+    Svg,
+}
+
 impl MapnikMap {
     fn check_error(&self) -> Result<(), MapnikError> {
         MapnikMap::check_error_(self.map)
@@ -62,17 +74,36 @@ impl MapnikMap {
         miny: f64,
         maxx: f64,
         maxy: f64,
+        format: OutputFormat,
+        scale_factor: f64,
     ) -> Result<Vec<u8>, MapnikError> {
         unsafe {
             let bbox = mapnik_bbox(minx, miny, maxx, maxy);
             mapnik_map_zoom_to_box(self.map, bbox);
             self.check_error()?;
 
-            let image = mapnik_map_render_to_image(self.map);
-            self.check_error()?;
-
-            let blob = mapnik_image_to_png_blob(image);
-            self.check_error()?;
+            let blob = if format == OutputFormat::Svg {
+                // SVG is rendered straight from the map, like mapnik's `render_to_file1`
+                // "svg-ng" mode; there's no intermediate raster `mapnik_image_t`, so there's
+                // nothing for `scale_factor` (a raster stroke/text/resolution multiplier) to do.
+                let blob = mapnik_map_render_to_svg_blob(self.map);
+                self.check_error()?;
+                blob
+            } else {
+                let image = mapnik_map_render_to_image_with_scale_factor(self.map, scale_factor);
+                self.check_error()?;
+
+                let blob = match format {
+                    OutputFormat::Png => mapnik_image_to_png_blob(image),
+                    OutputFormat::Jpeg(quality) => {
+                        mapnik_image_to_jpeg_blob(image, quality as i32)
+                    }
+                    OutputFormat::WebP(quality) => mapnik_image_to_webp_blob(image, quality as i32),
+                    OutputFormat::Svg => unreachable!(),
+                };
+                self.check_error()?;
+                blob
+            };
 
             let data_slice = std::slice::from_raw_parts(
                 (*blob).ptr as *const u8,
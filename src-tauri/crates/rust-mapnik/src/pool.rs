@@ -0,0 +1,94 @@
+//! A pool of pre-loaded [`MapnikMap`] clones, so concurrent tile requests for the same style
+//! don't serialize on a single `mapnik_map_t`.
+use crate::errors::MapnikError;
+use crate::mapnik::MapnikMap;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+
+/// `MapnikMap` wraps a raw `*mut mapnik_map_t`, which isn't `Send` by default. `MapPool` never
+/// hands the same slot to two callers at once (the channel it round-trips through is the
+/// exclusion mechanism), so it's sound to mark a pooled map `Send`.
+struct PooledMap(MapnikMap);
+unsafe impl Send for PooledMap {}
+
+/// Hands out exclusive, pre-loaded [`MapnikMap`] instances rendering the same style, so `size`
+/// tiles can render concurrently instead of serializing on one map object.
+///
+/// Cheap to clone: every clone shares the same underlying channel, so a cache keyed by style can
+/// hand out clones of one pool without re-loading `size` maps per lookup.
+#[derive(Clone)]
+pub struct MapPool {
+    sender: SyncSender<PooledMap>,
+    receiver: Arc<Mutex<Receiver<PooledMap>>>,
+}
+
+impl std::fmt::Debug for MapPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MapPool").finish_non_exhaustive()
+    }
+}
+
+impl MapPool {
+    /// Pre-loads `size` independent `MapnikMap`s from the same `style` XML, each `width`x`height`.
+    pub fn new(
+        width: usize,
+        height: usize,
+        style: String,
+        size: usize,
+    ) -> Result<Self, MapnikError> {
+        let (sender, receiver) = sync_channel(size);
+        for _ in 0..size {
+            let map = MapnikMap::from_string(width, height, style.clone())?;
+            sender
+                .send(PooledMap(map))
+                .expect("pool channel was just created with capacity `size`");
+        }
+        Ok(Self {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        })
+    }
+
+    /// Borrows a map from the pool, blocking until one is free. The map is returned to the pool
+    /// when the returned [`MapGuard`] is dropped.
+    pub fn get(&self) -> MapGuard {
+        let map = self
+            .receiver
+            .lock()
+            .expect("pool receiver mutex is never poisoned")
+            .recv()
+            .expect("MapPool's own sender keeps the channel open for its lifetime");
+        MapGuard {
+            map: Some(map),
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+/// An exclusive borrow of a [`MapnikMap`] from a [`MapPool`], returned to the pool on drop.
+pub struct MapGuard {
+    map: Option<PooledMap>,
+    sender: SyncSender<PooledMap>,
+}
+
+impl std::ops::Deref for MapGuard {
+    type Target = MapnikMap;
+
+    fn deref(&self) -> &MapnikMap {
+        &self
+            .map
+            .as_ref()
+            .expect("map is only taken in Drop")
+            .0
+    }
+}
+
+impl Drop for MapGuard {
+    fn drop(&mut self) {
+        if let Some(map) = self.map.take() {
+            // The pool was created with capacity `size` and every guard returns its map
+            // exactly once, so this send can never block or fail.
+            let _ = self.sender.send(map);
+        }
+    }
+}
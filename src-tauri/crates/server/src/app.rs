@@ -1,10 +1,11 @@
 use crate::{
-    endpoints::{add_map, get_tile, preview},
+    endpoints::{add_map, get_tile, legend, legend_stops, point, preview, refresh, render_window, seed},
     state::State,
 };
 
 use http_types::headers::HeaderValue;
 use map_engine::vector::Vector;
+use tide::log::info;
 use tide::security::{CorsMiddleware, Origin};
 use tide::{Request, Response, Server, StatusCode};
 
@@ -24,20 +25,58 @@ pub async fn run(
 
     let mut app = create_app(&config).await;
     app.with(cors);
+
+    // The render pool's worker threads outlive this handler, so SIGINT would otherwise abort
+    // whatever tile each of them is mid-render on. Tell the pool to stop accepting new jobs and
+    // wait for the queued and in-progress ones to finish before the process actually exits; tide itself has no
+    // graceful-shutdown hook for `listen`, so this is as clean a stop as we can make it.
+    let render_pool = app.state().render_pool.clone();
+    ctrlc::set_handler(move || {
+        info!("received SIGINT; draining in-flight tile renders before shutting down");
+        render_pool.shutdown();
+        std::process::exit(0);
+    })
+    .expect("failed to install SIGINT handler");
+
     app.listen(format!("{}:{}", host, port)).await?;
 
     Ok(())
 }
 
 pub async fn create_app(conf_path: &str) -> Server<State> {
-    let state = State::from_file(conf_path).unwrap();
+    let preferred_encoding =
+        std::env::var("MAP_ENGINE_ENCODING").unwrap_or_else(|_| "gzip".to_string());
+    let cache_size_mb = std::env::var("MAP_ENGINE_CACHE_SIZE_MB")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(512);
+    let workers = std::env::var("MAP_ENGINE_WORKERS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+        });
+    let state = State::from_file(conf_path)
+        .unwrap()
+        .with_preferred_encoding(preferred_encoding)
+        .with_cache_size_mb(cache_size_mb)
+        .with_workers(workers);
     let mut app = tide::with_state(state);
 
     app.at("/favicon.ico").get(favicon);
     app.at("/:map_name").get(preview);
     app.at("/:map_name/").get(preview);
     app.at("/:map_name/:z/:x/:y").get(get_tile);
+    app.at("/:map_name/legend.png").get(legend);
+    app.at("/:map_name/legend.json").get(legend_stops);
     app.at("/map").post(add_map);
+    app.at("/refresh").post(refresh);
+    app.at("/:map_name/seed").post(seed);
+    app.at("/:map_name/render").post(render_window);
+    app.at("/:map_name/point").get(point);
+    app.at("/:map_name/point").post(point);
 
     app
 }
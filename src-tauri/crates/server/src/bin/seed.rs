@@ -0,0 +1,58 @@
+/*!Bake a registered map's tile pyramid into an MBTiles archive from the command line, for
+offline use. This is the CLI counterpart to the `POST /:map_name/seed` endpoint; both share
+[`map_engine_server::seed::seed_mbtiles`].
+
+```bash
+map-engine-seed --config config_file.json --map chile_optimised --min-zoom 0 --max-zoom 8 --output chile_optimised.mbtiles
+```
+*/
+use clap::Parser;
+use map_engine_server::seed::{seed_mbtiles, SeedRequest};
+use map_engine_server::state::State;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    /// Path to the server's config file, as used by `map-engine-server`.
+    #[clap(short, long)]
+    config: String,
+
+    /// Name of the map to seed, as registered in the config file.
+    #[clap(short, long)]
+    map: String,
+
+    #[clap(long)]
+    min_zoom: u32,
+
+    #[clap(long)]
+    max_zoom: u32,
+
+    /// `min_lon,min_lat,max_lon,max_lat`. Defaults to the map's own registered bounds.
+    #[clap(long, value_delimiter = ',')]
+    bbox: Option<Vec<f64>>,
+
+    /// Where to write the MBTiles archive.
+    #[clap(short, long)]
+    output: PathBuf,
+}
+
+fn main() -> tide::Result<()> {
+    pretty_env_logger::init();
+
+    let args = Args::parse();
+    let bbox = args.bbox.map(|b| {
+        <[f64; 4]>::try_from(b).expect("--bbox takes exactly 4 comma-separated values")
+    });
+
+    let state = State::from_file(&args.config)?;
+    let request = SeedRequest {
+        min_zoom: args.min_zoom,
+        max_zoom: args.max_zoom,
+        bbox,
+    };
+
+    seed_mbtiles(&state, &args.map, &request, &args.output)?;
+
+    Ok(())
+}
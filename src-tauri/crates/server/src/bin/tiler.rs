@@ -3,16 +3,22 @@ use map_engine::cmap::ColourDefinition;
 use map_engine::cmap::Composite;
 use map_engine::errors::MapEngineError;
 use map_engine::gdal::Dataset;
+use map_engine::gdal::ResampleAlg;
 use map_engine::raster::Raster;
 use map_engine::raster::RawPixels;
+use map_engine::raster::WarpTarget;
 use map_engine::tiles::Tile;
 use map_engine::windows::Window;
 use map_engine_server::mapsettings::MapSettings;
 use map_engine_server::style::Style;
+use rayon::prelude::*;
+use rusqlite::Connection;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
@@ -28,6 +34,201 @@ struct Args {
 
     #[clap(short, long)]
     output: String,
+
+    /// Resample algorithm used to warp a non-Web-Mercator source into each tile's grid:
+    /// `nearest` (the right choice for categorical/classified data), `bilinear` or `cubic`.
+    #[clap(long, default_value = "nearest")]
+    resampling: String,
+
+    /// Where to write rendered tiles: `directory` (a `z/x/y.png` tree, the original behavior) or
+    /// `mbtiles` (a single SQLite archive at `--output`).
+    #[clap(long, default_value = "directory")]
+    output_format: String,
+
+    /// Worker threads rendering tiles concurrently. Defaults to the available CPU parallelism.
+    #[clap(long)]
+    threads: Option<usize>,
+}
+
+/// Parse [`Args::resampling`] into the [`ResampleAlg`] `read_tile` expects.
+fn parse_resampling(resampling: &str) -> Result<ResampleAlg, MapEngineError> {
+    match resampling {
+        "nearest" => Ok(ResampleAlg::NearestNeighbour),
+        "bilinear" => Ok(ResampleAlg::Bilinear),
+        "cubic" => Ok(ResampleAlg::Cubic),
+        other => Err(MapEngineError::Msg(format!(
+            "unsupported --resampling '{}': expected nearest, bilinear or cubic",
+            other
+        ))),
+    }
+}
+
+/// Where [`main`] sends rendered tile bytes. See [`DirectorySink`] and [`MbtilesSink`].
+enum OutputFormat {
+    Directory,
+    Mbtiles,
+}
+
+fn parse_output_format(output_format: &str) -> Result<OutputFormat, MapEngineError> {
+    match output_format {
+        "directory" => Ok(OutputFormat::Directory),
+        "mbtiles" => Ok(OutputFormat::Mbtiles),
+        other => Err(MapEngineError::Msg(format!(
+            "unsupported --output-format '{}': expected directory or mbtiles",
+            other
+        ))),
+    }
+}
+
+/// A destination for rendered tile bytes, so `main`'s render loop doesn't need to know whether
+/// it's writing a `z/x/y.png` tree or a single MBTiles archive.
+trait TileSink {
+    /// Whether this sink wants XYZ rows (a directory tree) or TMS rows (MBTiles) in its tile
+    /// overlay, so `generate_leaflet` can match it.
+    fn uses_tms(&self) -> bool;
+
+    /// Called once per zoom level before its tiles are written, so a transactional sink (MBTiles)
+    /// can batch the whole level into one transaction.
+    fn begin_zoom(&mut self, _z: u32) -> Result<(), MapEngineError> {
+        Ok(())
+    }
+
+    fn write_tile(&mut self, z: u32, x: u32, y: u32, png_data: &[u8]) -> Result<(), MapEngineError>;
+
+    fn end_zoom(&mut self, _z: u32) -> Result<(), MapEngineError> {
+        Ok(())
+    }
+
+    /// Called once after every tile has been written, to flush any archive-level metadata.
+    fn finish(
+        &mut self,
+        _map: &MapSettings,
+        _title: &str,
+        _min_zoom: u32,
+        _max_zoom: u32,
+    ) -> Result<(), MapEngineError> {
+        Ok(())
+    }
+}
+
+/// Writes tiles as a `{output}/{z}/{x}/{y}.png` tree, the tool's original behavior.
+struct DirectorySink {
+    root: PathBuf,
+}
+
+impl DirectorySink {
+    fn new(root: PathBuf) -> Result<Self, MapEngineError> {
+        if !Path::exists(&root) {
+            fs::create_dir_all(&root)?;
+        }
+        Ok(DirectorySink { root })
+    }
+}
+
+impl TileSink for DirectorySink {
+    fn uses_tms(&self) -> bool {
+        false
+    }
+
+    fn write_tile(&mut self, z: u32, x: u32, y: u32, png_data: &[u8]) -> Result<(), MapEngineError> {
+        let dir = self.root.join(z.to_string()).join(x.to_string());
+        if !Path::exists(&dir) {
+            fs::create_dir_all(&dir)?;
+        }
+        let mut file = File::create(dir.join(format!("{}.png", y)))?;
+        file.write_all(png_data)?;
+        Ok(())
+    }
+}
+
+/// Writes tiles into a single MBTiles SQLite archive: a `tiles(zoom_level, tile_column,
+/// tile_row, tile_data)` table plus a `metadata` table, both per the
+/// [MBTiles spec](https://github.com/mapbox/mbtiles-spec).
+struct MbtilesSink {
+    conn: Connection,
+}
+
+impl MbtilesSink {
+    fn new(path: &Path) -> Result<Self, MapEngineError> {
+        if Path::exists(path) {
+            fs::remove_file(path)?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| MapEngineError::Msg(format!("cannot create MBTiles archive: {}", e)))?;
+        conn.execute_batch(
+            "CREATE TABLE metadata (name TEXT, value TEXT);
+             CREATE TABLE tiles (
+                 zoom_level INTEGER,
+                 tile_column INTEGER,
+                 tile_row INTEGER,
+                 tile_data BLOB
+             );
+             CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);",
+        )
+        .map_err(|e| MapEngineError::Msg(format!("cannot create MBTiles tables: {}", e)))?;
+        Ok(MbtilesSink { conn })
+    }
+}
+
+impl TileSink for MbtilesSink {
+    fn uses_tms(&self) -> bool {
+        true
+    }
+
+    fn begin_zoom(&mut self, _z: u32) -> Result<(), MapEngineError> {
+        self.conn
+            .execute_batch("BEGIN TRANSACTION;")
+            .map_err(|e| MapEngineError::Msg(format!("cannot start MBTiles transaction: {}", e)))
+    }
+
+    fn write_tile(&mut self, z: u32, x: u32, y: u32, png_data: &[u8]) -> Result<(), MapEngineError> {
+        // MBTiles numbers rows TMS-style (origin at the bottom), while the render loop above
+        // keeps producing XYZ rows (origin at the top).
+        let tms_row = 2u32.pow(z) - 1 - y;
+        self.conn
+            .execute(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data)
+                 VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![z, x, tms_row, png_data],
+            )
+            .map_err(|e| MapEngineError::Msg(format!("cannot insert tile ({},{},{}): {}", z, x, y, e)))?;
+        Ok(())
+    }
+
+    fn end_zoom(&mut self, _z: u32) -> Result<(), MapEngineError> {
+        self.conn
+            .execute_batch("COMMIT;")
+            .map_err(|e| MapEngineError::Msg(format!("cannot commit MBTiles transaction: {}", e)))
+    }
+
+    fn finish(
+        &mut self,
+        map: &MapSettings,
+        title: &str,
+        min_zoom: u32,
+        max_zoom: u32,
+    ) -> Result<(), MapEngineError> {
+        let bounds = map.bounds.unwrap_or([-180.0, -85.0511, 180.0, 85.0511]);
+        let metadata = [
+            ("name".to_string(), title.to_string()),
+            (
+                "bounds".to_string(),
+                format!("{},{},{},{}", bounds[0], bounds[1], bounds[2], bounds[3]),
+            ),
+            ("minzoom".to_string(), min_zoom.to_string()),
+            ("maxzoom".to_string(), max_zoom.to_string()),
+            ("format".to_string(), "png".to_string()),
+        ];
+        for (name, value) in metadata {
+            self.conn
+                .execute(
+                    "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+                    rusqlite::params![name, value],
+                )
+                .map_err(|e| MapEngineError::Msg(format!("cannot write MBTiles metadata: {}", e)))?;
+        }
+        Ok(())
+    }
 }
 
 fn main() -> Result<(), MapEngineError> {
@@ -52,22 +253,19 @@ fn main() -> Result<(), MapEngineError> {
         .collect();
 
     println!("no data values: {:?}", no_data_values);
-    let raster = Raster::from_src(path.to_path_buf(), &src)?;
+    let resample_alg = parse_resampling(&args.resampling)?;
+    let raster = Raster::from_src(path.to_path_buf(), &src)?
+        .with_warp_target(WarpTarget::default().with_resample_alg(resample_alg));
     let spatial_ref = raster.spatial_ref()?;
-    let epsg = spatial_ref.auth_code()?;
-    if epsg != 4326 {
-        println!("only support epsg:4326 spatial ref!");
-        return Err(MapEngineError::Msg(
-            "only support epsg:4326 spatial ref!".into(),
-        ));
-    }
 
-    // bounds
+    // bounds, in the source SRS
     let geo = raster.geo();
-    let minx = geo.geo[2];
-    let maxx = geo.geo[2] + raster_w as f64 * geo.geo[0];
-    let maxy = geo.geo[5];
-    let miny = geo.geo[5] + raster_h as f64 * geo.geo[4];
+
+    // Reproject the raster's own corners through source->EPSG:4326 so the tile range below (and
+    // `MapSettings.bounds`, which the Leaflet overlay's `fitBounds` reads) stay correct even when
+    // the source isn't already geographic. `read_tile` warps the pixels themselves into each
+    // tile's Web Mercator grid (see `Raster::warp_target`).
+    let (minx, maxy, maxx, miny) = raster_win.bounds_lat_long(&raster.spatial_info(), geo);
 
     let map = MapSettings {
         extent: Some(raster_win),
@@ -91,16 +289,32 @@ fn main() -> Result<(), MapEngineError> {
             bands: Some([1, 2, 3].to_vec()),
             vmax: Some(0.0),
             vmin: Some(255.0),
+            interpolation: None,
         }),
         geo_type: "raster".into(),
+        geojson: None,
+        csv: None,
+        dimensions: None,
+        target_srs: None,
+        resampling: None,
+        classification: None,
+        stretch_percentiles: None,
+        jpeg_background: None,
     };
     println!("Processing {}\n", args.input);
 
-    let output_dir = PathBuf::from(args.output);
-    if !Path::exists(&output_dir) {
-        fs::create_dir_all(&output_dir)?;
-    }
+    let output_path = PathBuf::from(&args.output);
+    let title: String = path.to_path_buf().file_stem().unwrap().to_str().unwrap().into();
+    let mut sink: Box<dyn TileSink> = match parse_output_format(&args.output_format)? {
+        OutputFormat::Directory => Box::new(DirectorySink::new(output_path.clone())?),
+        OutputFormat::Mbtiles => Box::new(MbtilesSink::new(&output_path)?),
+    };
+
     let style_gradient = map.to_composite();
+
+    // Enumerate every (z, x, y) up front, dropping tiles that don't intersect the raster before
+    // they're ever handed to a worker.
+    let mut work: Vec<(u32, Vec<(u32, u32)>)> = Vec::new();
     for z in args.min_zoom..=args.max_zoom {
         let (tile_minx, tile_miny) = lon_lat_to_tile(minx, maxy, z);
         let (tile_maxx, tile_maxy) = lon_lat_to_tile(maxx, miny, z);
@@ -109,33 +323,73 @@ fn main() -> Result<(), MapEngineError> {
             z, tile_minx, tile_maxx, tile_miny, tile_maxy,
         );
 
+        let mut xys = Vec::new();
         for x in tile_minx..=tile_maxx {
-            let dir = output_dir.join(z.to_string()).join(x.to_string());
-            if !Path::exists(&dir) {
-                println!("create dir: {}", dir.display());
-                fs::create_dir_all(&dir)?;
-            }
             for y in tile_miny..=tile_maxy {
-                tile(
-                    &map,
-                    &raster,
-                    &style_gradient,
-                    z,
-                    x,
-                    y,
-                    &dir.join(format!("{}.png", y)),
-                )?;
+                let mut t = Tile::new(x, y, z);
+                t.set_extension("png").unwrap();
+                if raster.intersects(&t)? {
+                    xys.push((x, y));
+                }
             }
         }
+        work.push((z, xys));
+    }
+    let total: usize = work.iter().map(|(_, xys)| xys.len()).sum();
+
+    let thread_count = args
+        .threads
+        .or_else(|| std::thread::available_parallelism().map(|n| n.get()).ok())
+        .unwrap_or(1);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count)
+        .build()
+        .map_err(|e| MapEngineError::Msg(format!("cannot build thread pool: {}", e)))?;
+    let rendered = AtomicUsize::new(0);
+
+    // Each worker clones its own `Raster` (cheap: it's metadata, not an open `Dataset`) and opens
+    // its own `Dataset` inside `read_tile`, since GDAL's `Dataset` isn't `Sync`. Results stream
+    // back over a channel to this thread, which owns the sink, so directory creation and MBTiles
+    // inserts stay race-free.
+    for (z, xys) in work {
+        sink.begin_zoom(z)?;
+
+        let (tx, rx) = mpsc::channel::<(u32, u32, Vec<u8>)>();
+        pool.install(|| {
+            xys.par_iter().for_each_with(tx, |tx, &(x, y)| {
+                let raster = raster.clone();
+                match tile(&map, &raster, &style_gradient, resample_alg, z, x, y) {
+                    Ok(Some(png_data)) => {
+                        let n = rendered.fetch_add(1, Ordering::Relaxed) + 1;
+                        println!("rendered {}/{}", n, total);
+                        let _ = tx.send((x, y, png_data));
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("error rendering tile {}/{}/{}: {:?}", z, x, y, e),
+                }
+            });
+        });
+
+        for (x, y, png_data) in rx {
+            sink.write_tile(z, x, y, &png_data)?;
+        }
+        sink.end_zoom(z)?;
     }
 
+    sink.finish(&map, &title, args.min_zoom, args.max_zoom)?;
+
+    let html_path = match output_path.extension() {
+        Some(_) => output_path.with_extension("html"),
+        None => output_path.join("map.html"),
+    };
     generate_leaflet(
-        path.to_path_buf().file_stem().unwrap().to_str().unwrap().into(),
+        title,
         map.bounds.unwrap().into(),
         args.min_zoom,
         args.max_zoom,
         (args.min_zoom + args.max_zoom) / 2,
-        &output_dir.join("map.html"),
+        sink.uses_tms(),
+        &html_path,
     );
     Ok(())
 }
@@ -164,21 +418,24 @@ pub fn lon_lat_to_tile(lon: f64, lat: f64, zoom: u32) -> (u32, u32) {
     (xtile, ytile)
 }
 
+/// Render one tile, skipping ones the raster doesn't intersect. The caller hands the encoded PNG
+/// bytes to whichever [`TileSink`] it's using instead of writing a file itself, so the same
+/// render path feeds a directory tree or an MBTiles archive.
 fn tile(
     map: &MapSettings,
     raster: &Raster,
     style_gradient: &Composite,
+    resample_alg: ResampleAlg,
     z: u32,
     x: u32,
     y: u32,
-    output: &PathBuf,
-) -> Result<(), MapEngineError> {
+) -> Result<Option<Vec<u8>>, MapEngineError> {
     let mut tile = Tile::new(x, y, z);
     tile.set_extension("png").unwrap();
 
     if !raster.intersects(&tile)? {
         println!("{:?} does not intersect, Returning empty", tile);
-        return Ok(());
+        return Ok(None);
     }
 
     let bands = map.get_bands();
@@ -188,14 +445,10 @@ fn tile(
         .map(|v| no_data_value[*v as usize - 1])
         .collect();
 
-    let arr: RawPixels<f64> = raster.read_tile(&tile, Some(bands), None)?;
+    let arr: RawPixels<f64> = raster.read_tile(&tile, Some(bands), Some(resample_alg))?;
     let styled = arr.style(style_gradient.clone(), style_no_data_value)?;
 
-    let png_data = styled.into_png()?;
-    let mut file = File::create(&output)?;
-    file.write_all(&png_data[..])?;
-
-    Ok(())
+    Ok(Some(styled.into_png()?))
 }
 
 fn generate_leaflet(
@@ -204,6 +457,7 @@ fn generate_leaflet(
     min_zoom: u32,
     max_zoom: u32,
     begin_zoom: u32,
+    tms: bool,
     output_path: &PathBuf,
 ) {
     let west = bounds[0];
@@ -269,7 +523,7 @@ fn generate_leaflet(
         var white = L.tileLayer("data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAQAAAAEAAQMAAABmvDolAAAAA1BMVEX///+nxBvIAAAAH0lEQVQYGe3BAQ0AAADCIPunfg43YAAAAAAAAAAA5wIhAAAB9aK9BAAAAABJRU5ErkJggg==");
 
         // Overlay layers (TMS)
-        var lyr = L.tileLayer('./{{z}}/{{x}}/{{y}}.png', {{tms: false, opacity: 0.7, attribution: "SenseTime"}});
+        var lyr = L.tileLayer('./{{z}}/{{x}}/{{y}}.png', {{tms: {}, opacity: 0.7, attribution: "SenseTime"}});
 
         // Map
         var map = L.map('map', {{
@@ -319,7 +573,7 @@ fn generate_leaflet(
 
         </body>
         </html>"#,
-        title, center_lon, center_lat, begin_zoom, min_zoom, max_zoom, title, south, east, north, west
+        title, tms, center_lon, center_lat, begin_zoom, min_zoom, max_zoom, title, south, east, north, west
     );
     let mut html = File::create(&output_path).unwrap();
     writeln!(html, "{}", s).unwrap();
@@ -10,6 +10,7 @@ pub async fn add_map(mut req: Request<State>) -> tide::Result<impl Into<Response
     let map: MapSettings = match map_setting.geo_type.as_str() {
         "vector" =>  req.state().add_map_vector(map_setting)?,
         "raster" =>  req.state().add_map(map_setting)?,
+        "geojson" | "csv" => req.state().add_map_inline(map_setting)?,
         _ => return Ok(Response::builder(StatusCode::BadRequest).content_type(mime::PLAIN).body(String::from("invalid geo type")))
     };
 
@@ -18,4 +19,17 @@ pub async fn add_map(mut req: Request<State>) -> tide::Result<impl Into<Response
         .body(Body::from_json(&map)?);
 
     Ok(response)
+}
+
+/// Re-read the config file the server was started with and live-swap in any added/removed/
+/// changed maps, without restarting.
+pub async fn refresh(req: Request<State>) -> tide::Result<impl Into<Response>> {
+    match req.state().refresh() {
+        Ok(summary) => Ok(Response::builder(StatusCode::Ok)
+            .content_type(mime::JSON)
+            .body(Body::from_json(&summary)?)),
+        Err(e) => Ok(Response::builder(StatusCode::BadRequest)
+            .content_type(mime::PLAIN)
+            .body(e.to_string())),
+    }
 }
\ No newline at end of file
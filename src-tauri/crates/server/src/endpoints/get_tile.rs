@@ -1,13 +1,174 @@
-use crate::state::State;
-use map_engine::{png::EMPTY_PNG, raster::RawPixels, tiles::Tile};
+use crate::state::{RenderWindowRequest, State, TileCacheKey};
+use flate2::{write::GzEncoder, Compression};
+use map_engine::{
+    png::{EMPTY_JPEG, EMPTY_PNG, EMPTY_WEBP},
+    raster::{RawPixels, DEFAULT_JPEG_BACKGROUND},
+    tiles::Tile,
+};
 use std::convert::Into;
-use tide::{http::mime, log::info, log::debug, Request, Response, StatusCode};
+use std::io::Write;
+use tide::{http::mime, http::Mime, log::debug, log::info, Request, Response, StatusCode};
+
+/// JPEG quality (1-100) `get_tile` encodes at; there's no per-map setting for this yet.
+const JPEG_QUALITY: u8 = 85;
+/// WebP quality (0.0-100.0) `get_tile` encodes at; there's no per-map setting for this yet.
+const WEBP_QUALITY: f32 = 80.0;
+
+/// `Content-Encoding`s `get_tile` can compress a tile body into, picked from `State`'s
+/// `preferred_encoding` and the request's `Accept-Encoding` header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    fn from_config(s: &str) -> Self {
+        match s {
+            "gzip" => Encoding::Gzip,
+            "brotli" | "br" => Encoding::Brotli,
+            _ => Encoding::Identity,
+        }
+    }
+
+    fn header_name(self) -> &'static str {
+        match self {
+            Encoding::Identity => "identity",
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    /// Compress `body` with this encoding. A no-op for [`Encoding::Identity`].
+    fn compress(self, body: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Identity => body.to_vec(),
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body).expect("gzip encoding failed");
+                encoder.finish().expect("gzip encoding failed")
+            }
+            Encoding::Brotli => {
+                let mut out = Vec::new();
+                let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 5, 22);
+                writer.write_all(body).expect("brotli encoding failed");
+                drop(writer);
+                out
+            }
+        }
+    }
+}
+
+/// Negotiate the response `Content-Encoding` from the server's `preferred` encoding and the
+/// client's `Accept-Encoding` header: use `preferred` if the client advertises support for it,
+/// falling back to the other supported codec, or [`Encoding::Identity`] if neither is accepted.
+/// `preferred == Encoding::Identity` (an operator opting out of compression entirely) always wins,
+/// regardless of what the client's `Accept-Encoding` allows.
+fn negotiate_encoding(accept_encoding: Option<&str>, preferred: Encoding) -> Encoding {
+    if preferred == Encoding::Identity {
+        return Encoding::Identity;
+    }
+
+    let accept_encoding = match accept_encoding {
+        Some(value) => value,
+        None => return Encoding::Identity,
+    };
+    let accepts = |encoding: Encoding| accept_encoding.contains(encoding.header_name());
+
+    if accepts(preferred) {
+        return preferred;
+    }
+    for fallback in [Encoding::Gzip, Encoding::Brotli] {
+        if fallback != preferred && accepts(fallback) {
+            return fallback;
+        }
+    }
+    Encoding::Identity
+}
+
+/// Tile encodings `get_tile` can produce, chosen from the URL extension or, failing that, the
+/// request's `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TileFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+impl TileFormat {
+    fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "png" => Some(TileFormat::Png),
+            "jpg" | "jpeg" => Some(TileFormat::Jpeg),
+            "webp" => Some(TileFormat::WebP),
+            _ => None,
+        }
+    }
+
+    /// Picks the most specific format the client's `Accept` header names, defaulting to PNG
+    /// (the original, always-supported behaviour) when neither is present.
+    fn from_accept(accept: &str) -> Self {
+        if accept.contains("image/webp") {
+            TileFormat::WebP
+        } else if accept.contains("image/jpeg") {
+            TileFormat::Jpeg
+        } else {
+            TileFormat::Png
+        }
+    }
+
+    fn content_type(self) -> Mime {
+        match self {
+            TileFormat::Png => mime::PNG,
+            TileFormat::Jpeg => "image/jpeg".parse().expect("valid mime"),
+            TileFormat::WebP => "image/webp".parse().expect("valid mime"),
+        }
+    }
+
+    fn empty_tile(self) -> Vec<u8> {
+        match self {
+            TileFormat::Png => EMPTY_PNG.clone(),
+            TileFormat::Jpeg => EMPTY_JPEG.clone(),
+            TileFormat::WebP => EMPTY_WEBP.clone(),
+        }
+    }
+
+    fn as_extension(self) -> &'static str {
+        match self {
+            TileFormat::Png => "png",
+            TileFormat::Jpeg => "jpeg",
+            TileFormat::WebP => "webp",
+        }
+    }
+}
 
 /// Generate a tile given a XYZ URL.
 pub async fn get_tile(req: Request<State>) -> tide::Result<impl Into<Response>> {
-    let (map_name, z, x, y, ext) = get_params(&req).await?;
+    let (map_name, z, x, y, scale, ext) = get_params(&req).await?;
+    if scale != 1 {
+        // The raster pipeline always reads a fixed TILE_SIZE window, so it has no way to
+        // honour a `@2x`/`@3x` request yet; report this honestly instead of silently
+        // serving a 1x tile under a 2x URL.
+        return Ok(Response::builder(StatusCode::NotImplemented)
+            .body(format!("@{}x tiles are not yet supported for raster maps", scale)));
+    }
+    let format = match ext {
+        Some(ext) => match TileFormat::from_extension(ext) {
+            Some(format) => format,
+            None => {
+                return Ok(Response::builder(StatusCode::NotImplemented)
+                    .body(format!("unsupported tile extension: {}", ext)))
+            }
+        },
+        None => req
+            .header("Accept")
+            .map(|values| TileFormat::from_accept(&values.to_string()))
+            .unwrap_or(TileFormat::Png),
+    };
+
     let mut tile = Tile::new(x, y, z);
-    if let Err(e) = tile.set_extension(ext) {
+    if let Err(e) = tile.set_extension(format.as_extension()) {
         return Ok(Response::builder(StatusCode::NotImplemented).body(e.to_string()));
     };
     let req_map = req.state().get_map(map_name);
@@ -18,45 +179,153 @@ pub async fn get_tile(req: Request<State>) -> tide::Result<impl Into<Response>>
     // We already checked if the map exists, so it should be ok to unwrap
     let req_map = &req_map.unwrap();
 
+        let preferred_encoding = Encoding::from_config(&req.state().preferred_encoding);
+        let accept_encoding = req.header("Accept-Encoding").map(|v| v.to_string());
+        let encoding = negotiate_encoding(accept_encoding.as_deref(), preferred_encoding);
+
+        let cache_key = TileCacheKey::new(map_name, z, x, y, format.as_extension());
+        if let Some(cached) = req.state().tile_cache.write().get(&cache_key) {
+            return Ok(encoded_response(format, encoding, cached));
+        }
+
         let raster = req.state().get_raster(map_name).unwrap();
         let style_gradient = req.state().get_style(map_name).unwrap();
 
         if !raster.intersects(&tile)? {
             info!(
-                "{:?} does not intersect {}. Returning empty {}",
-                tile, map_name, ext
+                "{:?} does not intersect {}. Returning empty {:?}",
+                tile, map_name, format
             );
-            return Ok(Response::builder(StatusCode::Ok)
-                .content_type(mime::PNG)
-                .body(EMPTY_PNG.clone()));
+            return Ok(encoded_response(format, encoding, format.empty_tile()));
         }
 
-        info!("Processing {:?} ({:?}) for {:?}", tile, ext, map_name);
+        info!("Processing {:?} ({:?}) for {:?}", tile, format, map_name);
         debug!("map: {:?}", req_map);
         debug!("style: {:?}", style_gradient);
 
-        let bands = req_map.get_bands();
+        let bands: Vec<isize> = req_map.get_bands().clone();
         let no_data_value = req_map.get_no_data_values();
-        let style_no_data_value = bands
+        let style_no_data_value: Vec<f64> = bands
             .iter()
             .map(|v| no_data_value[*v as usize - 1])
             .collect();
+        let jpeg_background = req_map.jpeg_background.unwrap_or(DEFAULT_JPEG_BACKGROUND);
+
+        // Hand the actual GDAL read/Mapnik-style render off to `RenderPool`'s worker threads, so
+        // this and every other concurrently-arriving request don't all block the async-std
+        // runtime doing heavy, not-cheaply-parallel rendering work at once.
+        let body = req
+            .state()
+            .render_pool
+            .submit(Box::new(move || {
+                let arr: RawPixels<f64> = raster.read_tile(&tile, Some(&bands), None)?;
+                let styled = arr.style(style_gradient, style_no_data_value)?;
+                match format {
+                    TileFormat::Png => styled.into_png(),
+                    TileFormat::Jpeg => styled.into_jpeg(JPEG_QUALITY, jpeg_background),
+                    TileFormat::WebP => Ok(styled.into_webp(WEBP_QUALITY)),
+                }
+            }))
+            .await?;
+        req.state().tile_cache.write().insert(cache_key, body.clone());
+        Ok(encoded_response(format, encoding, body))
+}
+
+/// Build the final tile response, compressing `body` with `encoding` (a no-op for
+/// [`Encoding::Identity`]) and setting `Content-Encoding` to match.
+fn encoded_response(format: TileFormat, encoding: Encoding, body: Vec<u8>) -> Response {
+    let body = encoding.compress(&body);
+    let mut builder = Response::builder(StatusCode::Ok).content_type(format.content_type());
+    if encoding != Encoding::Identity {
+        builder = builder.header("Content-Encoding", encoding.header_name());
+    }
+    builder.body(body).build()
+}
 
-        let arr: RawPixels<f64> = raster.read_tile(&tile, Some(bands), None)?;
-        let styled = arr.style(style_gradient, style_no_data_value)?;
+/// Render a map over a caller-supplied `{bbox, srs, width, height}` window, for clients that need
+/// an arbitrary extent/resolution (e.g. a print/export view) rather than a fixed XYZ tile.
+pub async fn render_window(mut req: Request<State>) -> tide::Result<impl Into<Response>> {
+    let map_name = req.param("map_name")?.to_string();
+    let request: RenderWindowRequest = req.body_json().await?;
 
-        let response = Response::builder(StatusCode::Ok)
+    match req.state().render_window(&map_name, &request) {
+        Ok(png_data) => Ok(Response::builder(StatusCode::Ok)
             .content_type(mime::PNG)
-            .body(styled.into_png().expect("Could not create PNG"));
-        Ok(response)
+            .body(png_data)),
+        Err(e) => Ok(Response::builder(StatusCode::BadRequest).body(e.to_string())),
+    }
 }
 
-pub async fn get_params(req: &Request<State>) -> tide::Result<(&str, u32, u32, u32, &str)> {
+/// `ext` is `None` when the URL's `y` segment has no extension, so the caller should fall back to
+/// content negotiation via the `Accept` header. `scale` is the `@Nx` suffix (e.g. `2` for `@2x`),
+/// defaulting to `1` when absent.
+pub async fn get_params(
+    req: &Request<State>,
+) -> tide::Result<(&str, u32, u32, u32, u8, Option<&str>)> {
     let map_name = req.param("map_name")?;
     let z: u32 = req.param("z")?.parse()?;
     let x: u32 = req.param("x")?.parse()?;
-    let mut y_ext = req.param("y")?.split('.');
-    let y: u32 = y_ext.next().unwrap().parse()?;
-    let ext = y_ext.next().unwrap_or("png");
-    Ok((map_name, z, x, y, ext))
+
+    let y_param = req.param("y")?;
+    let (y_and_scale, ext) = match y_param.split_once('.') {
+        Some((a, b)) => (a, Some(b)),
+        None => (y_param, None),
+    };
+    let (y_str, scale) = match y_and_scale.split_once('@') {
+        Some((y_str, scale_str)) => {
+            let digits = scale_str.strip_suffix('x').ok_or_else(|| {
+                tide::Error::from_str(
+                    StatusCode::BadRequest,
+                    format!("invalid tile scale suffix: @{}", scale_str),
+                )
+            })?;
+            (y_str, digits.parse::<u8>()?)
+        }
+        None => (y_and_scale, 1),
+    };
+    let y: u32 = y_str.parse()?;
+
+    Ok((map_name, z, x, y, scale, ext))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_encoding_identity_preferred_always_wins() {
+        // Even when the client advertises support for gzip/brotli, an operator-configured
+        // `Encoding::Identity` preference must not be overridden by the fallback loop.
+        assert_eq!(
+            negotiate_encoding(Some("gzip, br"), Encoding::Identity),
+            Encoding::Identity
+        );
+        assert_eq!(negotiate_encoding(None, Encoding::Identity), Encoding::Identity);
+    }
+
+    #[test]
+    fn test_negotiate_encoding_uses_preferred_when_accepted() {
+        assert_eq!(
+            negotiate_encoding(Some("gzip, br"), Encoding::Brotli),
+            Encoding::Brotli
+        );
+    }
+
+    #[test]
+    fn test_negotiate_encoding_falls_back_when_preferred_not_accepted() {
+        assert_eq!(
+            negotiate_encoding(Some("br"), Encoding::Gzip),
+            Encoding::Brotli
+        );
+    }
+
+    #[test]
+    fn test_negotiate_encoding_no_accept_encoding_header_is_identity() {
+        assert_eq!(negotiate_encoding(None, Encoding::Gzip), Encoding::Identity);
+    }
+
+    #[test]
+    fn test_negotiate_encoding_unsupported_accept_encoding_is_identity() {
+        assert_eq!(negotiate_encoding(Some("deflate"), Encoding::Gzip), Encoding::Identity);
+    }
 }
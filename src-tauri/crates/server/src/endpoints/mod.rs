@@ -1,9 +1,17 @@
 pub use get_tile::get_tile;
 pub use get_tile::get_tile_vector;
+pub use get_tile::render_window;
+pub use preview::legend;
+pub use preview::legend_stops;
 pub use preview::preview;
 pub use add_map::add_map;
 pub use add_map::add_map_vector;
+pub use add_map::refresh;
+pub use seed::seed;
+pub use point::point;
 
 mod get_tile;
 mod preview;
 mod add_map;
+mod seed;
+mod point;
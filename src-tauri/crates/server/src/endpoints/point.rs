@@ -0,0 +1,101 @@
+use crate::state::State;
+use map_engine::{
+    errors::MapEngineError,
+    gdal::spatial_ref::{CoordTransform, SpatialRef},
+    raster::Raster,
+};
+use serde::Deserialize;
+use tide::{http::mime, http::Method, Request, Response, StatusCode};
+
+/// `?lon=&lat=` query parameters for a single-point [`point`] request.
+#[derive(Debug, Deserialize)]
+struct PointQuery {
+    lon: Option<f64>,
+    lat: Option<f64>,
+}
+
+/// Per-band values at one point; `None` (serialised as `null`) for a nodata band or a coordinate
+/// that falls outside the raster.
+type PointValues = Option<Vec<Option<f64>>>;
+
+/// Look up a map's raster band value(s) at one or more geographic coordinates: a single point via
+/// `GET ?lon=&lat=`, or a batch via a `POST` body of `[lon, lat]` pairs. Coordinates outside the
+/// raster extent, or that round to a negative/overflowing row/col, yield `null` for that point
+/// instead of failing the whole batch.
+pub async fn point(mut req: Request<State>) -> tide::Result<impl Into<Response>> {
+    let map_name = req.param("map_name")?.to_string();
+    if let Err(e) = req.state().get_map(&map_name) {
+        return Ok(Response::builder(StatusCode::NotFound).body(e.to_string()));
+    }
+    let req_map = req.state().get_map(&map_name)?;
+    let raster = req.state().get_raster(&map_name)?;
+
+    let coords: Vec<(f64, f64)> = if req.method() == Method::Post {
+        req.body_json().await?
+    } else {
+        let query: PointQuery = req.query()?;
+        match (query.lon, query.lat) {
+            (Some(lon), Some(lat)) => vec![(lon, lat)],
+            _ => {
+                return Ok(Response::builder(StatusCode::BadRequest).body(
+                    "expected ?lon=&lat= query parameters, or a POST body of [lon, lat] pairs",
+                ))
+            }
+        }
+    };
+
+    let bands = req_map.get_bands();
+    let no_data_value = req_map.get_no_data_values();
+
+    let values = coords
+        .into_iter()
+        .map(|(lon, lat)| sample_point(&raster, bands, no_data_value, lon, lat))
+        .collect::<Result<Vec<PointValues>, MapEngineError>>()?;
+
+    Ok(Response::builder(StatusCode::Ok)
+        .content_type(mime::JSON)
+        .body(serde_json::to_string(&values)?))
+}
+
+/// Sample `raster` at WGS84 `(lon, lat)`, mapping each band's nodata value (per `no_data_value`,
+/// indexed the same way as [`crate::mapsettings::MapSettings::get_no_data_values`]) to `None`.
+fn sample_point(
+    raster: &Raster,
+    bands: &[isize],
+    no_data_value: &[f64],
+    lon: f64,
+    lat: f64,
+) -> Result<PointValues, MapEngineError> {
+    // `raster.geo()`'s affine transform is expressed in the raster's native CRS, not WGS84 (see
+    // `Tile::to_window`/`State::render_window`, which reproject for the same reason), so `(lon,
+    // lat)` has to be transformed into that CRS before `rowcol` can make sense of it.
+    let wgs84 = SpatialRef::from_epsg(4326)?;
+    let raster_srs = raster.spatial_ref()?;
+    wgs84.set_axis_mapping_strategy(0);
+    raster_srs.set_axis_mapping_strategy(0);
+    let transform = CoordTransform::new(&wgs84, &raster_srs)?;
+
+    let mut xs = [lon];
+    let mut ys = [lat];
+    let mut zs = [0.0f64];
+    transform.transform_coords(&mut xs, &mut ys, &mut zs)?;
+
+    let (row, col) = raster.geo().rowcol(xs[0], ys[0])?;
+    let Some(raw) = raster.read_point(row, col, Some(bands))? else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        raw.into_iter()
+            .zip(bands)
+            .map(|(value, band)| {
+                let nodata = no_data_value[*band as usize - 1];
+                if value == nodata {
+                    None
+                } else {
+                    Some(value)
+                }
+            })
+            .collect(),
+    ))
+}
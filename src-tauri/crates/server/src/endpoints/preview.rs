@@ -1,6 +1,57 @@
 use crate::{mapsettings::MapSettings, state::State};
+use map_engine::{mercator::GlobalMercator, MAXZOOMLEVEL};
 use tide::{http::mime, Request, Response, StatusCode};
 
+/// Width/height (in pixels) of the PNG swatch strip served by [`legend`].
+const LEGEND_WIDTH: usize = 256;
+const LEGEND_HEIGHT: usize = 20;
+
+/// Assumed viewport size (in pixels) used to seed the preview map's initial zoom/center, since the
+/// actual browser window size isn't known until the page loads. The Leaflet map is fully
+/// pannable/zoomable afterwards; this only affects where it starts.
+const PREVIEW_VIEWPORT_WIDTH: f64 = 1024.0;
+const PREVIEW_VIEWPORT_HEIGHT: f64 = 768.0;
+
+/// `[lat_min, lon_min, lat_max, lon_max]` fallback used by [`gen_template`] for a map whose
+/// `bounds` is unset (e.g. [`crate::state::State::add_map_inline`], which has no source file to
+/// derive an extent from). Clamped to the standard Web Mercator latitude range rather than the
+/// full `[-90, 90]`, since [`GlobalMercator::fit_bounds`] projects through `tan`/`ln` and blows up
+/// to infinity at the poles.
+const WORLD_BOUNDS: [f64; 4] = [-85.05112878, -180.0, 85.05112878, 180.0];
+
+/// Render a map's colour map as a PNG legend, so a front-end always shows the styling actually
+/// applied to its tiles instead of a hard-coded one.
+pub async fn legend(req: Request<State>) -> tide::Result<impl Into<Response>> {
+    let map_name = req.param("map_name")?;
+    let style = req.state().get_style(map_name);
+    if let Err(e) = style {
+        return Ok(Response::builder(StatusCode::NotFound).body(e.to_string()));
+    };
+
+    let png_data = style
+        .unwrap()
+        .render_legend(LEGEND_WIDTH, LEGEND_HEIGHT)?
+        .into_png_sized()?;
+    Ok(Response::builder(StatusCode::Ok)
+        .content_type(mime::PNG)
+        .body(png_data))
+}
+
+/// Structured `{value, rgba}` legend stops for a map, for front-ends that want to draw their own
+/// (labelled) legend instead of the bitmap served by [`legend`].
+pub async fn legend_stops(req: Request<State>) -> tide::Result<impl Into<Response>> {
+    let map_name = req.param("map_name")?;
+    let style = req.state().get_style(map_name);
+    if let Err(e) = style {
+        return Ok(Response::builder(StatusCode::NotFound).body(e.to_string()));
+    };
+
+    let stops = style.unwrap().legend_stops(10);
+    Ok(Response::builder(StatusCode::Ok)
+        .content_type(mime::JSON)
+        .body(serde_json::to_string(&stops)?))
+}
+
 /// Generate a webmap preview.
 pub async fn preview(req: Request<State>) -> tide::Result<impl Into<Response>> {
     let map_name = req.param("map_name")?;
@@ -15,22 +66,30 @@ pub async fn preview(req: Request<State>) -> tide::Result<impl Into<Response>> {
 }
 
 async fn gen_template(req_map: &MapSettings, map_name: &str) -> tide::Result<String> {
-    let geo = req_map
-        .geotransform
-        .as_ref()
-        .expect("Map was not initialised");
-    let spatial_ref_code = req_map.spatial_ref_code.expect("Map was not initialised");
-    let ext = req_map.extent.expect("Map was not initialised");
+    // `add_map_inline` leaves `bounds` unset (no source file to derive an extent from); fall back
+    // to the whole (Web-Mercator-projectable) world rather than panicking on a map that otherwise
+    // renders tiles just fine.
+    let bounds = req_map.bounds.unwrap_or(WORLD_BOUNDS);
+    // `State::add_map`/`add_map_vector` store bounds as [lat_min, lon_min, lat_max, lon_max].
+    let [lat_min, lon_min, lat_max, lon_max] = bounds;
 
-    let (lat_max, long_min, lat_min, long_max) = ext.bounds_lat_long(spatial_ref_code, geo);
+    let mercator = GlobalMercator::default();
+    let (zoom, (center_lat, center_lon)) = mercator.fit_bounds(
+        [lon_min, lat_min, lon_max, lat_max],
+        PREVIEW_VIEWPORT_WIDTH,
+        PREVIEW_VIEWPORT_HEIGHT,
+        MAXZOOMLEVEL,
+    );
 
     let params = &[
-        ("m", map_name),
+        ("m", map_name.to_string()),
         (
             "bo",
-            &format!("[[{},{}],[{},{}]]", lat_max, long_min, lat_min, long_max),
+            format!("[[{},{}],[{},{}]]", lat_max, lon_min, lat_min, lon_max),
         ),
-        ("ba", "true"),
+        ("ba", "true".to_string()),
+        ("zoom", zoom.to_string()),
+        ("center", format!("[{},{}]", center_lat, center_lon)),
     ];
 
     let mut template = include_str!("../../template/preview.html").to_string();
@@ -0,0 +1,29 @@
+use crate::{seed::SeedRequest, state::State};
+use tempfile::NamedTempFile;
+use tide::{http::mime, Request, Response, StatusCode};
+
+/// Bake a registered map's tile pyramid into an MBTiles archive and return it as the response
+/// body, so a client can save it straight to disk for offline use.
+pub async fn seed(mut req: Request<State>) -> tide::Result<impl Into<Response>> {
+    let map_name = req.param("map_name")?.to_string();
+    let request: SeedRequest = req.body_json().await?;
+
+    // A unique per-request path, not one keyed only on `map_name`: two concurrent seed requests
+    // for the same map must not race on reading/writing/deleting the same file.
+    let output_file = NamedTempFile::new()?;
+    if let Err(e) =
+        crate::seed::seed_mbtiles(req.state(), &map_name, &request, output_file.path())
+    {
+        return Ok(Response::builder(StatusCode::InternalServerError).body(e.to_string()));
+    }
+
+    let mbtiles_data = std::fs::read(output_file.path())?;
+
+    Ok(Response::builder(StatusCode::Ok)
+        .content_type(mime::BYTE_STREAM)
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.mbtiles\"", map_name),
+        )
+        .body(mbtiles_data))
+}
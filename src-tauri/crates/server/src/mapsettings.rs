@@ -1,6 +1,38 @@
 use super::style::Style;
 use map_engine::{affine::GeoTransform, cmap::Composite, windows::Window, raster::SpatialInfo};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How [`crate::state::State::add_map_vector`] should turn a layer attribute into multiple
+/// styled [`map_engine::vector::Rule`]s instead of one flat fill colour.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorClassification {
+    /// Attribute/field name (as reported by GDAL/OGR) to classify features by.
+    pub field: String,
+    /// How to turn the field's values into classes.
+    pub method: VectorClassifyMethod,
+    /// Number of classes to split the field's numeric range into. Ignored by
+    /// [`VectorClassifyMethod::UniqueValues`], which gets one class per distinct value instead.
+    pub classes: usize,
+    /// Named colour ramp to sample class colours from (see [`map_engine::cmap`]'s gradients,
+    /// e.g. `"viridis"`/`"inferno"`), matching [`Style::name`]'s lookup. Unrecognised/unset names
+    /// fall back to `"viridis"`.
+    pub ramp: Option<String>,
+}
+
+/// Method [`State::add_map_vector`](crate::state::State::add_map_vector) uses to turn a layer
+/// attribute's values into classes for [`VectorClassification`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VectorClassifyMethod {
+    /// Split the field's `[min, max]` into `classes` equal-width ranges.
+    EqualInterval,
+    /// Break at the `classes`-quantiles of the field's values, so each class holds roughly the
+    /// same number of features.
+    Quantile,
+    /// One class per distinct value of the field, e.g. for a categorical land-use code.
+    UniqueValues,
+}
 
 /// Configurable setting for individual maps.
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
@@ -31,6 +63,45 @@ pub struct MapSettings {
     pub bounds: Option<[f64; 4]>,
     /// Has overview or not
     pub has_overview: Option<bool>,
+    /// Dispatches `add_map`: `"raster"`, `"vector"` (a GDAL/OGR file at `path`), `"geojson"` or
+    /// `"csv"` (an inline datasource, see [`MapSettings::geojson`]/[`MapSettings::csv`]).
+    pub geo_type: String,
+    /// Inline GeoJSON `FeatureCollection`, for `geo_type == "geojson"`. Rendered straight from
+    /// this value via Mapnik's GeoJSON input plugin, with no file written to disk.
+    pub geojson: Option<serde_json::Value>,
+    /// Inline CSV blob (header row plus a `wkt`/`lat,lon` geometry column Mapnik's CSV plugin
+    /// understands), for `geo_type == "csv"`. No file is written to disk.
+    pub csv: Option<String>,
+    /// Selects a single slice of a multidimensional source (NetCDF/HDF/Zarr), e.g.
+    /// `{"time": "2020-01"}`. A `"variable"` key picks which subdataset to open when `path`
+    /// itself isn't already a GDAL subdataset name; any other key is matched against GDAL's
+    /// per-band `NETCDF_DIM_*` metadata (see [`map_engine::raster::select_band_for_dimensions`])
+    /// to pin the served band to that coordinate value. `None`/empty for an ordinary 2D raster.
+    pub dimensions: Option<HashMap<String, String>>,
+    /// EPSG code to warp the served raster into before tiling, e.g. `3857` to guarantee
+    /// web-servable tiles out of a source in an arbitrary native projection. `None` serves the
+    /// source in its native CRS, unchanged.
+    pub target_srs: Option<i32>,
+    /// Resample algorithm used for that warp: `"nearest"`, `"bilinear"`, `"cubic"` or
+    /// `"average"`. Ignored (and implicitly `"nearest"`) when `target_srs` is unset. A categorical
+    /// single-band style (`ColourDefinition::Discrete`) must use `"nearest"`, since any other
+    /// algorithm would blend class codes into meaningless values.
+    pub resampling: Option<String>,
+    /// Classify a vector layer's attribute into multiple styled rules (a choropleth or
+    /// categorized map) instead of [`State::add_map_vector`](crate::state::State::add_map_vector)'s
+    /// default single random fill colour. `None` for a flat, unstyled layer.
+    pub classification: Option<VectorClassification>,
+    /// `(lower, upper)` cumulative-distribution fractions (e.g. `(0.02, 0.98)`) an auto-assigned
+    /// raster style stretches `vmin`/`vmax` to, instead of the raw per-band min/max, so a handful
+    /// of outlier pixels don't wash out the whole display. `None` defaults to `(0.02, 0.98)`; set
+    /// to `(0.0, 1.0)` to fall back to the raw min/max. Ignored once `style` already has explicit
+    /// colours/a name, i.e. once an auto-style would no longer be assigned.
+    pub stretch_percentiles: Option<(f64, f64)>,
+    /// Opaque `[r, g, b]` background a `.jpg`/`.jpeg` tile request flattens nodata/transparent
+    /// pixels onto, since JPEG has no alpha channel. `None` defaults to
+    /// [`map_engine::raster::DEFAULT_JPEG_BACKGROUND`] (white). Ignored for `.png`/`.webp`
+    /// requests, which preserve alpha instead.
+    pub jpeg_background: Option<[u8; 3]>,
 }
 
 impl MapSettings {
@@ -0,0 +1,165 @@
+//! Bake a registered map's tile pyramid into an [MBTiles](https://github.com/mapbox/mbtiles-spec)
+//! archive for offline use. Renders through the same per-tile path `get_tile` uses (`Raster` for
+//! raster maps, [`Vector::tile_with_options`] for vector maps), so a seeded archive matches what
+//! the live server would have returned for the same tile.
+use crate::{mapsettings::MapSettings, state::State};
+use map_engine::{
+    cmap::Composite,
+    errors::MapEngineError,
+    raster::{Raster, RawPixels},
+    tiles::Tile,
+    vector::{OutputFormat, Vector},
+};
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// A seeding job: a zoom range and an optional bbox restricting which tiles get rendered.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedRequest {
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+    /// `[min_lon, min_lat, max_lon, max_lat]`. Defaults to the map's own registered `bounds`.
+    pub bbox: Option<[f64; 4]>,
+}
+
+/// Render every tile `request` covers for `map_name` and write them into a fresh MBTiles archive
+/// at `output_path`, replacing any file already there.
+pub fn seed_mbtiles(
+    state: &State,
+    map_name: &str,
+    request: &SeedRequest,
+    output_path: &Path,
+) -> Result<(), MapEngineError> {
+    let map = state.get_map(map_name)?;
+    // `MapSettings.bounds` is stored as [lat_min, lon_min, lat_max, lon_max].
+    let bbox = request
+        .bbox
+        .or_else(|| map.bounds.map(|b| [b[1], b[0], b[3], b[2]]))
+        .ok_or_else(|| MapEngineError::Msg(format!("map `{}` has no bounds to seed from", map_name)))?;
+    let [min_lon, min_lat, max_lon, max_lat] = bbox;
+
+    let raster = state.get_raster(map_name).ok();
+    let vector = state.get_vector(map_name).ok();
+    let style = state.get_style(map_name).ok();
+    if raster.is_none() && vector.is_none() {
+        return Err(MapEngineError::Msg(format!(
+            "map `{}` is registered as neither a raster nor a vector",
+            map_name
+        )));
+    }
+
+    if Path::exists(output_path) {
+        fs::remove_file(output_path)?;
+    }
+    let conn = Connection::open(output_path)
+        .map_err(|e| MapEngineError::Msg(format!("cannot create MBTiles archive: {}", e)))?;
+    conn.execute_batch(
+        "CREATE TABLE metadata (name TEXT, value TEXT);
+         CREATE TABLE tiles (
+             zoom_level INTEGER,
+             tile_column INTEGER,
+             tile_row INTEGER,
+             tile_data BLOB
+         );
+         CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);",
+    )
+    .map_err(|e| MapEngineError::Msg(format!("cannot create MBTiles tables: {}", e)))?;
+
+    for z in request.min_zoom..=request.max_zoom {
+        // `from_lat_lng` finds the tile under a point; the bbox's NW/SE corners bound the tile
+        // range that covers it at this zoom.
+        let min_tile = Tile::from_lat_lng(min_lon, max_lat, z);
+        let max_tile = Tile::from_lat_lng(max_lon, min_lat, z);
+
+        conn.execute_batch("BEGIN TRANSACTION;")
+            .map_err(|e| MapEngineError::Msg(format!("cannot start MBTiles transaction: {}", e)))?;
+
+        for x in min_tile.x..=max_tile.x {
+            for y in min_tile.y..=max_tile.y {
+                let png_data = render_tile(
+                    &map,
+                    raster.as_ref(),
+                    vector.as_ref(),
+                    style.as_ref(),
+                    x,
+                    y,
+                    z,
+                )?;
+                let Some(png_data) = png_data else { continue };
+
+                // MBTiles numbers rows TMS-style (origin at the bottom); `Tile` is XYZ (origin
+                // at the top).
+                let tms_row = 2u32.pow(z) - 1 - y;
+                conn.execute(
+                    "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![z, x, tms_row, png_data],
+                )
+                .map_err(|e| {
+                    MapEngineError::Msg(format!("cannot insert tile ({},{},{}): {}", z, x, y, e))
+                })?;
+            }
+        }
+
+        conn.execute_batch("COMMIT;")
+            .map_err(|e| MapEngineError::Msg(format!("cannot commit MBTiles transaction: {}", e)))?;
+    }
+
+    let metadata = [
+        ("name".to_string(), map_name.to_string()),
+        (
+            "bounds".to_string(),
+            format!("{},{},{},{}", min_lon, min_lat, max_lon, max_lat),
+        ),
+        ("minzoom".to_string(), request.min_zoom.to_string()),
+        ("maxzoom".to_string(), request.max_zoom.to_string()),
+        ("format".to_string(), "png".to_string()),
+    ];
+    for (name, value) in metadata {
+        conn.execute(
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+            rusqlite::params![name, value],
+        )
+        .map_err(|e| MapEngineError::Msg(format!("cannot write MBTiles metadata: {}", e)))?;
+    }
+
+    Ok(())
+}
+
+/// Render one tile, preferring the map's vector layer (if registered) over its raster. Returns
+/// `None` for a raster tile the source doesn't intersect, so the caller can skip writing it.
+fn render_tile(
+    map: &MapSettings,
+    raster: Option<&Raster>,
+    vector: Option<&Vector>,
+    style: Option<&Composite>,
+    x: u32,
+    y: u32,
+    z: u32,
+) -> Result<Option<Vec<u8>>, MapEngineError> {
+    let mut tile = Tile::new(x, y, z);
+    tile.set_extension("png")?;
+
+    if let Some(vector) = vector {
+        return Ok(Some(vector.tile_with_options(&tile, OutputFormat::Png, 1.0)?));
+    }
+
+    let raster = raster.expect("checked by seed_mbtiles's raster/vector guard");
+    let style = style.expect("a registered raster always has a style in `State.styles`");
+    if !raster.intersects(&tile)? {
+        return Ok(None);
+    }
+
+    let bands = map.get_bands();
+    let no_data_value = map.get_no_data_values();
+    let style_no_data_value = bands
+        .iter()
+        .map(|v| no_data_value[*v as usize - 1])
+        .collect();
+
+    let arr: RawPixels<f64> = raster.read_tile(&tile, Some(bands), None)?;
+    let styled = arr.style(style.clone(), style_no_data_value)?;
+    Ok(Some(styled.into_png()?))
+}
@@ -1,23 +1,286 @@
-use crate::{mapsettings::MapSettings, style::Style};
-use log::debug;
+use crate::{
+    mapsettings::{MapSettings, VectorClassification, VectorClassifyMethod},
+    style::Style,
+};
+use log::{debug, warn};
 use map_engine::{
-    cmap::{ColourDefinition, Composite},
+    cmap::{classify_breaks, inferno, viridis, ClassifyMethod, ColourDefinition, Composite, GradientLinearRGBA},
+    colour::Colour,
     errors::MapEngineError,
     gdal::spatial_ref::{CoordTransform, SpatialRef},
+    gdal::raster::ResampleAlg,
     gdal::Dataset,
+    gdal::Layer as GdalLayer,
     gdal::LayerAccess,
-    raster::{Raster, SpatialInfo},
-    vector::{DataSource, Layer, Map, Parameter, Rule, StyleName, Vector, VectorSymbolizer, PolygonSymbolizer, LineSymbolizer},
+    raster::{Raster, RawPixels, SpatialInfo, WarpTarget},
+    vector::{DataSource, Layer, Map, MarkerSymbolizer, Parameter, Rule, StyleName, Vector, VectorStyle, VectorSymbolizer, PolygonSymbolizer, LineSymbolizer},
     windows::Window,
 };
-use std::collections::HashMap;
+use crossbeam_channel::Sender;
+use parking_lot::RwLock as CacheLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 use rand::Rng;
 
+/// Default budget for [`TileCache`], in megabytes, when [`State::with_cache_size_mb`] is never
+/// called.
+const DEFAULT_CACHE_SIZE_MB: usize = 512;
+
+/// Qualitative palette a default vector style picks one colour from, so layers published without
+/// an explicit style still get a readable, if arbitrary, fill.
+const DEFAULT_VECTOR_COLOURS: [&str; 10] = [
+    "#8e0152", "#c51b7d", "#de77ae", "#f1b6da", "#fde0ef", "#e6f5d0", "#b8e186", "#7fbc41",
+    "#4d9221", "#276419",
+];
+
+/// Pick a random colour from [`DEFAULT_VECTOR_COLOURS`].
+fn random_fill_colour() -> &'static str {
+    let mut rng = rand::thread_rng();
+    DEFAULT_VECTOR_COLOURS[rng.gen_range(0..DEFAULT_VECTOR_COLOURS.len())]
+}
+
+/// Default `(lower, upper)` percentiles [`State::percentile_min_max`] stretches an auto-assigned
+/// style's `vmin`/`vmax` to when [`MapSettings::stretch_percentiles`] is unset.
+const DEFAULT_STRETCH_PERCENTILES: (f64, f64) = (0.02, 0.98);
+
+/// A caller-supplied geographic window and output grid for [`State::render_window`], as an
+/// alternative to the fixed `TILE_SIZE` XYZ pyramid [`State::get_raster`] callers normally read.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RenderWindowRequest {
+    /// Requested extent as `[xmin, ymin, xmax, ymax]`, in `srs`.
+    pub bbox: [f64; 4],
+    /// EPSG code `bbox` is expressed in. Defaults to the raster's own native SRS, i.e. no
+    /// reprojection.
+    pub srs: Option<i32>,
+    /// Output grid width, in pixels.
+    pub width: usize,
+    /// Output grid height, in pixels.
+    pub height: usize,
+}
+
+/// Which maps changed in a [`State::refresh`], for the `/refresh` route to report back.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefreshSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub updated: Vec<String>,
+}
+
+/// Identifies one cached [`get_tile`](crate::endpoints::get_tile::get_tile) response in
+/// [`TileCache`]: the same `(x, y, z)` renders differently per map and per output format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TileCacheKey {
+    pub map_name: String,
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+    pub ext: String,
+}
+
+impl TileCacheKey {
+    pub fn new(map_name: &str, z: u32, x: u32, y: u32, ext: &str) -> Self {
+        TileCacheKey {
+            map_name: map_name.to_string(),
+            z,
+            x,
+            y,
+            ext: ext.to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    body: Vec<u8>,
+    /// [`TileCache::clock`] reading at the last hit, used to find the least-recently-used entry
+    /// to evict. Not a wall-clock timestamp.
+    last_used: u64,
+}
+
+/// Bounded, in-memory LRU cache of encoded tile bytes (the image-format-encoded body, before any
+/// HTTP `Content-Encoding` compression), keyed by [`TileCacheKey`].
+/// [`get_tile`](crate::endpoints::get_tile::get_tile) checks it before rendering and inserts
+/// after; [`State::add_map`]/[`State::add_map_vector`]/[`State::add_map_inline`] invalidate a
+/// map's entries whenever that source is replaced.
+#[derive(Debug)]
+pub struct TileCache {
+    entries: HashMap<TileCacheKey, CacheEntry>,
+    budget_bytes: usize,
+    clock: u64,
+}
+
+impl TileCache {
+    /// `budget_bytes == 0` disables caching: [`TileCache::insert`] becomes a no-op.
+    pub fn new(budget_bytes: usize) -> Self {
+        TileCache {
+            entries: HashMap::new(),
+            budget_bytes,
+            clock: 0,
+        }
+    }
+
+    /// Look up `key`, marking it most-recently-used on a hit.
+    pub fn get(&mut self, key: &TileCacheKey) -> Option<Vec<u8>> {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = clock;
+        Some(entry.body.clone())
+    }
+
+    /// Insert `body` under `key`, evicting least-recently-used entries until the cache is back
+    /// under budget. A no-op when caching is disabled (`budget_bytes == 0`).
+    pub fn insert(&mut self, key: TileCacheKey, body: Vec<u8>) {
+        if self.budget_bytes == 0 {
+            return;
+        }
+        self.clock += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                body,
+                last_used: self.clock,
+            },
+        );
+        self.evict();
+    }
+
+    /// Drop every cached tile for `map_name`, e.g. because the map was just replaced by
+    /// [`State::add_map`].
+    pub fn invalidate_map(&mut self, map_name: &str) {
+        self.entries.retain(|key, _| key.map_name != map_name);
+    }
+
+    /// Replace [`TileCache::budget_bytes`], dropping everything currently cached (its sizes were
+    /// only ever tracked against the old budget).
+    fn replace_budget(&mut self, budget_bytes: usize) {
+        self.entries.clear();
+        self.budget_bytes = budget_bytes;
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.entries.values().map(|entry| entry.body.len()).sum()
+    }
+
+    fn evict(&mut self) {
+        while self.size_bytes() > self.budget_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => {
+                    self.entries.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Size of [`RenderPool`]'s job queue before [`RenderPool::submit`] starts blocking the calling
+/// task instead of buffering another pending render.
+const DEFAULT_RENDER_QUEUE_LEN: usize = 64;
+
+/// Worker count [`RenderPool`] uses when [`State::with_workers`] is never called: one per
+/// available CPU, falling back to `4` if that can't be determined.
+fn default_render_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Heavy rendering work submitted to a [`RenderPool`]: read the raster and encode it, returning
+/// the final tile bytes.
+type RenderJob = Box<dyn FnOnce() -> Result<Vec<u8>, MapEngineError> + Send>;
+
+/// Fixed-size pool of worker threads rendering tiles off the tide/async-std runtime, so
+/// concurrently arriving requests don't each kick off their own simultaneous GDAL read/Mapnik
+/// render (neither is cheap to run many-at-once). Handlers call [`RenderPool::submit`] and
+/// `.await` the result; [`crate::app::run`] calls [`RenderPool::shutdown`] on `SIGINT` so
+/// in-flight renders finish instead of being aborted mid-tile.
+#[derive(Clone)]
+pub struct RenderPool {
+    sender: Sender<(RenderJob, Sender<Result<Vec<u8>, MapEngineError>>)>,
+    accepting: Arc<AtomicBool>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl RenderPool {
+    /// Spawn `workers` OS threads pulling jobs off a channel bounded to `queue_len`.
+    pub fn new(workers: usize, queue_len: usize) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded::<(
+            RenderJob,
+            Sender<Result<Vec<u8>, MapEngineError>>,
+        )>(queue_len);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            let in_flight = in_flight.clone();
+            std::thread::spawn(move || {
+                for (job, reply) in receiver {
+                    in_flight.fetch_add(1, Ordering::SeqCst);
+                    let _ = reply.send(job());
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                }
+            });
+        }
+
+        RenderPool {
+            sender,
+            accepting: Arc::new(AtomicBool::new(true)),
+            in_flight,
+        }
+    }
+
+    /// Submit `job` and asynchronously wait for a worker to run it, without blocking the
+    /// async-std runtime thread the caller is on. Fails immediately, without rendering, once
+    /// [`RenderPool::shutdown`] has been called.
+    pub async fn submit(&self, job: RenderJob) -> Result<Vec<u8>, MapEngineError> {
+        if !self.accepting.load(Ordering::SeqCst) {
+            return Err(MapEngineError::Msg("server is shutting down".into()));
+        }
+
+        let (reply_tx, reply_rx) = crossbeam_channel::bounded(1);
+        self.sender
+            .send((job, reply_tx))
+            .map_err(|_| MapEngineError::Msg("render pool has shut down".into()))?;
+
+        async_std::task::spawn_blocking(move || {
+            reply_rx.recv().unwrap_or_else(|_| {
+                Err(MapEngineError::Msg(
+                    "render worker dropped the reply channel".into(),
+                ))
+            })
+        })
+        .await
+    }
+
+    /// Stop accepting new jobs and block until every already-queued/in-progress render finishes,
+    /// so [`crate::app::run`]'s `SIGINT` handler can shut the listener down without aborting a
+    /// tile mid-render.
+    pub fn shutdown(&self) {
+        self.accepting.store(false, Ordering::SeqCst);
+        while self.sender.len() > 0 || self.in_flight.load(Ordering::SeqCst) > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    }
+}
+
+impl std::fmt::Debug for RenderPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderPool").finish_non_exhaustive()
+    }
+}
+
 /// The shared application state.
 #[derive(Clone, Debug)]
 pub struct State {
@@ -28,6 +291,28 @@ pub struct State {
 
     // mapnik maps
     pub vectors: Arc<RwLock<HashMap<String, Vector>>>,
+
+    /// Default `Content-Encoding` [`crate::endpoints::get_tile::get_tile`] negotiates with a
+    /// client that doesn't rule it out via `Accept-Encoding`: `"gzip"`, `"brotli"`/`"br"` or
+    /// `"identity"` to disable compression. Set from [`crate::app::run`]'s `MAP_ENGINE_ENCODING`
+    /// env var, defaulting to `"gzip"`.
+    pub preferred_encoding: String,
+
+    /// Bounded cache of already-rendered tile bytes, shared across requests. See [`TileCache`].
+    pub tile_cache: Arc<CacheLock<TileCache>>,
+
+    /// Worker pool [`crate::endpoints::get_tile::get_tile`] renders tiles on. See [`RenderPool`].
+    pub render_pool: RenderPool,
+
+    /// Config file [`State::from_file`] was loaded from, re-read by [`State::refresh`]. `None`
+    /// for a state built straight from settings (e.g. `State::from_file("")`'s empty server, or
+    /// tests), which has nothing on disk to refresh from.
+    pub conf_path: Option<String>,
+
+    /// Names of the maps currently sourced from `conf_path`, as of the last load/refresh.
+    /// [`State::refresh`] only ever adds/removes/updates entries in this set, leaving maps
+    /// published at runtime via `POST /map` (never part of the config file) untouched.
+    conf_map_names: Arc<RwLock<HashSet<String>>>,
 }
 
 impl State {
@@ -47,7 +332,69 @@ impl State {
 
         let settings: Vec<MapSettings> = serde_json::from_reader(reader)?;
 
-        State::init_state(settings)
+        let mut state = State::init_state(settings)?;
+        state.conf_path = Some(conf_path.to_string());
+        Ok(state)
+    }
+
+    /// Open `map.path`, resolving it down to a concrete 2D band if it's a multidimensional source
+    /// (NetCDF/HDF/Zarr via GDAL subdatasets): picks the subdataset named by
+    /// `map.dimensions["variable"]` (falling back to the sole subdataset if there's only one),
+    /// then, if `map.dimensions` selects a coordinate like `time`/`level`, pins `map.style` to the
+    /// single band [`select_band_for_dimensions`] resolves that slice to. `map.path` is rewritten
+    /// to whatever GDAL name was actually opened, so later reopens (tile reads, `add_map`'s own
+    /// `Raster::from_src`) see the same resolved source.
+    fn open_dataset(map: &mut MapSettings) -> Result<Dataset, MapEngineError> {
+        let mut src = Dataset::open(Path::new(&map.path))?;
+
+        let candidates = map_engine::raster::subdatasets(&src);
+        if !candidates.is_empty() {
+            let wanted = map.dimensions.as_ref().and_then(|d| d.get("variable"));
+            let chosen = match wanted {
+                Some(variable) => candidates
+                    .iter()
+                    .find(|s| s.name.contains(variable.as_str()) || s.description.contains(variable.as_str()))
+                    .ok_or_else(|| {
+                        MapEngineError::Msg(format!(
+                            "map `{}`: no subdataset matches dimensions.variable = `{}` (available: {:?})",
+                            map.name, variable, candidates
+                        ))
+                    })?,
+                None if candidates.len() == 1 => &candidates[0],
+                None => {
+                    return Err(MapEngineError::Msg(format!(
+                        "map `{}`: `{}` has multiple variables; set dimensions.variable to one of {:?}",
+                        map.name, map.path, candidates
+                    )))
+                }
+            };
+            map.path = chosen.name.clone();
+            src = Dataset::open(Path::new(&map.path))?;
+        }
+
+        if let Some(dimensions) = &map.dimensions {
+            let dimensions: HashMap<String, String> = dimensions
+                .iter()
+                .filter(|(k, _)| k.as_str() != "variable")
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            if let Some(band) = map_engine::raster::select_band_for_dimensions(&src, &dimensions)? {
+                // Pin the resolved band but otherwise leave colours/name unset, so the
+                // auto-viridis-with-computed-min/max assignment below still applies to it.
+                let mut style = map.style.clone().unwrap_or(Style {
+                    name: None,
+                    colours: None,
+                    vmin: None,
+                    vmax: None,
+                    bands: None,
+                    interpolation: None,
+                });
+                style.bands = Some(vec![band]);
+                map.style = Some(style);
+            }
+        }
+
+        Ok(src)
     }
 
     fn validate_no_data_values(src: &Dataset, map: &mut MapSettings) -> Result<(), MapEngineError> {
@@ -108,6 +455,161 @@ impl State {
         Ok(())
     }
 
+    /// Sanity-check `src`, freshly opened from `map.path`, before `init_state`/`add_map`/
+    /// `add_map_vector` register it: at least one raster band or one non-empty vector layer, and
+    /// that any band `map.style` requests is actually in range. Surfaces a descriptive
+    /// [`MapEngineError::Msg`] instead of the panic or opaque GDAL error a bad source would
+    /// otherwise cause deep inside rendering.
+    fn validate_source(src: &Dataset, map: &MapSettings) -> Result<(), MapEngineError> {
+        if map.geo_type == "vector" {
+            if src.layer_count() < 1 {
+                return Err(MapEngineError::Msg(format!(
+                    "map `{}`: `{}` has no vector layers",
+                    map.name, map.path
+                )));
+            }
+            let mut layer = src.layer(0)?;
+            if layer.feature_count() == 0 {
+                return Err(MapEngineError::Msg(format!(
+                    "map `{}`: `{}`'s layer 0 has no features",
+                    map.name, map.path
+                )));
+            }
+        } else {
+            if src.raster_count() < 1 {
+                return Err(MapEngineError::Msg(format!(
+                    "map `{}`: `{}` has no raster bands",
+                    map.name, map.path
+                )));
+            }
+            if let Some(bands) = map.style.as_ref().and_then(|s| s.bands.as_ref()) {
+                for band in bands {
+                    if *band < 1 || *band > src.raster_count() {
+                        return Err(MapEngineError::Msg(format!(
+                            "map `{}`: requested band {} but `{}` only has {} bands",
+                            map.name, band, map.path, src.raster_count()
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve `layer`'s spatial reference, falling back to (and warning about) EPSG:4326 when
+    /// the source has none, instead of the panic `.unwrap_or_else(|| ...unwrap())` would cause if
+    /// EPSG:4326 itself somehow failed to resolve.
+    fn vector_spatial_ref(
+        layer: &GdalLayer,
+        map: &MapSettings,
+    ) -> Result<SpatialRef, MapEngineError> {
+        match layer.spatial_ref() {
+            Some(spatial_ref) => Ok(spatial_ref),
+            None => {
+                warn!(
+                    "map `{}`: `{}` has no resolvable spatial reference; defaulting to EPSG:4326",
+                    map.name, map.path
+                );
+                Ok(SpatialRef::from_epsg(4326)?)
+            }
+        }
+    }
+
+    /// Resolve `raster`'s spatial reference, falling back to (and warning about) EPSG:4326 when
+    /// the source has none, instead of propagating an opaque "Unknow spatial ref" error.
+    fn raster_spatial_ref(raster: &Raster, map: &MapSettings) -> Result<SpatialRef, MapEngineError> {
+        match raster.spatial_ref() {
+            Ok(spatial_ref) => Ok(spatial_ref),
+            Err(_) => {
+                warn!(
+                    "map `{}`: `{}` has no resolvable spatial reference; defaulting to EPSG:4326",
+                    map.name, map.path
+                );
+                Ok(SpatialRef::from_epsg(4326)?)
+            }
+        }
+    }
+
+    /// Stretch `band`'s auto-style `vmin`/`vmax` to `percentiles` instead of its raw `min_max`
+    /// (computed by the caller from [`Raster::min_max`]), so a handful of outlier pixels don't
+    /// wash out the whole display. Falls back to `min_max` unstretched if the percentiles land on
+    /// the same value (e.g. a near-constant band), which would otherwise collapse the style range.
+    fn percentile_min_max(
+        raster: &Raster,
+        band: isize,
+        min_max: (f64, f64),
+        percentiles: (f64, f64),
+    ) -> Result<(f64, f64), MapEngineError> {
+        let (low, high) = percentiles;
+        let histogram = raster.statistics(band as usize, None)?.histogram().clone();
+        let vmin = histogram.value_at_fraction(low);
+        let vmax = histogram.value_at_fraction(high);
+        if vmin == vmax {
+            return Ok(min_max);
+        }
+        Ok((vmin, vmax))
+    }
+
+    /// Parse [`MapSettings::resampling`] into the [`ResampleAlg`] [`WarpTarget::with_resample_alg`]
+    /// expects.
+    fn parse_resampling(resampling: &str) -> Result<ResampleAlg, MapEngineError> {
+        match resampling {
+            "nearest" => Ok(ResampleAlg::NearestNeighbour),
+            "bilinear" => Ok(ResampleAlg::Bilinear),
+            "cubic" => Ok(ResampleAlg::Cubic),
+            "average" => Ok(ResampleAlg::Average),
+            other => Err(MapEngineError::Msg(format!(
+                "unsupported resampling '{}': expected nearest, bilinear, cubic or average",
+                other
+            ))),
+        }
+    }
+
+    /// If `map.target_srs` is set, build the [`WarpTarget`] [`Raster::with_warp_target`] should
+    /// warp tile reads into, validating `map.resampling` against the style first: a categorical
+    /// (`ColourDefinition::Discrete`) single-band style must resample with `nearest`, since any
+    /// other algorithm would blend class codes into values that no longer mean anything.
+    fn warp_target(map: &MapSettings) -> Result<Option<WarpTarget>, MapEngineError> {
+        let Some(epsg_code) = map.target_srs else {
+            return Ok(None);
+        };
+
+        let is_categorical = matches!(
+            map.style.as_ref().and_then(|s| s.colours.as_ref()),
+            Some(ColourDefinition::Discrete(_))
+        );
+        let resample_alg = match &map.resampling {
+            Some(resampling) => {
+                let resample_alg = State::parse_resampling(resampling)?;
+                if is_categorical && resample_alg != ResampleAlg::NearestNeighbour {
+                    return Err(MapEngineError::Msg(format!(
+                        "map `{}`: a categorical (discrete colour) style must use resampling \
+                         'nearest', not '{}'",
+                        map.name, resampling
+                    )));
+                }
+                resample_alg
+            }
+            None => ResampleAlg::NearestNeighbour,
+        };
+
+        Ok(Some(
+            WarpTarget::new(SpatialInfo::from_spatial_ref(&SpatialRef::from_epsg(
+                epsg_code as u32,
+            )?))
+            .with_resample_alg(resample_alg),
+        ))
+    }
+
+    /// Whether `map` still needs an RGB/viridis style auto-assigned from `raster.min_max()`.
+    /// True both when `map.style` is unset and when [`State::open_dataset`] already pinned it to
+    /// a single band (for a `dimensions` slice) without picking any colours.
+    fn needs_auto_style(map: &MapSettings) -> bool {
+        map.style
+            .as_ref()
+            .map_or(true, |s| s.colours.is_none() && s.name.is_none())
+    }
+
     fn fill_style(map: &mut MapSettings) -> Result<(), MapEngineError> {
         let default_syle = Style::default();
         let default_bands = default_syle.bands.clone().unwrap();
@@ -124,14 +626,28 @@ impl State {
         Ok(())
     }
 
-    fn init_state(settings: Vec<MapSettings>) -> Result<Self, MapEngineError> {
+    /// Validate and open every raster in `settings`, building the `(maps, rasters, styles)`
+    /// [`State`] serves from a config file. Shared by [`State::init_state`] (the initial load)
+    /// and [`State::refresh`] (a live reload), so both apply exactly the same validation before
+    /// anything is registered.
+    #[allow(clippy::type_complexity)]
+    fn load_maps(
+        settings: Vec<MapSettings>,
+    ) -> Result<
+        (
+            HashMap<String, MapSettings>,
+            HashMap<String, Raster>,
+            HashMap<String, Composite>,
+        ),
+        MapEngineError,
+    > {
         let mut maps = HashMap::new();
         let mut rasters = HashMap::new();
         let mut styles = HashMap::new();
-        let vectors = HashMap::new();
         for mut map in settings.into_iter() {
+            let src = State::open_dataset(&mut map)?;
+            State::validate_source(&src, &map)?;
             let path = Path::new(&map.path);
-            let src = Dataset::open(path)?;
             if map.extent.is_none() {
                 let (raster_w, raster_h) = src.raster_size();
                 let raster_win = Window::new(0, 0, raster_w, raster_h);
@@ -139,13 +655,13 @@ impl State {
             };
             map.driver_name = Some(src.driver().short_name());
 
-            let raster = Raster::from_src(path.to_path_buf(), &src)?;
+            let mut raster = Raster::from_src(path.to_path_buf(), &src)?;
             println!("raster: {:?}", raster);
 
             let geo = raster.geo();
             map.geotransform = Some(geo.clone());
 
-            let spatial_ref = raster.spatial_ref()?;
+            let spatial_ref = State::raster_spatial_ref(&raster, &map)?;
             map.spatial_info = Some(SpatialInfo::from_spatial_ref(&spatial_ref));
             let spatial_units = spatial_ref.linear_units_name()?;
             map.spatial_units = Some(spatial_units);
@@ -167,9 +683,7 @@ impl State {
             let mut xs = [minx, maxx];
             let mut ys = [maxy, miny];
             let mut zs = [0.0f64; 2];
-            transform
-                .transform_coords(&mut xs, &mut ys, &mut zs)
-                .unwrap();
+            transform.transform_coords(&mut xs, &mut ys, &mut zs)?;
             debug!(
                 "after transform: {}, {}, {}, {}",
                 ys[1], xs[0], ys[0], xs[1]
@@ -178,29 +692,44 @@ impl State {
             map.bounds = Some([ys[1], xs[0], ys[0], xs[1]]);
 
             // calculate band min/max
-            if map.style.is_none() && raster.raster_count() >= 3 {
+            let percentiles = map.stretch_percentiles.unwrap_or(DEFAULT_STRETCH_PERCENTILES);
+            if State::needs_auto_style(&map) && raster.raster_count() >= 3 {
                 let min_max = raster.min_max();
+                let (r_min, r_max) = State::percentile_min_max(&raster, 1, min_max[0], percentiles)?;
+                let (g_min, g_max) = State::percentile_min_max(&raster, 2, min_max[1], percentiles)?;
+                let (b_min, b_max) = State::percentile_min_max(&raster, 3, min_max[2], percentiles)?;
                 map.style = Some(Style {
                     name: None,
                     colours: Some(ColourDefinition::RGB(
-                        [min_max[0].0, min_max[0].0, min_max[2].0],
-                        [min_max[0].1, min_max[1].1, min_max[2].1],
+                        [r_min, g_min, b_min],
+                        [r_max, g_max, b_max],
                     )),
                     bands: Some([1, 2, 3].to_vec()),
                     vmax: None,
                     vmin: None,
+                    interpolation: None,
                 });
                 println!("auto add map style: {:?}", map.style);
             }
 
-            if map.style.is_none() && raster.raster_count() < 3 {
+            if State::needs_auto_style(&map) && raster.raster_count() < 3 {
+                // A `dimensions` selector may already have pinned a single band.
+                let band = map.style.as_ref().and_then(|s| s.bands.clone()).unwrap_or(vec![1]);
                 let min_max = raster.min_max();
+                let band_index = band[0];
+                let (vmin, vmax) = State::percentile_min_max(
+                    &raster,
+                    band_index,
+                    min_max[(band_index - 1) as usize],
+                    percentiles,
+                )?;
                 map.style = Some(Style {
                     name: Some("viridis".into()),
                     colours: None,
-                    bands: Some([1].to_vec()),
-                    vmin: Some(min_max[0].0),
-                    vmax: Some(min_max[0].1),
+                    bands: Some(band),
+                    vmin: Some(vmin),
+                    vmax: Some(vmax),
+                    interpolation: None,
                 });
                 println!("auto add map style: {:?}", map.style);
             }
@@ -210,6 +739,10 @@ impl State {
 
             State::fill_style(&mut map)?;
 
+            if let Some(target) = State::warp_target(&map)? {
+                raster = raster.with_warp_target(target);
+            }
+
             let name = map.name.clone();
             let style_gradient = map.to_composite();
             styles.insert(name.clone(), style_gradient);
@@ -217,22 +750,58 @@ impl State {
             rasters.insert(name.clone(), raster);
         }
 
+        Ok((maps, rasters, styles))
+    }
+
+    fn init_state(settings: Vec<MapSettings>) -> Result<Self, MapEngineError> {
+        let (maps, rasters, styles) = State::load_maps(settings)?;
+        let conf_map_names = maps.keys().cloned().collect();
+
         Ok(State {
             maps: Arc::new(RwLock::new(maps)),
             rasters: Arc::new(RwLock::new(rasters)),
             styles: Arc::new(RwLock::new(styles)),
-            vectors: Arc::new(RwLock::new(vectors)),
+            vectors: Arc::new(RwLock::new(HashMap::new())),
+            preferred_encoding: "gzip".to_string(),
+            tile_cache: Arc::new(CacheLock::new(TileCache::new(
+                DEFAULT_CACHE_SIZE_MB * 1024 * 1024,
+            ))),
+            render_pool: RenderPool::new(default_render_workers(), DEFAULT_RENDER_QUEUE_LEN),
+            conf_path: None,
+            conf_map_names: Arc::new(RwLock::new(conf_map_names)),
         })
     }
 
+    /// Override [`State::preferred_encoding`], e.g. from the `MAP_ENGINE_ENCODING` env var.
+    pub fn with_preferred_encoding(mut self, encoding: String) -> Self {
+        self.preferred_encoding = encoding;
+        self
+    }
+
+    /// Override [`TileCache`]'s budget, e.g. from the `MAP_ENGINE_CACHE_SIZE_MB` env var.
+    /// `0` disables the cache.
+    pub fn with_cache_size_mb(self, cache_size_mb: usize) -> Self {
+        self.tile_cache
+            .write()
+            .replace_budget(cache_size_mb * 1024 * 1024);
+        self
+    }
+
+    /// Override [`RenderPool`]'s worker count, e.g. from the `MAP_ENGINE_WORKERS` env var.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.render_pool = RenderPool::new(workers, DEFAULT_RENDER_QUEUE_LEN);
+        self
+    }
+
     pub fn add_map(&self, map_setting: MapSettings) -> Result<MapSettings, MapEngineError> {
         let map: &mut MapSettings = &mut map_setting.clone();
         if map.name == "" {
             map.name = Uuid::new_v4().to_string()
         }
 
+        let src = State::open_dataset(map)?;
+        State::validate_source(&src, map)?;
         let path = Path::new(&map.path);
-        let src = Dataset::open(path)?;
         if map.extent.is_none() {
             let (raster_w, raster_h) = src.raster_size();
             let raster_win = Window::new(0, 0, raster_w, raster_h);
@@ -240,14 +809,14 @@ impl State {
         };
         map.driver_name = Some(src.driver().short_name());
 
-        let raster = Raster::from_src(path.to_path_buf(), &src)?;
+        let mut raster = Raster::from_src(path.to_path_buf(), &src)?;
         debug!("raster: {:?}", raster);
 
         let geo = raster.geo();
         map.geotransform = Some(geo.clone());
 
         debug!("get spatial ref");
-        let spatial_ref = raster.spatial_ref()?;
+        let spatial_ref = State::raster_spatial_ref(&raster, map)?;
         map.spatial_info = Some(raster.spatial_info());
 
         debug!("get spatial ref unit");
@@ -278,9 +847,7 @@ impl State {
         let mut xs = [minx, maxx];
         let mut ys = [maxy, miny];
         let mut zs = [0.0f64; 2];
-        transform
-            .transform_coords(&mut xs, &mut ys, &mut zs)
-            .unwrap();
+        transform.transform_coords(&mut xs, &mut ys, &mut zs)?;
         debug!(
             "after transform: {}, {}, {}, {}",
             ys[1], xs[0], ys[0], xs[1]
@@ -290,28 +857,43 @@ impl State {
         //map.bounds = Some(transform.transform_bounds(&[minx, miny, maxx, maxy], 21)?);
 
         // auto assign style if not specified
-        if map.style.is_none() && raster.raster_count() >= 3 {
+        let percentiles = map.stretch_percentiles.unwrap_or(DEFAULT_STRETCH_PERCENTILES);
+        if State::needs_auto_style(map) && raster.raster_count() >= 3 {
             let min_max = raster.min_max();
+            let (r_min, r_max) = State::percentile_min_max(&raster, 1, min_max[0], percentiles)?;
+            let (g_min, g_max) = State::percentile_min_max(&raster, 2, min_max[1], percentiles)?;
+            let (b_min, b_max) = State::percentile_min_max(&raster, 3, min_max[2], percentiles)?;
             map.style = Some(Style {
                 name: None,
                 colours: Some(ColourDefinition::RGB(
-                    [min_max[0].0, min_max[1].0, min_max[2].0],
-                    [min_max[0].1, min_max[1].1, min_max[2].1],
+                    [r_min, g_min, b_min],
+                    [r_max, g_max, b_max],
                 )),
                 bands: Some([1, 2, 3].to_vec()),
                 vmax: None,
                 vmin: None,
+                interpolation: None,
             });
             debug!("auto add map style: {:?}", map.style);
         }
-        if map.style.is_none() && raster.raster_count() < 3 {
+        if State::needs_auto_style(map) && raster.raster_count() < 3 {
+            // A `dimensions` selector may already have pinned a single band.
+            let band = map.style.as_ref().and_then(|s| s.bands.clone()).unwrap_or(vec![1]);
             let min_max = raster.min_max();
+            let band_index = band[0];
+            let (vmin, vmax) = State::percentile_min_max(
+                &raster,
+                band_index,
+                min_max[(band_index - 1) as usize],
+                percentiles,
+            )?;
             map.style = Some(Style {
                 name: Some("viridis".into()),
                 colours: None,
-                bands: Some([1].to_vec()),
-                vmin: Some(min_max[0].0),
-                vmax: Some(min_max[0].1),
+                bands: Some(band),
+                vmin: Some(vmin),
+                vmax: Some(vmax),
+                interpolation: None,
             });
             debug!("auto add map style: {:?}", map.style);
         }
@@ -329,21 +911,107 @@ impl State {
             .insert(name.clone(), style_gradient);
         self.maps.write().unwrap().insert(name.clone(), map.clone());
         self.rasters.write().unwrap().insert(name.clone(), raster);
+        self.tile_cache.write().invalidate_map(&name);
 
         Ok(map.clone())
     }
 
+    /// Resolve [`VectorClassification::ramp`] to a gradient function, matching [`Style`]'s
+    /// `name` lookup (unrecognised/unset names fall back to `"viridis"`).
+    fn vector_ramp(ramp: Option<&str>) -> &'static dyn Fn(f64, f64) -> GradientLinearRGBA {
+        match ramp {
+            Some("inferno") => &inferno,
+            _ => &viridis,
+        }
+    }
+
+    /// Build a data-driven [`VectorStyle`] for `classification`, scanning `layer`'s
+    /// [`VectorClassification::field`] to compute class values/breaks and sampling
+    /// `classification.classes` colours from [`VectorClassification::ramp`].
+    fn classify_vector_style(
+        layer: &GdalLayer,
+        classification: &VectorClassification,
+    ) -> Result<VectorStyle, MapEngineError> {
+        let field = &classification.field;
+        let cmap_f = State::vector_ramp(classification.ramp.as_deref());
+
+        if classification.method == VectorClassifyMethod::UniqueValues {
+            let mut values: Vec<String> = layer
+                .features()
+                .filter_map(|feature| feature.field_as_string_by_name(field).ok().flatten())
+                .collect();
+            values.sort();
+            values.dedup();
+            if values.is_empty() {
+                return Err(MapEngineError::Msg(format!(
+                    "classification field `{}` has no values to classify",
+                    field
+                )));
+            }
+
+            let grad = cmap_f(0.0, (values.len() - 1) as f64);
+            let categories = values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    let fill = Colour::from(grad.get(i as f64)).to_hex()[..7].to_string();
+                    (
+                        value,
+                        VectorSymbolizer::Polygon(PolygonSymbolizer {
+                            fill,
+                            fill_opacity: 0.7,
+                        }),
+                    )
+                })
+                .collect();
+            return Ok(VectorStyle::Categorized(categories));
+        }
+
+        let samples: Vec<f64> = layer
+            .features()
+            .filter_map(|feature| feature.field_as_double_by_name(field).ok().flatten())
+            .collect();
+        if samples.is_empty() {
+            return Err(MapEngineError::Msg(format!(
+                "classification field `{}` has no numeric values to classify",
+                field
+            )));
+        }
+
+        let method = match classification.method {
+            VectorClassifyMethod::EqualInterval => ClassifyMethod::EqualInterval,
+            VectorClassifyMethod::Quantile => ClassifyMethod::Quantile,
+            VectorClassifyMethod::UniqueValues => unreachable!("handled above"),
+        };
+        let breaks = classify_breaks(&samples, classification.classes.max(1), method);
+        let grad = cmap_f(0.0, (breaks.len() - 2) as f64);
+        let graduated = breaks[1..]
+            .iter()
+            .enumerate()
+            .map(|(i, &upper)| {
+                let fill = Colour::from(grad.get(i as f64)).to_hex()[..7].to_string();
+                (
+                    upper,
+                    VectorSymbolizer::Polygon(PolygonSymbolizer {
+                        fill,
+                        fill_opacity: 0.7,
+                    }),
+                )
+            })
+            .collect();
+        Ok(VectorStyle::Graduated(graduated))
+    }
+
     pub fn add_map_vector(&self, map_setting: MapSettings) -> Result<MapSettings, MapEngineError> {
         let map: &mut MapSettings = &mut map_setting.clone();
 
         // open data to fetch more info
         let path = Path::new(&map.path);
         let ds = Dataset::open(path)?;
+        State::validate_source(&ds, map)?;
 
         let layer = ds.layer(0)?;
-        let spatial_ref = layer
-            .spatial_ref()
-            .unwrap_or_else(|| SpatialRef::from_epsg(4326).unwrap());
+        let spatial_ref = State::vector_spatial_ref(&layer, map)?;
         map.spatial_info = Some(SpatialInfo::from_spatial_ref(&spatial_ref));
         let spatial_units = spatial_ref.linear_units_name()?;
         map.spatial_units = Some(spatial_units);
@@ -363,9 +1031,7 @@ impl State {
         let mut xs = [minx, maxx];
         let mut ys = [maxy, miny];
         let mut zs = [0.0f64; 2];
-        transform
-            .transform_coords(&mut xs, &mut ys, &mut zs)
-            .unwrap();
+        transform.transform_coords(&mut xs, &mut ys, &mut zs)?;
         // println!(
         //     "after transform: {}, {}, {}, {}",
         //     ys[1], xs[0], ys[0], xs[1]
@@ -375,25 +1041,26 @@ impl State {
         // map.bounds = Some(transform.transform_bounds(&[minx, miny, maxx, maxy], 21)?);
 
         // create map style
-        let colors = [
-            "#8e0152", "#c51b7d", "#de77ae", "#f1b6da", "#fde0ef", "#e6f5d0", "#b8e186", "#7fbc41",
-            "#4d9221", "#276419",
-        ];
-        let mut rng = rand::thread_rng();
-        let color_index = rng.gen_range(0..colors.len());
+        let fill_colour = random_fill_colour();
 
         let m = Map {
             srs: "epsg:3857".into(),
             style: vec![map_engine::vector::Style {
                 name: "My Style".into(),
+                opacity: None,
+                image_filters: None,
+                comp_op: None,
                 rule: vec![Rule {
+                    filter: None,
+                    min_scale_denominator: None,
+                    max_scale_denominator: None,
                     symbolizer: vec![
                         VectorSymbolizer::Polygon(PolygonSymbolizer {
-                            fill: colors[color_index].into(),
+                            fill: fill_colour.into(),
                             fill_opacity: 0.5,
                         }),
                         VectorSymbolizer::Line(LineSymbolizer {
-                            stroke: colors[color_index].into(),
+                            stroke: fill_colour.into(),
                             stroke_opacity: 1.0,
                             stroke_width: 1.0,
                         }),
@@ -427,18 +1094,131 @@ impl State {
 
         map.xml = Some(m.to_xml()?);
         println!("xml: {:?}", map.xml);
-        let v = Vector::from(map.xml.clone().unwrap())?;
+        let mut v = Vector::from(map.xml.clone().unwrap())?;
         map.name = v.name.clone();
 
+        if let Some(classification) = &map.classification {
+            let vector_style = State::classify_vector_style(&layer, classification)?;
+            v = v.with_vector_style("My Style".into(), classification.field.clone(), vector_style);
+        }
+
         self.maps
             .write()
             .unwrap()
             .insert(map.name.clone(), map.clone());
         self.vectors.write().unwrap().insert(map.name.clone(), v);
+        self.tile_cache.write().invalidate_map(&map.name);
 
         Ok(map.clone())
     }
 
+    /// Publish a layer from GeoJSON/CSV given directly in the request body (`geo_type ==
+    /// "geojson"`/`"csv"`), via Mapnik's own GeoJSON/CSV input plugins rather than a GDAL/OGR
+    /// file on disk. Unlike [`State::add_map_vector`], there is no source file to inspect for a
+    /// spatial reference or extent, so the layer is assumed to be WGS84 and `bounds` is left
+    /// unset; the caller can still request tiles straight away.
+    pub fn add_map_inline(&self, map_setting: MapSettings) -> Result<MapSettings, MapEngineError> {
+        let mut map = map_setting.clone();
+        if map.name == "" {
+            map.name = Uuid::new_v4().to_string()
+        }
+
+        let (datasource_type, inline) = match map.geo_type.as_str() {
+            "geojson" => {
+                let feature_collection = map.geojson.as_ref().ok_or_else(|| {
+                    MapEngineError::Msg(
+                        "geo_type \"geojson\" requires a `geojson` FeatureCollection".into(),
+                    )
+                })?;
+                ("geojson", serde_json::to_string(feature_collection)?)
+            }
+            "csv" => {
+                let csv = map.csv.clone().ok_or_else(|| {
+                    MapEngineError::Msg("geo_type \"csv\" requires a `csv` blob".into())
+                })?;
+                ("csv", csv)
+            }
+            other => {
+                return Err(MapEngineError::Msg(format!(
+                    "add_map_inline does not support geo_type `{}`",
+                    other
+                )))
+            }
+        };
+
+        // Default style covering every geometry type the inline data might hold, since we have
+        // no source file to inspect up front; symbolizers for the "wrong" geometry are simply
+        // ignored by Mapnik when it renders each feature.
+        let fill_colour = random_fill_colour();
+
+        let m = Map {
+            srs: "epsg:3857".into(),
+            style: vec![map_engine::vector::Style {
+                name: "My Style".into(),
+                opacity: None,
+                image_filters: None,
+                comp_op: None,
+                rule: vec![Rule {
+                    filter: None,
+                    min_scale_denominator: None,
+                    max_scale_denominator: None,
+                    symbolizer: vec![
+                        VectorSymbolizer::Polygon(PolygonSymbolizer {
+                            fill: fill_colour.into(),
+                            fill_opacity: 0.5,
+                        }),
+                        VectorSymbolizer::Line(LineSymbolizer {
+                            stroke: fill_colour.into(),
+                            stroke_opacity: 1.0,
+                            stroke_width: 1.0,
+                        }),
+                        VectorSymbolizer::Marker(MarkerSymbolizer {
+                            fill: fill_colour.into(),
+                            fill_opacity: 0.8,
+                            stroke: fill_colour.into(),
+                            stroke_opacity: 1.0,
+                            stroke_width: 1.0,
+                            width: 6.0,
+                            height: 6.0,
+                        }),
+                    ],
+                }],
+            }],
+            layer: vec![Layer {
+                name: None,
+                srs: Some("epsg:4326".into()),
+                style_name: StyleName {
+                    name: "My Style".into(),
+                },
+                data_source: DataSource {
+                    parameter: vec![
+                        Parameter {
+                            name: "type".into(),
+                            val: datasource_type.into(),
+                        },
+                        Parameter {
+                            name: "inline".into(),
+                            val: inline,
+                        },
+                    ],
+                },
+            }],
+        };
+
+        map.xml = Some(m.to_xml()?);
+        let v = Vector::from(map.xml.clone().unwrap())?;
+        map.name = v.name.clone();
+
+        self.maps
+            .write()
+            .unwrap()
+            .insert(map.name.clone(), map.clone());
+        self.vectors.write().unwrap().insert(map.name.clone(), v);
+        self.tile_cache.write().invalidate_map(&map.name);
+
+        Ok(map)
+    }
+
     pub fn get_map(&self, map_name: &str) -> Result<MapSettings, MapEngineError> {
         if self.maps.read().unwrap().contains_key(map_name) {
             Ok(self
@@ -506,6 +1286,115 @@ impl State {
             )));
         }
     }
+
+    /// Render `map_name` over an arbitrary geographic `request.bbox` at `request.width` x
+    /// `request.height`, instead of `get_tile`'s fixed `TILE_SIZE` XYZ grid. Lets a client fetch
+    /// any extent at any resolution without pre-registering it, e.g. for a print/export view.
+    pub fn render_window(
+        &self,
+        map_name: &str,
+        request: &RenderWindowRequest,
+    ) -> Result<Vec<u8>, MapEngineError> {
+        let map = self.get_map(map_name)?;
+        let raster = self.get_raster(map_name)?;
+        let style_gradient = self.get_style(map_name)?;
+
+        let [xmin, ymin, xmax, ymax] = request.bbox;
+        let (left, bottom, right, top) = match request.srs {
+            Some(epsg) => {
+                let source_spatial_ref = SpatialRef::from_epsg(epsg as u32)?;
+                let target_spatial_ref = raster.spatial_ref()?;
+                source_spatial_ref.set_axis_mapping_strategy(0);
+                target_spatial_ref.set_axis_mapping_strategy(0);
+                let transform = CoordTransform::new(&source_spatial_ref, &target_spatial_ref)?;
+
+                let mut xs = [xmin, xmax];
+                let mut ys = [ymax, ymin];
+                let mut zs = [0.0f64; 2];
+                transform.transform_coords(&mut xs, &mut ys, &mut zs)?;
+                (xs[0], ys[1], xs[1], ys[0])
+            }
+            None => (xmin, ymin, xmax, ymax),
+        };
+
+        let window = Window::from_bounds(left, bottom, right, top, raster.geo())?;
+
+        let bands = map.get_bands();
+        let no_data_value = map.get_no_data_values();
+        let style_no_data_value = bands
+            .iter()
+            .map(|v| no_data_value[*v as usize - 1])
+            .collect();
+
+        let arr: RawPixels<f64> =
+            raster.read_window(&window, request.width, request.height, Some(bands), None)?;
+        let styled = arr.style(style_gradient, style_no_data_value)?;
+        styled.into_png()
+    }
+
+    /// Re-read [`State::conf_path`] and atomically swap in whatever raster maps it now
+    /// describes. The new config is fully validated via [`State::load_maps`] before anything is
+    /// replaced, so a bad config leaves the currently-served maps untouched. Tile cache entries
+    /// for added/removed/updated maps are evicted; vector maps (published via `POST /map`, never
+    /// part of the config file) are left alone.
+    pub fn refresh(&self) -> Result<RefreshSummary, MapEngineError> {
+        let conf_path = self.conf_path.as_ref().ok_or_else(|| {
+            MapEngineError::Msg("this server was not started from a config file".into())
+        })?;
+
+        let file = File::open(Path::new(conf_path))?;
+        let settings: Vec<MapSettings> = serde_json::from_reader(BufReader::new(file))?;
+        let (mut new_maps, mut new_rasters, mut new_styles) = State::load_maps(settings)?;
+        let new_conf_names: HashSet<String> = new_maps.keys().cloned().collect();
+
+        let mut summary = RefreshSummary::default();
+        let old_conf_names = self.conf_map_names.read().unwrap().clone();
+        {
+            let old_maps = self.maps.read().unwrap();
+            for name in new_maps.keys() {
+                match old_maps.get(name) {
+                    Some(old_map) if old_map == &new_maps[name] => {}
+                    Some(_) => summary.updated.push(name.clone()),
+                    None => summary.added.push(name.clone()),
+                }
+            }
+        }
+        for name in &old_conf_names {
+            if !new_maps.contains_key(name) {
+                summary.removed.push(name.clone());
+            }
+        }
+
+        // Only touch maps sourced from the config file: runtime maps published via `POST /map`
+        // (vector, or raster added outside the config) are never in `new_maps` and must survive.
+        let mut maps = self.maps.write().unwrap();
+        let mut rasters = self.rasters.write().unwrap();
+        let mut styles = self.styles.write().unwrap();
+        for name in &old_conf_names {
+            if !new_maps.contains_key(name) {
+                maps.remove(name);
+                rasters.remove(name);
+                styles.remove(name);
+            }
+        }
+        for name in new_maps.keys().cloned().collect::<Vec<_>>() {
+            maps.insert(name.clone(), new_maps.remove(&name).unwrap());
+            rasters.insert(name.clone(), new_rasters.remove(&name).unwrap());
+            styles.insert(name.clone(), new_styles.remove(&name).unwrap());
+        }
+        drop(maps);
+        drop(rasters);
+        drop(styles);
+
+        *self.conf_map_names.write().unwrap() = new_conf_names;
+
+        let mut tile_cache = self.tile_cache.write();
+        for name in summary.added.iter().chain(&summary.removed).chain(&summary.updated) {
+            tile_cache.invalidate_map(name);
+        }
+
+        Ok(summary)
+    }
 }
 
 #[cfg(test)]
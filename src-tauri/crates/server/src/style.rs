@@ -1,5 +1,5 @@
 //! Types and helpers to style pixels.
-use map_engine::cmap::{inferno, viridis, ColourDefinition, Composite};
+use map_engine::cmap::{inferno, viridis, ColourDefinition, Composite, InterpolationSpace};
 use serde::{Deserialize, Serialize};
 use std::convert::From;
 
@@ -14,6 +14,10 @@ pub struct Style {
     pub vmax: Option<f64>,
     /// Band index
     pub bands: Option<Vec<isize>>,
+    /// Colour space to interpolate `Colours`/`ColoursAndBreaks` gradients in. Defaults to
+    /// [`InterpolationSpace::LinearRgb`]; ignored by other `colours` variants (see
+    /// [`Composite::with_interpolation_space`]).
+    pub interpolation: Option<InterpolationSpace>,
 }
 
 impl Default for Style {
@@ -27,6 +31,7 @@ impl Default for Style {
             vmin: Some(0.),
             vmax: Some(1.),
             bands: Some(vec![1]),
+            interpolation: None,
         }
     }
 }
@@ -35,6 +40,7 @@ impl From<&Style> for Composite {
     fn from(style: &Style) -> Composite {
         let vmin = style.vmin.expect("vmin not available in Style");
         let vmax = style.vmax.expect("vmax not available in Style");
+        let interpolation = style.interpolation.unwrap_or_default();
         match style {
             Style {
                 name: Some(name), ..
@@ -51,9 +57,11 @@ impl From<&Style> for Composite {
                     vmin,
                     vmax,
                     col_vec.clone().into_iter().map(Into::into).collect(),
-                ),
+                )
+                .with_interpolation_space(interpolation),
                 ColourDefinition::ColoursAndBreaks(cols_and_breaks) => {
                     Composite::new_gradient_with_breaks(cols_and_breaks.clone())
+                        .with_interpolation_space(interpolation)
                 }
                 ColourDefinition::RGB(vmin, vmax) => {
                     Composite::new_rgb(vmin.to_vec(), vmax.to_vec())
@@ -61,6 +69,10 @@ impl From<&Style> for Composite {
                 ColourDefinition::Discrete(col_vec) => {
                     Composite::new_discrete_palette(col_vec.clone())
                 }
+                ColourDefinition::Classified(breaks, colours) => {
+                    Composite::new_classified(breaks.clone(), colours.clone())
+                }
+                ColourDefinition::Hillshade(hillshade) => Composite::new_hillshade(*hillshade),
             },
             _ => Composite::new_gradient(vmin, vmax, &viridis),
         }
@@ -1,5 +1,5 @@
 use crate::{mapsettings::MapSettings, style::Style};
-use map_engine::cmap::{ColourDefinition, Composite, HandleGet};
+use map_engine::cmap::{ColourDefinition, Composite, HandleGet, InterpolationSpace};
 
 #[async_std::test]
 async fn test_make_gradient_from_map_settings_inferno() {
@@ -9,6 +9,7 @@ async fn test_make_gradient_from_map_settings_inferno() {
         vmax: Some(100.0),
         colours: None,
         bands: None,
+        interpolation: None,
     };
     let settings = MapSettings {
         style: Some(style),
@@ -36,6 +37,7 @@ async fn test_make_gradient_from_map_settings_viridis() {
         vmax: Some(100.0),
         colours: None,
         bands: None,
+        interpolation: None,
     };
     let settings = MapSettings {
         style: Some(style),
@@ -176,6 +178,51 @@ async fn test_make_gradient_from_map_settings_rgb() {
     );
 }
 
+#[async_std::test]
+async fn test_make_gradient_from_map_settings_interpolation() {
+    let colours = Some(ColourDefinition::Colours(vec![
+        (0.0, 0.0, 0.0, 1.0).into(),
+        (1.0, 1.0, 1.0, 1.0).into(),
+    ]));
+    let linear = Style {
+        vmin: Some(0.0),
+        vmax: Some(100.0),
+        colours: colours.clone(),
+        ..Default::default()
+    };
+    let rgb = Style {
+        vmin: Some(0.0),
+        vmax: Some(100.0),
+        colours,
+        interpolation: Some(InterpolationSpace::Rgb),
+        ..Default::default()
+    };
+    let linear_settings = MapSettings {
+        style: Some(linear),
+        ..Default::default()
+    };
+    let rgb_settings = MapSettings {
+        style: Some(rgb),
+        ..Default::default()
+    };
+
+    let linear_gradient: Composite = linear_settings
+        .style
+        .as_ref()
+        .expect("Style not availble in MapSettings")
+        .into();
+    let rgb_gradient: Composite = rgb_settings
+        .style
+        .as_ref()
+        .expect("Style not availble in MapSettings")
+        .into();
+
+    assert_ne!(
+        linear_gradient.get(&[50.0], None),
+        rgb_gradient.get(&[50.0], None)
+    );
+}
+
 #[async_std::test]
 async fn test_style_hierarchy() {
     let style = Style {